@@ -100,6 +100,7 @@ cp -r ./service-worker/pkg/* ./pkg
 cp -r ./content/pkg/* ./pkg
 cp ./run_service_worker.js ./pkg
 cp ./run_content.js ./pkg
+cp ./offscreen.html ./offscreen.js ./pkg
 cp ./manifest_v3_chrome.json ./pkg/manifest_v3_chrome.json
 cp ./manifest_v3_firefox.json ./pkg/manifest_v3_firefox.json
 
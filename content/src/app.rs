@@ -1,179 +1,748 @@
-use std::{collections::BTreeMap, rc::Rc};
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
 
-use browser_rpass::{get_domain_name, types::Account};
+use browser_rpass::store_routing::StoreRoute;
+use browser_rpass::types::Account;
+use gloo::{
+    events::{EventListener, EventListenerOptions},
+    timers::callback::{Interval, Timeout},
+};
 use gloo_utils::{document, window};
 use log::*;
 use secrecy::ExposeSecret;
 use sublime_fuzzy::best_match;
-use wasm_bindgen::{prelude::Closure, JsCast};
-use web_sys::HtmlElement;
+use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
+use web_sys::{Element, HtmlElement, KeyboardEvent, MutationObserver, MutationObserverInit, Node};
 use yew::prelude::*;
 use yewdux::{mrc::Mrc, prelude::*};
 
 use crate::{
-    store::ContentScriptStore,
-    util::{fetch_accounts, find_password_input_element, find_username_input_element},
+    components::SaveBanner,
+    form_detection::open_shadow_roots,
+    store::{ContentScriptStore, PasswordChange, PendingCredential},
+    util::{
+        capture_credential, capture_password_change, commit_password_change, current_domain,
+        detect_fields, fetch_accounts, fetch_equivalent_domains, fetch_store_routes,
+        fill_one_time_code, generate_password, match_account, matches_page, recall_login_step,
+        remember_login_step, report_frame_status, set_context_menu_target, sort_by_route,
+        take_pending_credential, DetectedFields,
+    },
 };
+//mutations come in bursts, so detection waits for the page to settle
+const DETECTION_DELAY_MS: u32 = 200;
+//history.pushState fires no event, so the url is polled as well
+const URL_POLL_INTERVAL_MS: u32 = 1000;
+//clicks on these may submit a login form without a submit event, as with script-driven forms
+const SUBMIT_TRIGGER_SELECTOR: &str =
+    "button, input[type=submit], input[type=image], [role=button]";
+const RPASS_ELEMENT_SELECTOR: &str = ".rpass-save-banner, .rpass-suggestion";
+//attributes that show, hide or retype inputs
+const WATCHED_ATTRIBUTES: &[&str] = &[
+    "type",
+    "style",
+    "class",
+    "hidden",
+    "disabled",
+    "aria-hidden",
+];
+
+fn observe_mutations(observer: &MutationObserver, target: &Node) {
+    let attribute_filter = WATCHED_ATTRIBUTES
+        .iter()
+        .map(|attribute| JsValue::from_str(attribute))
+        .collect::<js_sys::Array>();
+    if let Err(err) = observer.observe_with_options(
+        target,
+        MutationObserverInit::new()
+            .child_list(true)
+            .subtree(true)
+            .attributes(true)
+            .attribute_filter(&attribute_filter),
+    ) {
+        error!("failed to observe page mutations: {:?}", err);
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Properties, Default)]
 pub struct Props {}
 #[function_component]
 pub fn App(_props: &Props) -> Html {
     trace!("App rendered!");
-    let page_domain =
-        use_state(|| get_domain_name(&window().location().href().unwrap_or_default()));
+    let page_domain = use_state_eq(current_domain);
+    let fields = use_state_eq(detect_fields);
     let username_input = use_state(|| "".to_owned());
-    let username_input_element = find_username_input_element();
     let current_focus = use_state(|| None::<web_sys::HtmlInputElement>);
-    let password_input = use_state(|| "".to_owned());
-    let password_input_element = find_password_input_element();
     let verified = use_selector(|state: &ContentScriptStore| state.data.verified);
     let store_id = use_selector(|state: &ContentScriptStore| state.data.store_id.clone());
     let account_selector = use_selector(|state: &ContentScriptStore| state.data.accounts.clone());
+    let unlocked_store_ids = use_selector(|state: &ContentScriptStore| state.store_ids.clone());
+    //suggestions name their store once accounts of several stores are shown
+    let label_stores = use_selector(|state: &ContentScriptStore| state.store_accounts.len() > 1);
     let accounts = use_state(|| Rc::new(Vec::<Rc<Account>>::new()));
+    let remembered_account_id =
+        use_selector(|state: &ContentScriptStore| state.remembered_account_id.clone());
+    let pending_credential =
+        use_selector(|state: &ContentScriptStore| state.pending_credential.clone());
+    let pending_password_change =
+        use_selector(|state: &ContentScriptStore| state.pending_password_change.clone());
+    let generated_password =
+        use_selector(|state: &ContentScriptStore| state.generated_password.clone());
+    let picker_requests = use_selector(|state: &ContentScriptStore| state.picker_requests);
+    let highlighted = use_state_eq(|| None::<usize>);
+    let DetectedFields {
+        username: username_input_element,
+        password: password_input_element,
+        one_time_code: one_time_code_input_element,
+        change_password: change_password_form,
+    } = (*fields).clone();
+    let new_password_input_elements = change_password_form
+        .as_ref()
+        .map(|form| {
+            vec![
+                Some(form.new_password.clone()),
+                form.confirm_password.clone(),
+            ]
+        })
+        .unwrap_or_default();
+    //accounts offered for the username and password fields, best match first
+    let suggested_accounts = {
+        let mut result_vec: Vec<Rc<Account>> = vec![];
+        if username_input.is_empty() {
+            result_vec = (**accounts).clone();
+        } else {
+            let mut filtered_with_current_input: BTreeMap<isize, Vec<Rc<Account>>> =
+                BTreeMap::new();
+            let account_data = &**accounts;
+            for account in account_data {
+                let account_id = &account.id;
+                let result = best_match(&*username_input, account_id);
+                if let Some(result) = result {
+                    let score = result.score();
+                    filtered_with_current_input
+                        .entry(score)
+                        .and_modify(|ls| ls.push(account.clone()))
+                        .or_insert_with(|| vec![account.clone()]);
+                }
+            }
+            for vac in filtered_with_current_input.into_values() {
+                for v in vac {
+                    result_vec.push(v);
+                }
+            }
+        }
+        //offer the account picked on the previous login step first
+        if let Some(remembered_account_id) = (*remembered_account_id).as_ref() {
+            result_vec.sort_by_key(|account| &account.id != remembered_account_id);
+        }
+        result_vec
+    };
+    let fill_suggestion = {
+        let current_focus = current_focus.clone();
+        let username_input_element = username_input_element.clone();
+        let password_input_element = password_input_element.clone();
+        let page_domain = page_domain.clone();
+        Callback::from(move |entry: Rc<Account>| {
+            if let Some(username_input_element) = username_input_element.as_ref() {
+                username_input_element.set_value(&entry.username);
+            }
+            if let Some(password_input_element) = password_input_element.as_ref() {
+                password_input_element.set_value(
+                    &entry
+                        .get_password()
+                        .map(|s| s.expose_secret().clone())
+                        .unwrap_or(String::new()),
+                );
+            } else {
+                //username-only page, the password comes on the next step
+                remember_login_step(
+                    Dispatch::<ContentScriptStore>::new()
+                        .get()
+                        .account_store_id(&entry),
+                    (*page_domain).clone(),
+                    entry.id.clone(),
+                );
+            }
+            current_focus.set(None);
+        })
+    };
+    //login forms rendered after load (SPAs, modals) and client-side navigation show up as dom
+    //mutations or url changes, so detection re-runs whenever either happens
     use_effect_with((), {
+        let fields = fields.clone();
         let page_domain = page_domain.clone();
-        let user_input_element = username_input_element.clone();
-        let _password_input = password_input.clone();
-        let password_input_element = password_input_element.clone();
-        let current_focus = current_focus.clone();
-        {
-            let username_input_element = username_input_element.clone();
-            let username_input = username_input.clone();
-            let password_input_element = password_input_element.clone();
-            move |_| {
-                let current_address =
-                    get_domain_name(&window().location().href().unwrap_or_default());
-                if *page_domain != current_address {
-                    page_domain.set(current_address);
+        move |_| {
+            let pending_detection = RefCell::new(None::<Timeout>);
+            let observer = Rc::new(RefCell::new(None::<MutationObserver>));
+            let schedule_detection = Rc::new({
+                let observer = observer.clone();
+                move || {
+                    let fields = fields.clone();
+                    let page_domain = page_domain.clone();
+                    let observer = observer.clone();
+                    *pending_detection.borrow_mut() =
+                        Some(Timeout::new(DETECTION_DELAY_MS, move || {
+                            //mutations inside a shadow root do not reach the body, so shadow
+                            //roots attached since the last run are observed as well
+                            if let Some(observer) = observer.borrow().as_ref() {
+                                for shadow_root in open_shadow_roots() {
+                                    observe_mutations(observer, &shadow_root);
+                                }
+                            }
+                            page_domain.set(current_domain());
+                            fields.set(detect_fields());
+                        }));
                 }
-                if let Some(ref username_input_element) = user_input_element {
-                    let on_focus = {
-                        let user_input_element = username_input_element.clone();
-                        let current_focus = current_focus.clone();
-                        Closure::<dyn Fn(_)>::new(move |_event: web_sys::FocusEvent| {
-                            current_focus.set(Some(user_input_element.clone()));
-                        })
-                    };
-                    username_input_element
-                        .add_event_listener_with_callback(
-                            "focus",
-                            on_focus.as_ref().unchecked_ref(),
-                        )
-                        .unwrap();
-                    on_focus.forget();
+            });
+            let on_mutation = {
+                let schedule_detection = schedule_detection.clone();
+                Closure::<dyn Fn(js_sys::Array, MutationObserver)>::new(
+                    move |_: js_sys::Array, _: MutationObserver| schedule_detection(),
+                )
+            };
+            *observer.borrow_mut() =
+                MutationObserver::new(on_mutation.as_ref().unchecked_ref()).ok();
+            if let (Some(observer), Some(body)) = (observer.borrow().as_ref(), document().body()) {
+                observe_mutations(observer, &body);
+                for shadow_root in open_shadow_roots() {
+                    observe_mutations(observer, &shadow_root);
                 }
-                if let Some(ref password_input_element) = password_input_element {
-                    let on_focus = {
-                        let password_input_element = password_input_element.clone();
-                        let current_focus = current_focus.clone();
-                        Closure::<dyn Fn(_)>::new(move |_event: web_sys::FocusEvent| {
-                            current_focus.set(Some(password_input_element.clone()));
-                        })
-                    };
-                    password_input_element
-                        .add_event_listener_with_callback(
-                            "focus",
-                            on_focus.as_ref().unchecked_ref(),
-                        )
-                        .unwrap();
-                    on_focus.forget();
+            }
+            let navigation_listeners = ["popstate", "hashchange"].map(|event_type| {
+                let schedule_detection = schedule_detection.clone();
+                EventListener::new(&window(), event_type, move |_| schedule_detection())
+            });
+            let url_poll = {
+                let schedule_detection = schedule_detection.clone();
+                let last_href = RefCell::new(window().location().href().unwrap_or_default());
+                Interval::new(URL_POLL_INTERVAL_MS, move || {
+                    let href = window().location().href().unwrap_or_default();
+                    if *last_href.borrow() != href {
+                        *last_href.borrow_mut() = href;
+                        schedule_detection();
+                    }
+                })
+            };
+            move || {
+                if let Some(observer) = observer.borrow_mut().take() {
+                    observer.disconnect();
                 }
-                let on_click = {
+                drop(on_mutation);
+                drop(navigation_listeners);
+                drop(url_poll);
+            }
+        }
+    });
+    //context menu entries act on the input the menu was opened on
+    use_effect_with((), |_| {
+        let context_menu_listener = EventListener::new_with_options(
+            &document(),
+            "contextmenu",
+            EventListenerOptions::run_in_capture_phase(),
+            |event| {
+                set_context_menu_target(
+                    event
+                        .composed_path()
+                        .get(0)
+                        .dyn_into::<web_sys::HtmlInputElement>()
+                        .ok(),
+                )
+            },
+        );
+        move || drop(context_menu_listener)
+    });
+    //listeners are attached to the fields currently detected and removed once they go away
+    use_effect_with(fields.clone(), {
+        let current_focus = current_focus.clone();
+        let username_input = username_input.clone();
+        move |fields: &UseStateHandle<DetectedFields>| {
+            let change_password = fields.change_password.as_ref();
+            let detected_elements = [
+                fields.username.clone(),
+                fields.password.clone(),
+                fields.one_time_code.clone(),
+                change_password.map(|form| form.new_password.clone()),
+                change_password.and_then(|form| form.confirm_password.clone()),
+            ];
+            let focus_listeners = detected_elements
+                .iter()
+                .flatten()
+                .map(|element| {
                     let current_focus = current_focus.clone();
-                    let username_input_element = username_input_element.clone();
-                    let password_input_element = password_input_element.clone();
-                    Closure::<dyn Fn(_)>::new(move |_event: web_sys::MouseEvent| {
-                        if let Some(event_target) = _event.target() {
-                            if let Ok(target_element) =
-                                event_target.clone().dyn_into::<HtmlElement>()
-                            {
-                                if target_element.class_name().contains("rpass-suggestion")
-                                    || (username_input_element.is_some()
-                                        && target_element
-                                            == username_input_element
-                                                .clone()
-                                                .unwrap()
-                                                .dyn_into()
-                                                .unwrap())
-                                    || (password_input_element.is_some()
-                                        && target_element
-                                            == password_input_element
-                                                .clone()
-                                                .unwrap()
-                                                .dyn_into()
-                                                .unwrap())
-                                {
-                                    // do nothing when click on suggestion list
-                                } else {
-                                    current_focus.set(None);
-                                }
-                            } else {
-                                current_focus.set(None);
-                            }
-                        } else {
-                            current_focus.set(None);
-                        }
+                    let focused_element = element.clone();
+                    EventListener::new(element, "focus", move |_| {
+                        current_focus.set(Some(focused_element.clone()))
                     })
-                };
-                document()
-                    .body()
-                    .unwrap()
-                    .add_event_listener_with_callback("click", on_click.as_ref().unchecked_ref())
-                    .unwrap();
-                on_click.forget();
-                if let Some(username_input_element) = username_input_element {
-                    username_input_element.set_oninput(Some(
-                        &Closure::<dyn Fn(_)>::new({
-                            let username_input_element = username_input_element.clone();
-                            let username_input = username_input.clone();
-                            move |_event: web_sys::InputEvent| {
-                                let username = username_input_element.value();
-                                username_input.set(username);
-                            }
-                        })
-                        .into_js_value()
-                        .unchecked_into(),
-                    ));
+                })
+                .collect::<Vec<EventListener>>();
+            let input_listener = fields.username.as_ref().map(|element| {
+                let username_input = username_input.clone();
+                let username_input_element = element.clone();
+                EventListener::new(element, "input", move |_| {
+                    username_input.set(username_input_element.value())
+                })
+            });
+            let click_listener = document().body().map(|body| {
+                let current_focus = current_focus.clone();
+                let detected_elements = detected_elements.clone();
+                EventListener::new(&body, "click", move |event| {
+                    //the target of a click inside a shadow root is its host
+                    let keep_suggestions = event
+                        .composed_path()
+                        .get(0)
+                        .dyn_into::<HtmlElement>()
+                        .ok()
+                        .is_some_and(|target| {
+                            // do nothing when click on suggestion list or on the fields themselves
+                            target.class_name().contains("rpass-suggestion")
+                                || detected_elements
+                                    .iter()
+                                    .flatten()
+                                    .any(|element| **element == target)
+                        });
+                    if !keep_suggestions {
+                        current_focus.set(None);
+                    }
+                })
+            });
+            if let Some(focused_element) = (*current_focus).clone() {
+                if !detected_elements.contains(&Some(focused_element)) {
+                    current_focus.set(None);
                 }
             }
+            move || {
+                drop(focus_listeners);
+                drop(input_listener);
+                drop(click_listener);
+            }
+        }
+    });
+    //credentials are read when the form is submitted, before the page gets to clear or leave it
+    use_effect_with(
+        fields.clone(),
+        move |fields: &UseStateHandle<DetectedFields>| {
+            let capture = {
+                let fields = fields.clone();
+                move || {
+                    let value = |element: &Option<web_sys::HtmlInputElement>| {
+                        element
+                            .as_ref()
+                            .map(|element| element.value())
+                            .filter(|value| !value.is_empty())
+                    };
+                    if let Some(change_password) = fields.change_password.as_ref() {
+                        capture_password_change(change_password);
+                    } else if let Some(password) = value(&fields.password) {
+                        capture_credential(PendingCredential {
+                            domain: current_domain(),
+                            username: value(&fields.username),
+                            password,
+                        });
+                    }
+                }
+            };
+            //submit events do not leave the shadow root of the form, so the listeners go on the
+            //root the password field lives in
+            let password_root = fields
+                .password
+                .clone()
+                .or_else(|| {
+                    fields
+                        .change_password
+                        .as_ref()
+                        .map(|form| form.current_password.clone())
+                })
+                .map(|element| element.get_root_node());
+            let submit_listeners = password_root.map(|root| {
+                let submit_listener = {
+                    let capture = capture.clone();
+                    EventListener::new_with_options(
+                        &root,
+                        "submit",
+                        EventListenerOptions::run_in_capture_phase(),
+                        move |_| capture(),
+                    )
+                };
+                let click_listener = EventListener::new_with_options(
+                    &root,
+                    "click",
+                    EventListenerOptions::run_in_capture_phase(),
+                    move |event| {
+                        //clicks on the save banner and the suggestion list are our own
+                        let is_submit_trigger = event
+                            .target()
+                            .and_then(|target| target.dyn_into::<Element>().ok())
+                            .is_some_and(|target| {
+                                target
+                                    .closest(SUBMIT_TRIGGER_SELECTOR)
+                                    .ok()
+                                    .flatten()
+                                    .is_some()
+                                    && target
+                                        .closest(RPASS_ELEMENT_SELECTOR)
+                                        .ok()
+                                        .flatten()
+                                        .is_none()
+                            });
+                        if is_submit_trigger {
+                            capture();
+                        }
+                    },
+                );
+                (submit_listener, click_listener)
+            });
+            move || drop(submit_listeners)
+        },
+    );
+    use_effect_with(fields.clone(), {
+        let store_id = store_id.clone();
+        move |fields: &UseStateHandle<DetectedFields>| {
+            report_frame_status(
+                (*store_id).clone(),
+                fields.username.is_some() || fields.password.is_some(),
+            );
         }
     });
     use_effect_with(verified.clone(), {
         let accounts = accounts.clone();
+        let store_id = store_id.clone();
         move |verified: &Rc<bool>| {
             if **verified && store_id.is_some() {
                 fetch_accounts((*store_id).clone(), None);
+                fetch_equivalent_domains();
+                fetch_store_routes();
+                //the form may have been submitted on the page before this one
+                take_pending_credential((*store_id).clone(), current_domain());
             } else {
                 accounts.set(Rc::new(Vec::<Rc<Account>>::new()));
             }
         }
     });
-    use_effect_with((page_domain.clone(), account_selector.clone()), {
-        let accounts = accounts.clone();
-        let verified = verified.clone();
-        move |(page_domain, account_selector): &(
-            UseStateHandle<String>,
-            Rc<Mrc<Vec<Rc<Account>>>>,
+    //every unlocked store is fetched, which also subscribes this frame to its events
+    use_effect_with(unlocked_store_ids.clone(), {
+        move |unlocked_store_ids: &Rc<Vec<String>>| {
+            for unlocked_store_id in unlocked_store_ids.iter() {
+                fetch_accounts(Some(unlocked_store_id.clone()), None);
+            }
+        }
+    });
+    let equivalent_domains =
+        use_selector(|state: &ContentScriptStore| state.equivalent_domains.clone());
+    let store_routes = use_selector(|state: &ContentScriptStore| state.store_routes.clone());
+    use_effect_with(
+        (
+            page_domain.clone(),
+            account_selector.clone(),
+            equivalent_domains.clone(),
+            store_routes.clone(),
+        ),
+        {
+            let accounts = accounts.clone();
+            let verified = verified.clone();
+            move |(_page_domain, account_selector, _equivalent_domains, _store_routes): &(
+                UseStateHandle<String>,
+                Rc<Mrc<Vec<Rc<Account>>>>,
+                Rc<Vec<Vec<String>>>,
+                Rc<Vec<StoreRoute>>,
+            )| {
+                if *verified {
+                    let account_state = account_selector.clone();
+                    let mut result_vec = account_state
+                        .borrow()
+                        .iter()
+                        .cloned()
+                        .filter(|account| matches_page(account))
+                        .collect::<Vec<Rc<Account>>>();
+                    sort_by_route(
+                        &Dispatch::<ContentScriptStore>::new().get(),
+                        &mut result_vec,
+                    );
+                    accounts.set(Rc::new(result_vec));
+                }
+            }
+        },
+    );
+    //a password field showing up may be the second step of a login started on a previous page
+    use_effect_with(
+        (
+            password_input_element.is_some(),
+            page_domain.clone(),
+            verified.clone(),
+        ),
+        {
+            move |(has_password_field, page_domain, verified): &(
+                bool,
+                UseStateHandle<String>,
+                Rc<bool>,
+            )| {
+                if *has_password_field && **verified {
+                    recall_login_step(None, (**page_domain).clone());
+                }
+            }
+        },
+    );
+    use_effect_with(
+        (
+            remembered_account_id.clone(),
+            accounts.clone(),
+            fields.clone(),
+        ),
+        move |(remembered_account_id, accounts, fields): &(
+            Rc<Option<String>>,
+            UseStateHandle<Rc<Vec<Rc<Account>>>>,
+            UseStateHandle<DetectedFields>,
         )| {
-            if *verified {
-                let account_state = account_selector.clone();
-                let result_vec = account_state
-                    .borrow()
+            let remembered_account = (**remembered_account_id).as_ref().and_then(|account_id| {
+                accounts
                     .iter()
+                    .find(|account| &account.id == account_id)
                     .cloned()
-                    .filter(|account| {
-                        let domain = account.domain.as_ref();
-                        let page_address = (**page_domain).clone();
-                        domain.unwrap_or(&String::new()) == &page_address
-                    })
-                    .collect::<Vec<Rc<Account>>>();
-                accounts.set(Rc::new(result_vec));
+            });
+            if let (Some(account), Some(password_input_element)) =
+                (remembered_account, fields.password.as_ref())
+            {
+                if password_input_element.value().is_empty() {
+                    if let Some(username_input_element) = fields.username.as_ref() {
+                        if username_input_element.value().is_empty() {
+                            username_input_element.set_value(&account.username);
+                        }
+                    }
+                    password_input_element.set_value(
+                        &account
+                            .get_password()
+                            .map(|s| s.expose_secret().clone())
+                            .unwrap_or(String::new()),
+                    );
+                }
+            }
+        },
+    );
+    //change-password forms: fill in the current password of the matching account
+    use_effect_with(
+        (fields.clone(), accounts.clone()),
+        |(fields, _accounts): &(
+            UseStateHandle<DetectedFields>,
+            UseStateHandle<Rc<Vec<Rc<Account>>>>,
+        )| {
+            if let Some(change_password) = fields.change_password.as_ref() {
+                let username = change_password
+                    .username
+                    .as_ref()
+                    .map(|username| username.value());
+                if let Some(account) = match_account(username) {
+                    if change_password.current_password.value().is_empty() {
+                        change_password.current_password.set_value(
+                            &account
+                                .get_password()
+                                .map(|s| s.expose_secret().clone())
+                                .unwrap_or(String::new()),
+                        );
+                    }
+                }
+            }
+        },
+    );
+    //a submitted change-password form that went away was accepted by the site
+    use_effect_with((fields.clone(), pending_password_change.clone()), {
+        move |(fields, pending_password_change): &(
+            UseStateHandle<DetectedFields>,
+            Rc<Option<PasswordChange>>,
+        )| {
+            if let Some(password_change) = (**pending_password_change).clone() {
+                if fields.change_password.is_none() {
+                    commit_password_change(password_change);
+                }
+            }
+        }
+    });
+    use_effect_with((*current_focus).clone(), {
+        let new_password_input_elements = new_password_input_elements.clone();
+        let generated_password = generated_password.clone();
+        let store_id = store_id.clone();
+        move |current_focus: &Option<web_sys::HtmlInputElement>| {
+            if current_focus.is_some()
+                && new_password_input_elements.contains(current_focus)
+                && generated_password.is_none()
+            {
+                generate_password((*store_id).clone());
+            }
+        }
+    });
+    //a keyboard shortcut found no clear match, so the suggestions are opened as a picker
+    use_effect_with(*picker_requests, {
+        let current_focus = current_focus.clone();
+        let account_field = username_input_element
+            .clone()
+            .or(password_input_element.clone());
+        move |picker_requests: &u32| {
+            if *picker_requests > 0 {
+                if let Some(account_field) = account_field {
+                    let _ = account_field.focus();
+                    current_focus.set(Some(account_field));
+                }
             }
         }
     });
+    use_effect_with(((*current_focus).clone(), suggested_accounts.clone()), {
+        let highlighted = highlighted.clone();
+        move |_| highlighted.set(None)
+    });
+    //arrow keys move through the account suggestions and enter fills the highlighted one
+    use_effect_with(
+        (
+            (*current_focus).clone(),
+            suggested_accounts.clone(),
+            *highlighted,
+        ),
+        {
+            let current_focus = current_focus.clone();
+            let highlighted = highlighted.clone();
+            let fill_suggestion = fill_suggestion.clone();
+            let account_fields = [
+                username_input_element.clone(),
+                password_input_element.clone(),
+            ];
+            move |(focused, suggested_accounts, highlighted_index): &(
+                Option<web_sys::HtmlInputElement>,
+                Vec<Rc<Account>>,
+                Option<usize>,
+            )| {
+                let keydown_listener = focused
+                    .as_ref()
+                    .filter(|focused| account_fields.contains(&Some((*focused).clone())))
+                    .map(|focused| {
+                        let suggested_accounts = suggested_accounts.clone();
+                        let highlighted_index = *highlighted_index;
+                        EventListener::new(focused, "keydown", move |event| {
+                            let Some(event) = event.dyn_ref::<KeyboardEvent>() else {
+                                return;
+                            };
+                            let count = suggested_accounts.len();
+                            match event.key().as_str() {
+                                "ArrowDown" if count > 0 => {
+                                    event.prevent_default();
+                                    highlighted.set(Some(
+                                        highlighted_index.map_or(0, |index| (index + 1) % count),
+                                    ));
+                                }
+                                "ArrowUp" if count > 0 => {
+                                    event.prevent_default();
+                                    highlighted
+                                        .set(Some(highlighted_index.map_or(count - 1, |index| {
+                                            (index + count - 1) % count
+                                        })));
+                                }
+                                "Enter" => {
+                                    if let Some(account) = highlighted_index
+                                        .and_then(|index| suggested_accounts.get(index))
+                                    {
+                                        //the form is not submitted until the account is filled
+                                        event.prevent_default();
+                                        fill_suggestion.emit(account.clone());
+                                    }
+                                }
+                                "Escape" => current_focus.set(None),
+                                _ => {}
+                            }
+                        })
+                    });
+                move || drop(keydown_listener)
+            }
+        },
+    );
 
-    if (*current_focus).is_none() {
+    let save_banner = match (*pending_credential).clone() {
+        Some(credential) => html!(
+            <SaveBanner
+                credential={credential}
+                accounts={(*accounts).clone()}
+                remembered_account_id={(*remembered_account_id).clone()}
+            />
+        ),
+        None => html!(<></>),
+    };
+    let suggestions = if (*current_focus).is_none() {
         html!(<></>)
+    } else if new_password_input_elements.contains(&*current_focus) {
+        let target_input_element: HtmlElement =
+            (*current_focus).to_owned().unwrap().dyn_into().unwrap();
+        let target_rect = target_input_element.get_bounding_client_rect();
+        let list_style = {
+            let top = target_rect.top() + target_rect.height();
+            let left = target_rect.left();
+            let width = target_rect.width();
+
+            format!(
+                "position:fixed;
+            top: {top}px; width:{width}px ;left: {left}px;
+                    min-width: 160px; box-shadow: 0px 8px 16px 0px rgba(0,0,0,0.2);
+                    z-index: 99;"
+            )
+        };
+        let suggestion = match (*generated_password).clone() {
+            Some(password) => {
+                let on_suggestion_click = {
+                    let current_focus = current_focus.clone();
+                    let new_password_input_elements = new_password_input_elements.clone();
+                    let password = password.clone();
+                    Callback::from(move |_event: web_sys::MouseEvent| {
+                        _event.prevent_default();
+                        for input_element in new_password_input_elements.iter().flatten() {
+                            input_element.set_value(&password);
+                        }
+                        current_focus.set(None);
+                    })
+                };
+                html!(<div style=" border: 3px solid black;" class="cursor-pointer rpass-suggestion bg-gray-200 text-gray-700 block px-3 py-2 text-sm" onclick={on_suggestion_click}>{format!("use generated password: {}", password)}</div>)
+            }
+            None => {
+                html!(<div style=" border: 3px solid black;" class="rpass-suggestion bg-gray-200 text-gray-700 block px-3 py-2 text-sm">{"generating password..."}</div>)
+            }
+        };
+        html!(
+              <div id={format!("{}-suggestions",target_input_element.id())}
+              class="w-56 origin-top-right rounded-md bg-white shadow-lg ring-1 ring-black ring-opacity-5 focus:outline-none" role="menu" aria-orientation="vertical" tabindex="-1"
+                style={list_style}
+              >
+              {suggestion}
+            </div>
+        )
+    } else if *current_focus == one_time_code_input_element {
+        let target_input_element: HtmlElement =
+            (*current_focus).to_owned().unwrap().dyn_into().unwrap();
+        let target_rect = target_input_element.get_bounding_client_rect();
+        let list_style = {
+            let top = target_rect.top() + target_rect.height();
+            let left = target_rect.left();
+            let width = target_rect.width();
+
+            format!(
+                "position:fixed;
+            top: {top}px; width:{width}px ;left: {left}px;
+                    min-width: 160px; box-shadow: 0px 8px 16px 0px rgba(0,0,0,0.2);
+                    z-index: 99;"
+            )
+        };
+        html!(
+              <div id={format!("{}-suggestions",target_input_element.id())}
+              class="w-56 origin-top-right rounded-md bg-white shadow-lg ring-1 ring-black ring-opacity-5 focus:outline-none" role="menu" aria-orientation="vertical" tabindex="-1"
+                style={list_style}
+              >
+            {
+                accounts.iter().filter(|account| account.has_totp).map(|entry|{
+                    let on_suggestion_click = {
+                        let current_focus=current_focus.clone();
+                        let entry=entry.clone();
+                        Callback::from(move |_event: web_sys::MouseEvent| {
+                            _event.prevent_default();
+                            let store_id = Dispatch::<ContentScriptStore>::new().get().account_store_id(&entry);
+                            fill_one_time_code(store_id, entry.id.clone());
+                            current_focus.set(None);
+                        })
+                    };
+                    html!(<div style=" border: 3px solid black;" class="cursor-pointer rpass-suggestion bg-gray-200 text-gray-700 block px-3 py-2 text-sm" onclick={on_suggestion_click}>{format!("one-time code for {}", entry.username)}</div>)
+                }).collect::<Html>()
+            }
+            </div>
+        )
     } else {
         let target_input_element: HtmlElement =
             (*current_focus).to_owned().unwrap().dyn_into().unwrap();
@@ -197,55 +766,37 @@ pub fn App(_props: &Props) -> Html {
               >
 
             {
-                {
-                    let username_input_element=username_input_element.clone();
-                    let password_input_element=password_input_element.clone();
-                    let username_input=username_input.clone();
-                    let mut result_vec: Vec<Rc<Account>> = vec![];
-                    if username_input.is_empty(){
-                        result_vec=(**accounts).clone();
-                    }else{
-                        let mut filtered_with_current_input: BTreeMap<isize, Vec<Rc<Account>>> = BTreeMap::new();
-                        let account_data=&**accounts;
-                        for account in account_data {
-                            let account_id = &account.id;
-                            let result = best_match(&*username_input, account_id);
-                            if let Some(result) = result {
-                                let score = result.score();
-                                filtered_with_current_input
-                                    .entry(score)
-                                    .and_modify(|ls| ls.push(account.clone()))
-                                    .or_insert_with(|| vec![account.clone()]);
-                            }
-                        }
-                        for vac in filtered_with_current_input.into_values() {
-                            for v in vac {
-                                result_vec.push(v);
-                            }
+                suggested_accounts.iter().enumerate().map(|(index, entry)|{
+                    let on_suggestion_click = {
+                        let fill_suggestion=fill_suggestion.clone();
+                        let entry=entry.clone();
+                        Callback::from(move |_event: web_sys::MouseEvent| {
+                            _event.prevent_default();
+                            fill_suggestion.emit(entry.clone());
+                        })
+                    };
+                    let background = if *highlighted == Some(index) { "bg-gray-400" } else { "bg-gray-200" };
+                    let store_label = if *label_stores {
+                        Dispatch::<ContentScriptStore>::new().get().account_store_id(entry)
+                    } else {
+                        None
+                    };
+                    let entry_element=html!(<div style=" border: 3px solid black;" class={format!("cursor-pointer rpass-suggestion {} text-gray-700 block px-3 py-2 text-sm", background)} onclick={on_suggestion_click}>
+                        {entry.username.clone()}
+                        if let Some(store_label) = store_label {
+                            <span class="ml-2 text-xs text-gray-500">{store_label}</span>
                         }
-                    }
-                    (*result_vec).iter().map(|entry|{
-                        let on_suggestion_click = {
-                            let current_focus=current_focus.clone();
-                            let entry=entry.clone();
-                            let username_input_element=username_input_element.clone();
-                            let password_input_element=password_input_element.clone();
-                            Callback::from(move |_event: web_sys::MouseEvent| {
-                                _event.prevent_default();
-                                if username_input_element.is_some() {
-                                    username_input_element.as_ref().unwrap().set_value(&entry.username);
-                                }
-                                if password_input_element.is_some() {
-                                    password_input_element.as_ref().unwrap().set_value(&entry.get_password().map(|s|s.expose_secret().clone()).unwrap_or(String::new()));
-                                }
-                                current_focus.set(None);
-                            })
-                        };
-                        let entry_element=html!(<div style=" border: 3px solid black;" class="cursor-pointer rpass-suggestion bg-gray-200 text-gray-700 block px-3 py-2 text-sm" onclick={on_suggestion_click}>{entry.username.clone()}</div>);
-                        entry_element
-                    }).collect::<Html>()}
+                    </div>);
+                    entry_element
+                }).collect::<Html>()
             }
             </div>
         )
-    }
+    };
+    html!(
+        <>
+            {save_banner}
+            {suggestions}
+        </>
+    )
 }
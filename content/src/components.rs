@@ -0,0 +1,2 @@
+mod save_banner;
+pub use save_banner::*;
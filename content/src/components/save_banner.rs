@@ -0,0 +1,124 @@
+use std::rc::Rc;
+
+use browser_rpass::types::Account;
+use secrecy::ExposeSecret;
+use web_sys::HtmlSelectElement;
+use yew::prelude::*;
+use yewdux::prelude::*;
+
+use crate::{
+    store::{ContentScriptStore, DataAction, PendingCredential},
+    util::{never_save_credential, save_credential, save_store_id, update_password},
+};
+
+const BANNER_STYLE: &str = "position:fixed; top:16px; right:16px; z-index:2147483647;
+    width:320px; padding:12px; background:white; color:#1f2937; font:14px sans-serif;
+    border:1px solid #d1d5db; border-radius:8px; box-shadow:0px 8px 16px 0px rgba(0,0,0,0.2);";
+const BUTTON_STYLE: &str =
+    "margin:8px 8px 0 0; padding:4px 10px; border:1px solid #9ca3af; border-radius:4px; cursor:pointer;";
+
+#[derive(Properties, PartialEq)]
+pub struct SaveBannerProps {
+    pub credential: PendingCredential,
+    //accounts of the current site in the unlocked stores
+    pub accounts: Rc<Vec<Rc<Account>>>,
+    #[prop_or_default]
+    pub remembered_account_id: Option<String>,
+}
+
+#[function_component(SaveBanner)]
+pub fn save_banner(props: &SaveBannerProps) -> Html {
+    let store_ids = use_selector(|state: &ContentScriptStore| state.store_ids.clone());
+    //the store the site's rule saves to is picked up front
+    let selected_store_id = use_state({
+        let store_ids = store_ids.clone();
+        move || {
+            save_store_id(&Dispatch::<ContentScriptStore>::new().get())
+                .or(store_ids.first().cloned())
+        }
+    });
+    let credential = props.credential.clone();
+    //password pages of multi-step logins have no username, the account comes from the step before
+    let existing_account = props
+        .accounts
+        .iter()
+        .find(|account| match &credential.username {
+            Some(username) => &account.username == username,
+            None => props.remembered_account_id.as_ref() == Some(&account.id),
+        })
+        .cloned();
+    if let Some(account) = existing_account.as_ref() {
+        let unchanged = account
+            .get_password()
+            .is_some_and(|password| password.expose_secret() == &credential.password);
+        if unchanged {
+            return html!(<></>);
+        }
+    }
+    if selected_store_id.is_none() {
+        return html!(<></>);
+    }
+    let on_store_change = {
+        let selected_store_id = selected_store_id.clone();
+        Callback::from(move |event: Event| {
+            let select: HtmlSelectElement = event.target_unchecked_into();
+            selected_store_id.set(Some(select.value()));
+        })
+    };
+    let on_dismiss = Callback::from(|_: MouseEvent| {
+        Dispatch::<ContentScriptStore>::new().apply(DataAction::CredentialDismissed);
+    });
+    let on_never = {
+        let domain = credential.domain.clone();
+        Callback::from(move |_: MouseEvent| never_save_credential(domain.clone()))
+    };
+    let is_update = existing_account.is_some();
+    let (title, on_accept, accept_label) = match existing_account {
+        Some(account) => {
+            let on_accept = {
+                let credential = credential.clone();
+                Callback::from(move |_: MouseEvent| {
+                    update_password(
+                        Dispatch::<ContentScriptStore>::new()
+                            .get()
+                            .account_store_id(&account),
+                        account.id.clone(),
+                        credential.clone(),
+                    );
+                })
+            };
+            ("Update password?", on_accept, "Update")
+        }
+        None => {
+            let on_accept = {
+                let credential = credential.clone();
+                let selected_store_id = selected_store_id.clone();
+                Callback::from(move |_: MouseEvent| {
+                    save_credential((*selected_store_id).clone(), credential.clone());
+                })
+            };
+            ("Save to rpass?", on_accept, "Save")
+        }
+    };
+    html! {
+        <div class="rpass-save-banner" style={BANNER_STYLE}>
+            <div style="font-weight:600; margin-bottom:4px;">{title}</div>
+            <div>{format!("{} on {}", credential.username.clone().unwrap_or("(no username)".to_owned()), credential.domain)}</div>
+            if !is_update {
+                <select style="margin-top:8px; width:100%;" onchange={on_store_change}>
+                {
+                    store_ids.iter().map(|store_id| {
+                        let selected = selected_store_id.as_ref() == Some(store_id);
+                        html!(<option value={store_id.clone()} selected={selected}>{store_id.clone()}</option>)
+                    }).collect::<Html>()
+                }
+                </select>
+            }
+            <div>
+                <button style={BUTTON_STYLE} onclick={on_accept}>{accept_label}</button>
+                <button style={BUTTON_STYLE} onclick={on_dismiss}>{"Not now"}</button>
+                <button style={BUTTON_STYLE} onclick={on_never}>{"Never for this site"}</button>
+            </div>
+        </div>
+    }
+}
@@ -12,6 +12,7 @@ use wasm_bindgen_futures::spawn_local;
 use yewdux::prelude::Dispatch;
 
 use crate::store::{ContentScriptStore, DataAction, LoginAction};
+use crate::util::{fetch_accounts, fill_account, handle_context_menu, run_command};
 pub fn create_message_listener(port: &Port) -> Closure<dyn Fn(JsValue)> {
     let port = port.clone();
     Closure::<dyn Fn(JsValue)>::new(move |msg: JsValue| {
@@ -35,43 +36,56 @@ pub fn create_message_listener(port: &Port) -> Closure<dyn Fn(JsValue)> {
                         let dispatch = Dispatch::<ContentScriptStore>::new();
                         let event_request = session_event.clone();
                         let event_type = &event_request.event_type;
-                        let data = event_request.data.clone().unwrap_or_default();
+                        //several stores can be unlocked, events name the one they are about
+                        let store_id = event_request.store_id_index.clone();
 
                         let resource = event_request.resource.unwrap_or(vec![]);
                         match event_type {
-                            &SessionEventType::Init(
-                                ref _not_implemented_for_content_script_yet,
-                            ) => {
-                                dispatch.apply(DataAction::Init(data));
+                            &SessionEventType::Init(ref payload) => {
+                                dispatch.apply(DataAction::Init(payload.payload.clone()));
                             }
-                            &SessionEventType::Login => {
-                                dispatch.apply(LoginAction::Login(data.clone().into()));
+                            &SessionEventType::Login(ref payload) => {
+                                dispatch.apply(LoginAction::Login(payload.payload.clone()));
                             }
-                            &SessionEventType::Logout => {
-                                dispatch.apply(LoginAction::Logout);
+                            &SessionEventType::Logout(ref payload) => {
+                                dispatch.apply(LoginAction::Logout(payload.store_id.clone()));
                             }
-                            &SessionEventType::Delete => {
+                            &SessionEventType::Delete(ref payload) => {
                                 let resource = resource[0].clone();
                                 match resource {
                                     Resource::Account => {
-                                        dispatch.apply(DataAction::ResourceDeleted(resource, data));
+                                        dispatch.apply(DataAction::ResourceDeleted(
+                                            resource,
+                                            store_id,
+                                            payload.id.clone(),
+                                        ));
                                     }
                                     _ => {}
                                 }
                             }
-                            &SessionEventType::Create => {
+                            &SessionEventType::Create(ref payload) => {
                                 let resource = resource[0].clone();
                                 match resource {
                                     Resource::Account => {
                                         dispatch.apply(DataAction::ResourceCreated(
                                             resource,
-                                            data.clone().into(),
+                                            store_id,
+                                            payload.account.clone(),
                                         ));
                                     }
                                     _ => {}
                                 }
                             }
-                            &SessionEventType::CreationFailed => {
+                            &SessionEventType::Update(_) => match resource[0] {
+                                Resource::Account => {
+                                    fetch_accounts(
+                                        store_id.or_else(|| dispatch.get().data.store_id.clone()),
+                                        None,
+                                    );
+                                }
+                                _ => {}
+                            },
+                            &SessionEventType::CreationFailed(_) => {
                                 let resource = resource[0].clone();
                                 match resource {
                                     Resource::Account => {
@@ -83,14 +97,17 @@ pub fn create_message_listener(port: &Port) -> Closure<dyn Fn(JsValue)> {
                                     _ => {}
                                 }
                             }
-                            &SessionEventType::Refreshed => match resource[0] {
+                            &SessionEventType::NativeAppConnection(ref native_connection) => {
+                                debug!("native app connection: {:?}", native_connection);
+                            }
+                            &SessionEventType::Refreshed(ref payload) => match resource[0] {
                                 Resource::Account => match &dispatch.get().data.storage_status {
                                     _ => {
-                                        dbg!(&data);
                                         dispatch.apply(DataAction::ResourceFetched(
                                             Resource::Account,
-                                            data.clone().into(),
-                                            None,
+                                            store_id,
+                                            payload.accounts.clone(),
+                                            event_request.header.clone(),
                                         ));
                                     }
                                 },
@@ -99,6 +116,17 @@ pub fn create_message_listener(port: &Port) -> Closure<dyn Fn(JsValue)> {
                             _ => {}
                         }
                     }
+                    RequestEnum::Fill(fill_request) => {
+                        if let Some(account) = fill_request.account.as_ref() {
+                            fill_account(fill_request.store_id.clone(), account);
+                        }
+                    }
+                    RequestEnum::Command(command_request) => {
+                        run_command(command_request.command);
+                    }
+                    RequestEnum::ContextMenu(context_menu_request) => {
+                        handle_context_menu(context_menu_request);
+                    }
                     _ => {}
                 },
             },
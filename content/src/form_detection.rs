@@ -0,0 +1,520 @@
+use gloo_utils::{document, window};
+#[allow(unused_imports)]
+use log::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Element, HtmlElement, HtmlFormElement, HtmlInputElement, NodeList, ShadowRoot};
+
+//matched against the normalized (lowercase, alphanumeric only) name and id of an input
+const USERNAME_KEYWORDS: &[&str] = &[
+    "username",
+    "user",
+    "login",
+    "email",
+    "mail",
+    "account",
+    "identifier",
+    "userid",
+    "loginid",
+    "customer",
+    "member",
+    "nickname",
+];
+const PASSWORD_KEYWORDS: &[&str] = &["password", "passwd", "pass", "pwd", "passcode", "secret"];
+const NEW_PASSWORD_KEYWORDS: &[&str] = &["new", "confirm", "repeat", "retype", "again", "verify"];
+const CURRENT_PASSWORD_KEYWORDS: &[&str] = &["current", "old", "existing"];
+const ONE_TIME_CODE_KEYWORDS: &[&str] = &[
+    "otp",
+    "totp",
+    "onetimecode",
+    "otc",
+    "mfa",
+    "2fa",
+    "twofactor",
+    "verificationcode",
+    "authenticatorcode",
+];
+//fields that look like text inputs but are never usernames
+const NOT_USERNAME_KEYWORDS: &[&str] = &[
+    "search",
+    "query",
+    "firstname",
+    "lastname",
+    "fullname",
+    "address",
+    "city",
+    "zip",
+    "postal",
+    "captcha",
+    "coupon",
+    "promo",
+    "newsletter",
+    "subscribe",
+];
+const LOGIN_FORM_KEYWORDS: &[&str] = &["login", "signin", "logon", "auth", "session"];
+const NOT_LOGIN_FORM_KEYWORDS: &[&str] = &[
+    "register",
+    "signup",
+    "join",
+    "search",
+    "subscribe",
+    "newsletter",
+    "checkout",
+    "contact",
+];
+const FIELD_INPUT_TYPES: &[&str] = &["", "text", "email", "tel", "password", "number"];
+//fields scoring below this are not considered at all
+const MIN_FIELD_SCORE: f64 = 30.0;
+//username-only forms (the first step of multi-step logins) need stronger evidence
+const MIN_USERNAME_ONLY_SCORE: f64 = 60.0;
+const FULL_CONFIDENCE_SCORE: f64 = 100.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldMatch {
+    pub element: HtmlInputElement,
+    //0.0 to 1.0
+    pub confidence: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoginForm {
+    //None for inputs that are not inside a form element
+    pub form: Option<HtmlFormElement>,
+    pub username: Option<FieldMatch>,
+    pub password: Option<FieldMatch>,
+    //0.0 to 1.0, how likely this is a form to sign in with an existing account
+    pub confidence: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangePasswordForm {
+    pub form: Option<HtmlFormElement>,
+    //often a hidden field, telling which account the password is changed for
+    pub username: Option<HtmlInputElement>,
+    pub current_password: HtmlInputElement,
+    pub new_password: HtmlInputElement,
+    pub confirm_password: Option<HtmlInputElement>,
+}
+
+//everything a page tells about what an input is for, normalized once
+struct FieldSignals {
+    element: HtmlInputElement,
+    input_type: String,
+    autocomplete: Vec<String>,
+    //name and id
+    identifiers: String,
+    //labels, placeholder, aria-label(ledby) and title
+    descriptions: String,
+    visible: bool,
+}
+
+fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+fn contains_any(text: &str, keywords: &[&str]) -> bool {
+    keywords.iter().any(|keyword| text.contains(keyword))
+}
+
+impl FieldSignals {
+    fn new(element: HtmlInputElement) -> Self {
+        let input_type = element.type_().to_lowercase();
+        let autocomplete = element
+            .get_attribute("autocomplete")
+            .unwrap_or_default()
+            .to_lowercase()
+            .split_whitespace()
+            .map(|token| token.to_owned())
+            .collect();
+        let identifiers = normalize(&format!("{} {}", element.name(), element.id()));
+        let mut descriptions = vec![
+            element.placeholder(),
+            element.title(),
+            element.get_attribute("aria-label").unwrap_or_default(),
+        ];
+        if let Some(labelled_by) = element.get_attribute("aria-labelledby") {
+            //ids are scoped to the shadow root the input lives in
+            let shadow_root = element.get_root_node().dyn_into::<ShadowRoot>().ok();
+            for id in labelled_by.split_whitespace() {
+                let label = match shadow_root.as_ref() {
+                    Some(shadow_root) => shadow_root.get_element_by_id(id),
+                    None => document().get_element_by_id(id),
+                };
+                if let Some(label) = label {
+                    descriptions.push(label.text_content().unwrap_or_default());
+                }
+            }
+        }
+        if let Some(labels) = element.labels() {
+            for i in 0..labels.length() {
+                if let Some(label) = labels.item(i) {
+                    descriptions.push(label.text_content().unwrap_or_default());
+                }
+            }
+        }
+        let descriptions = normalize(&descriptions.join(" "));
+        let visible = is_visible(&element);
+        FieldSignals {
+            element,
+            input_type,
+            autocomplete,
+            identifiers,
+            descriptions,
+            visible,
+        }
+    }
+    fn has_autocomplete(&self, token: &str) -> bool {
+        self.autocomplete.iter().any(|t| t == token)
+    }
+    fn is_one_time_code(&self) -> bool {
+        self.has_autocomplete("one-time-code")
+            || contains_any(&self.identifiers, ONE_TIME_CODE_KEYWORDS)
+    }
+    fn is_current_password(&self) -> bool {
+        self.has_autocomplete("current-password")
+            || contains_any(&self.identifiers, CURRENT_PASSWORD_KEYWORDS)
+            || contains_any(&self.descriptions, CURRENT_PASSWORD_KEYWORDS)
+    }
+    fn is_new_password(&self) -> bool {
+        self.has_autocomplete("new-password")
+            || contains_any(&self.identifiers, NEW_PASSWORD_KEYWORDS)
+            || contains_any(&self.descriptions, NEW_PASSWORD_KEYWORDS)
+    }
+    fn username_score(&self) -> f64 {
+        if self.input_type == "password" || self.is_one_time_code() {
+            return 0.0;
+        }
+        let mut score = 0.0;
+        if self.has_autocomplete("username") {
+            score += 60.0;
+        } else if self.has_autocomplete("email") {
+            score += 40.0;
+        }
+        if self.has_autocomplete("new-password") || self.has_autocomplete("current-password") {
+            score -= 100.0;
+        }
+        score += match self.input_type.as_str() {
+            "email" => 30.0,
+            "text" | "" => 5.0,
+            _ => 0.0,
+        };
+        if contains_any(&self.identifiers, USERNAME_KEYWORDS) {
+            score += 25.0;
+        }
+        if contains_any(&self.descriptions, USERNAME_KEYWORDS) {
+            score += 20.0;
+        }
+        if contains_any(&self.identifiers, NOT_USERNAME_KEYWORDS)
+            || contains_any(&self.descriptions, NOT_USERNAME_KEYWORDS)
+        {
+            score -= 40.0;
+        }
+        if !self.visible {
+            score -= 50.0;
+        }
+        score
+    }
+    fn password_score(&self) -> f64 {
+        if self.is_one_time_code() {
+            return 0.0;
+        }
+        let mut score = 0.0;
+        if self.input_type == "password" {
+            score += 50.0;
+        }
+        if self.has_autocomplete("current-password") {
+            score += 40.0;
+        }
+        if self.has_autocomplete("new-password") {
+            score -= 40.0;
+        }
+        if contains_any(&self.identifiers, PASSWORD_KEYWORDS) {
+            score += 20.0;
+        }
+        if contains_any(&self.descriptions, PASSWORD_KEYWORDS) {
+            score += 15.0;
+        }
+        if contains_any(&self.identifiers, NEW_PASSWORD_KEYWORDS)
+            || contains_any(&self.descriptions, NEW_PASSWORD_KEYWORDS)
+        {
+            score -= 25.0;
+        }
+        if self.input_type != "password" && !self.has_autocomplete("current-password") {
+            //a visible text field is where people type usernames, not passwords
+            score -= 30.0;
+        }
+        if !self.visible {
+            score -= 50.0;
+        }
+        score
+    }
+}
+
+pub fn is_visible(element: &HtmlElement) -> bool {
+    if element.has_attribute("disabled")
+        || element.get_attribute("aria-hidden").as_deref() == Some("true")
+        || element.offset_width() == 0
+        || element.offset_height() == 0
+    {
+        return false;
+    }
+    match window().get_computed_style(element) {
+        Ok(Some(style)) => {
+            style.get_property_value("visibility").unwrap_or_default() != "hidden"
+                && style.get_property_value("display").unwrap_or_default() != "none"
+                && style.get_property_value("opacity").unwrap_or_default() != "0"
+        }
+        _ => true,
+    }
+}
+
+fn confidence(score: f64) -> f64 {
+    (score / FULL_CONFIDENCE_SCORE).clamp(0.0, 1.0)
+}
+
+//hints from the form element itself, e.g. `<form id="login-form" action="/session">`
+fn form_score(form: &Option<HtmlFormElement>) -> f64 {
+    let form = match form {
+        Some(form) => form,
+        None => return 0.0,
+    };
+    let descriptor = normalize(&format!(
+        "{} {} {} {}",
+        form.id(),
+        form.name(),
+        form.class_name(),
+        form.get_attribute("action").unwrap_or_default()
+    ));
+    let mut score = 0.0;
+    if contains_any(&descriptor, LOGIN_FORM_KEYWORDS) {
+        score += 15.0;
+    }
+    if contains_any(&descriptor, NOT_LOGIN_FORM_KEYWORDS) {
+        score -= 25.0;
+    }
+    if form.get_attribute("role").as_deref() == Some("search") {
+        score -= 50.0;
+    }
+    score
+}
+
+fn node_list_elements(node_list: Option<NodeList>) -> Vec<Element> {
+    node_list
+        .map(|node_list| {
+            (0..node_list.length())
+                .filter_map(|i| node_list.item(i))
+                .filter_map(|node| node.dyn_into::<Element>().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+fn shadow_roots_in(node_list: Option<NodeList>) -> Vec<ShadowRoot> {
+    node_list_elements(node_list)
+        .iter()
+        .filter_map(|element| element.shadow_root())
+        .collect()
+}
+
+/// Every open shadow root on the page, including the ones nested in other shadow roots.
+/// Closed shadow roots are out of reach for content scripts.
+pub fn open_shadow_roots() -> Vec<ShadowRoot> {
+    let mut shadow_roots = vec![];
+    let mut pending = shadow_roots_in(document().query_selector_all("*").ok());
+    while let Some(shadow_root) = pending.pop() {
+        pending.extend(shadow_roots_in(shadow_root.query_selector_all("*").ok()));
+        shadow_roots.push(shadow_root);
+    }
+    shadow_roots
+}
+
+/// Like `querySelectorAll` on the document, but also looks inside open shadow roots. Elements
+/// of a shadow root come after the ones of the document.
+pub fn query_selector_all_deep(selector: &str) -> Vec<Element> {
+    let mut elements = node_list_elements(document().query_selector_all(selector).ok());
+    for shadow_root in open_shadow_roots() {
+        elements.extend(node_list_elements(
+            shadow_root.query_selector_all(selector).ok(),
+        ));
+    }
+    elements
+}
+
+fn collect_fields() -> Vec<FieldSignals> {
+    query_selector_all_deep("input")
+        .into_iter()
+        .filter_map(|element| element.dyn_into::<HtmlInputElement>().ok())
+        .filter(|element| FIELD_INPUT_TYPES.contains(&element.type_().to_lowercase().as_str()))
+        .map(FieldSignals::new)
+        .collect()
+}
+
+//groups inputs by the form they belong to, keeping document order. inputs outside of any form
+//are grouped together, since many sites build login forms out of bare inputs and a button
+fn group_by_form(fields: Vec<FieldSignals>) -> Vec<(Option<HtmlFormElement>, Vec<FieldSignals>)> {
+    let mut groups: Vec<(Option<HtmlFormElement>, Vec<FieldSignals>)> = vec![];
+    for field in fields {
+        let form = field.element.form();
+        match groups
+            .iter_mut()
+            .find(|(group_form, _)| *group_form == form)
+        {
+            Some((_, group)) => group.push(field),
+            None => groups.push((form, vec![field])),
+        }
+    }
+    groups
+}
+
+fn classify_form(form: Option<HtmlFormElement>, fields: &[FieldSignals]) -> Option<LoginForm> {
+    let form_score = form_score(&form);
+    let password_scores = fields
+        .iter()
+        .map(|field| field.password_score())
+        .collect::<Vec<f64>>();
+    let password_index = password_scores
+        .iter()
+        .enumerate()
+        .filter(|(_, score)| **score >= MIN_FIELD_SCORE)
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(index, _)| index);
+    //the username comes before the password; the closer it is, the likelier
+    let username_candidates = &fields[..password_index.unwrap_or(fields.len())];
+    let username = username_candidates
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            let distance = username_candidates.len() - index;
+            let proximity = if password_index.is_some() && distance == 1 {
+                20.0
+            } else {
+                0.0
+            };
+            (field, field.username_score() + proximity)
+        })
+        .filter(|(_, score)| *score >= MIN_FIELD_SCORE)
+        .max_by(|(_, a), (_, b)| a.total_cmp(b));
+    let visible_password_fields = fields
+        .iter()
+        .filter(|field| field.input_type == "password" && field.visible)
+        .count();
+    match (password_index, username) {
+        (Some(password_index), username) => {
+            let password_score = password_scores[password_index];
+            let mut score = password_score + form_score;
+            if let Some((_, username_score)) = username {
+                score = (score + username_score) / 2.0 + 10.0;
+            }
+            //sign-up and change-password forms ask for the password more than once
+            if visible_password_fields > 1 {
+                score /= 2.0;
+            }
+            Some(LoginForm {
+                form,
+                username: username.map(|(field, score)| FieldMatch {
+                    element: field.element.clone(),
+                    confidence: confidence(score),
+                }),
+                password: Some(FieldMatch {
+                    element: fields[password_index].element.clone(),
+                    confidence: confidence(password_score),
+                }),
+                confidence: confidence(score),
+            })
+        }
+        (None, Some((field, username_score)))
+            if username_score + form_score >= MIN_USERNAME_ONLY_SCORE =>
+        {
+            Some(LoginForm {
+                form,
+                username: Some(FieldMatch {
+                    element: field.element.clone(),
+                    confidence: confidence(username_score),
+                }),
+                password: None,
+                confidence: confidence(username_score + form_score) * 0.8,
+            })
+        }
+        _ => None,
+    }
+}
+
+//a change-password form asks for the current password and a new one, usually twice. sign-up
+//forms ask for a new password twice too, so the current password field is what sets them apart
+fn classify_change_password_form(
+    form: Option<HtmlFormElement>,
+    fields: &[FieldSignals],
+) -> Option<ChangePasswordForm> {
+    let password_fields = fields
+        .iter()
+        .filter(|field| field.input_type == "password" && field.visible)
+        .collect::<Vec<&FieldSignals>>();
+    if password_fields.len() < 2 {
+        return None;
+    }
+    let current_index = password_fields
+        .iter()
+        .position(|field| field.is_current_password() && !field.has_autocomplete("new-password"))
+        //without hints, three password fields are current, new and confirm in that order
+        .or((password_fields.len() == 3).then_some(0))?;
+    let mut new_fields = password_fields
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| *index != current_index)
+        .map(|(_, field)| field);
+    let new_password = new_fields.next()?;
+    if !new_password.is_new_password() && password_fields.len() < 3 {
+        return None;
+    }
+    let confirm_password = new_fields.next();
+    let username = fields
+        .iter()
+        .filter(|field| field.input_type != "password")
+        .find(|field| field.has_autocomplete("username"))
+        .or_else(|| {
+            fields
+                .iter()
+                .filter(|field| field.visible && field.username_score() >= MIN_FIELD_SCORE)
+                .max_by(|a, b| a.username_score().total_cmp(&b.username_score()))
+        });
+    Some(ChangePasswordForm {
+        form,
+        username: username.map(|field| field.element.clone()),
+        current_password: password_fields[current_index].element.clone(),
+        new_password: new_password.element.clone(),
+        confirm_password: confirm_password.map(|field| field.element.clone()),
+    })
+}
+
+/// Finds the change-password form on the page, if there is one.
+pub fn find_change_password_form() -> Option<ChangePasswordForm> {
+    let change_password_form = group_by_form(collect_fields())
+        .into_iter()
+        .find_map(|(form, fields)| classify_change_password_form(form, &fields));
+    if let Some(change_password_form) = change_password_form.as_ref() {
+        trace!(
+            "change-password form {:?}",
+            change_password_form.form.as_ref().map(|form| form.id())
+        );
+    }
+    change_password_form
+}
+
+/// Finds every form on the page that looks like a sign-in form, most confident first.
+pub fn find_login_forms() -> Vec<LoginForm> {
+    let mut login_forms = group_by_form(collect_fields())
+        .into_iter()
+        .filter_map(|(form, fields)| classify_form(form, &fields))
+        .collect::<Vec<LoginForm>>();
+    login_forms.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    for login_form in login_forms.iter() {
+        trace!(
+            "login form {:?} (confidence {:.2}): username {:?}, password {:?}",
+            login_form.form.as_ref().map(|form| form.id()),
+            login_form.confidence,
+            login_form.username.as_ref().map(|field| field.confidence),
+            login_form.password.as_ref().map(|field| field.confidence),
+        );
+    }
+    login_forms
+}
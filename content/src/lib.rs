@@ -8,6 +8,7 @@ use wasm_bindgen::prelude::*;
 mod util;
 mod app;
 mod components;
+mod form_detection;
 pub use browser_rpass::dbg;
 use cfg_if::cfg_if;
 pub use log::*;
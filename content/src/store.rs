@@ -1,7 +1,8 @@
-use browser_rpass::dbg;
 use browser_rpass::js_binding::extension_api::*;
+use browser_rpass::payload::{InitPayload, LoginPayload};
 use browser_rpass::request::DataFieldType;
 use browser_rpass::request::SessionEvent;
+use browser_rpass::store_routing::StoreRoute;
 use browser_rpass::types::Account;
 use browser_rpass::types::Resource;
 use browser_rpass::types::StorageStatus;
@@ -10,7 +11,6 @@ use lazy_static::lazy_static;
 #[allow(unused_imports)]
 use log::*;
 use parking_lot::ReentrantMutex;
-use serde_json;
 use wasm_bindgen::prelude::Closure;
 use yewdux::mrc::Mrc;
 
@@ -21,6 +21,7 @@ use serde::Deserialize;
 use serde::Serialize;
 use serde_json::Value;
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::ops::Deref;
 use std::rc::Rc;
@@ -78,8 +79,42 @@ pub struct ContentScriptStore {
     pub page_loading: bool,
     pub alert_input: AlertInput,
     pub status: StoreStatus,
+    //data.accounts holds the accounts of every unlocked store below, merged
     pub data: StoreData,
+    pub store_accounts: BTreeMap<String, Vec<Rc<Account>>>,
     pub path: Option<String>,
+    //account picked on the previous step of a multi-step login in this tab
+    pub remembered_account_id: Option<String>,
+    //unlocked stores the save banner can offer
+    pub store_ids: Vec<String>,
+    pub pending_credential: Option<PendingCredential>,
+    pub pending_password_change: Option<PasswordChange>,
+    //offered for the new password fields of change-password forms
+    pub generated_password: Option<String>,
+    //bumped when a keyboard shortcut asks for the account picker
+    pub picker_requests: u32,
+    //groups of domains the user treats as one site when matching accounts
+    pub equivalent_domains: Vec<Vec<String>>,
+    //which stores each site searches first and saves to
+    pub store_routes: Vec<StoreRoute>,
+    //prefix the native app saves custom fields under
+    pub custom_field_prefix: Option<String>,
+}
+
+//credentials captured from a submitted login or signup form, waiting for the user to save them
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PendingCredential {
+    pub domain: String,
+    pub username: Option<String>,
+    pub password: String,
+}
+
+//new password submitted on a change-password form, waiting for the form to go away
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PasswordChange {
+    pub store_id: Option<String>,
+    pub account_id: String,
+    pub credential: PendingCredential,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -91,26 +126,35 @@ pub enum LoginAction {
     LogoutSucceeded,
     LogoutFailed,
     LogoutStarted,
-    Logout,
-    Login(Option<HashMap<DataFieldType, Value>>),
+    Logout(Option<String>),
+    Login(LoginPayload),
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum DataAction {
     ResourceFetchStarted(Resource),
-    Init(HashMap<DataFieldType, Value>),
-    ResourceDeleted(Resource, HashMap<DataFieldType, Value>),
-    ResourceCreated(Resource, HashMap<DataFieldType, Value>),
+    Init(InitPayload),
+    ResourceDeleted(Resource, Option<String>, String),
+    ResourceCreated(Resource, Option<String>, Account),
     ResourceDeletionFailed(Resource, HashMap<DataFieldType, Value>),
     ResourceCreationFailed(Resource, SessionEvent),
     ResourceDeletionStarted(Resource, HashMap<DataFieldType, Value>),
     ResourceCreationStarted(Resource, HashMap<DataFieldType, Value>),
-    ResourceFetched(Resource, HashMap<DataFieldType, Value>, Option<Value>),
+    ResourceFetched(Resource, Option<String>, Vec<Account>, Option<Value>),
+    LoginStepRecalled(Option<String>),
+    CredentialCaptured(PendingCredential),
+    CredentialDismissed,
+    PasswordGenerated(String),
+    PasswordChangeSubmitted(PasswordChange),
+    PasswordChangeSettled,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum ContentScriptAction {
     PathSet(Option<String>),
+    PickerRequested,
+    EquivalentDomainsLoaded(Vec<Vec<String>>),
+    StoreRoutesLoaded(Vec<StoreRoute>),
 }
 impl Reducer<ContentScriptStore> for ContentScriptAction {
     fn apply(self, state: Rc<ContentScriptStore>) -> Rc<ContentScriptStore> {
@@ -120,6 +164,23 @@ impl Reducer<ContentScriptStore> for ContentScriptAction {
                 ..state.deref().clone()
             }
             .into(),
+            ContentScriptAction::PickerRequested => ContentScriptStore {
+                picker_requests: state.picker_requests.wrapping_add(1),
+                ..state.deref().clone()
+            }
+            .into(),
+            ContentScriptAction::EquivalentDomainsLoaded(equivalent_domains) => {
+                ContentScriptStore {
+                    equivalent_domains,
+                    ..state.deref().clone()
+                }
+                .into()
+            }
+            ContentScriptAction::StoreRoutesLoaded(store_routes) => ContentScriptStore {
+                store_routes,
+                ..state.deref().clone()
+            }
+            .into(),
         }
     }
 }
@@ -138,47 +199,37 @@ impl Reducer<ContentScriptStore> for DataAction {
                 }
             }
             .into(),
-            DataAction::ResourceFetched(resource, mut data, _meta) => match resource {
+            DataAction::ResourceFetched(resource, store_id, accounts, meta) => match resource {
                 Resource::Account => {
-                    dbg!(&data);
-                    let state_data = state.data.clone();
-                    let accounts = data.remove(&DataFieldType::Data).unwrap_or_default();
-                    let accounts = serde_json::from_value::<Vec<Account>>(accounts)
-                        .unwrap_or_default()
+                    let accounts = accounts
                         .into_iter()
                         .map(|v| Rc::new(v))
                         .collect::<Vec<Rc<Account>>>();
-                    dbg!(&accounts);
-                    ContentScriptStore {
-                        page_loading: false,
-                        data: StoreData {
-                            storage_status: StorageStatus::Loaded,
-                            accounts: Mrc::new(accounts),
-                            ..state_data
-                        },
-                        ..state.deref().clone()
+                    let mut store = state.with_store_accounts(store_id, |store_accounts| {
+                        *store_accounts = accounts;
+                    });
+                    store.page_loading = false;
+                    store.data.storage_status = StorageStatus::Loaded;
+                    if let Some(custom_field_prefix) = meta
+                        .as_ref()
+                        .and_then(|meta| meta.get("custom_field_prefix"))
+                        .and_then(|prefix| prefix.as_str())
+                    {
+                        store.custom_field_prefix = Some(custom_field_prefix.to_owned());
                     }
-                    .into()
+                    store.into()
                 }
                 _ => {
                     todo!();
                 }
             },
-            DataAction::ResourceCreated(resource, mut data) => match resource {
+            DataAction::ResourceCreated(resource, store_id, account) => match resource {
                 Resource::Account => {
-                    let account = data.remove(&DataFieldType::Data).unwrap_or_default();
-                    let account = serde_json::from_value::<Account>(account).unwrap();
-                    let mut state_data = state.data.clone();
-                    let accounts = state_data.accounts.clone();
-                    accounts.borrow_mut().push(Rc::new(account));
-
-                    state_data.accounts = accounts;
-                    ContentScriptStore {
-                        page_loading: false,
-                        data: state_data,
-                        ..state.deref().clone()
-                    }
-                    .into()
+                    let mut store = state.with_store_accounts(store_id, |store_accounts| {
+                        store_accounts.push(Rc::new(account));
+                    });
+                    store.page_loading = false;
+                    store.into()
                 }
                 _ => ContentScriptStore {
                     ..state.deref().clone()
@@ -190,42 +241,27 @@ impl Reducer<ContentScriptStore> for DataAction {
                 ..state.deref().clone()
             }
             .into(),
-            DataAction::ResourceDeleted(resource, mut data) => {
-                let state_data = state.data.clone();
-                match resource {
-                    Resource::Account => {
-                        let account = data.remove(&DataFieldType::Data).unwrap_or_default();
-                        let account = serde_json::from_value::<Account>(account).unwrap();
-                        state_data
-                            .accounts
-                            .borrow_mut()
-                            .retain(|ac| account.id != ac.id);
-                        ContentScriptStore {
-                            page_loading: false,
-                            data: state_data,
-                            ..state.deref().clone()
-                        }
-                        .into()
-                    }
-                    _ => ContentScriptStore {
-                        ..state.deref().clone()
-                    }
-                    .into(),
+            DataAction::ResourceDeleted(resource, store_id, deleted_id) => match resource {
+                Resource::Account => {
+                    let mut store = state.with_store_accounts(store_id, |store_accounts| {
+                        store_accounts.retain(|ac| deleted_id != ac.id);
+                    });
+                    store.page_loading = false;
+                    store.into()
                 }
-            }
-            DataAction::Init(data) => {
-                let default_store_available = data
-                    .get(&DataFieldType::DefaultStoreAvailable)
-                    .map_or(false, |v| v.as_bool().unwrap_or(false));
-                let store_id = data
-                    .get(&DataFieldType::DefaultStoreID)
-                    .map_or(None, |v| v.as_str().map_or(None, |v| Some(v.to_string())));
+                _ => ContentScriptStore {
+                    ..state.deref().clone()
+                }
+                .into(),
+            },
+            DataAction::Init(payload) => {
                 ContentScriptStore {
                     data: StoreData {
-                        store_id,
-                        verified: default_store_available,
+                        store_id: payload.default_store_id,
+                        verified: payload.default_store_available || !payload.store_ids.is_empty(),
                         ..state.data.clone()
                     },
+                    store_ids: payload.store_ids,
                     ..state.deref().clone()
                 }
             }
@@ -237,6 +273,37 @@ impl Reducer<ContentScriptStore> for DataAction {
                 }
             }
             .into(),
+            DataAction::LoginStepRecalled(remembered_account_id) => ContentScriptStore {
+                remembered_account_id,
+                ..state.deref().clone()
+            }
+            .into(),
+            DataAction::CredentialCaptured(pending_credential) => ContentScriptStore {
+                pending_credential: Some(pending_credential),
+                ..state.deref().clone()
+            }
+            .into(),
+            DataAction::CredentialDismissed => ContentScriptStore {
+                pending_credential: None,
+                ..state.deref().clone()
+            }
+            .into(),
+            DataAction::PasswordGenerated(generated_password) => ContentScriptStore {
+                generated_password: Some(generated_password),
+                ..state.deref().clone()
+            }
+            .into(),
+            DataAction::PasswordChangeSubmitted(pending_password_change) => ContentScriptStore {
+                pending_password_change: Some(pending_password_change),
+                ..state.deref().clone()
+            }
+            .into(),
+            DataAction::PasswordChangeSettled => ContentScriptStore {
+                pending_password_change: None,
+                generated_password: None,
+                ..state.deref().clone()
+            }
+            .into(),
             _ => state,
         }
     }
@@ -298,8 +365,20 @@ impl Reducer<ContentScriptStore> for LoginAction {
                 ..store.deref().clone()
             }
             .into(),
-            LoginAction::Logout => ContentScriptStore {
+            //only the given store is locked, the others keep their accounts
+            LoginAction::Logout(Some(store_id)) => {
+                let mut store = store.deref().clone();
+                store.store_ids.retain(|id| *id != store_id);
+                store.store_accounts.remove(&store_id);
+                store.data.accounts = Mrc::new(merged_accounts(&store.store_accounts));
+                store.data.verified = !store.store_ids.is_empty();
+                store.page_loading = false;
+                store.into()
+            }
+            LoginAction::Logout(None) => ContentScriptStore {
                 page_loading: false,
+                store_ids: vec![],
+                store_accounts: BTreeMap::new(),
                 data: StoreData {
                     accounts: Mrc::new(vec![]),
                     verified: false,
@@ -309,16 +388,20 @@ impl Reducer<ContentScriptStore> for LoginAction {
                 ..store.deref().clone()
             }
             .into(),
-            LoginAction::Login(data) => {
+            LoginAction::Login(payload) => {
+                let mut store_ids = store.store_ids.clone();
+                if !store_ids.contains(&payload.store_id) {
+                    store_ids.push(payload.store_id.clone());
+                }
+                //logging in to another store leaves the default store as it is
+                let default_store_id = Some(payload.store_id)
+                    .filter(|_| payload.is_default)
+                    .or(store.data.store_id.clone());
                 ContentScriptStore {
+                    store_ids,
                     data: StoreData {
-                        accounts: Mrc::new(vec![]),
                         verified: true,
-                        store_id: data
-                            .as_ref()
-                            .and_then(|v| v.get(&DataFieldType::DefaultStoreID))
-                            .and_then(|v| v.as_str())
-                            .map(|v| v.to_string()),
+                        store_id: default_store_id,
                         ..store.deref().clone().data
                     },
                     page_loading: false,
@@ -346,7 +429,43 @@ impl Store for ContentScriptStore {
         self != old
     }
 }
+//accounts of all unlocked stores, ordered by store
+fn merged_accounts(store_accounts: &BTreeMap<String, Vec<Rc<Account>>>) -> Vec<Rc<Account>> {
+    store_accounts.values().flatten().cloned().collect()
+}
+
 impl ContentScriptStore {
+    //events without a store are about the default store
+    fn with_store_accounts<F>(&self, store_id: Option<String>, update: F) -> ContentScriptStore
+    where
+        F: FnOnce(&mut Vec<Rc<Account>>),
+    {
+        let mut store = self.clone();
+        if let Some(store_id) = store_id.or_else(|| self.data.store_id.clone()) {
+            update(store.store_accounts.entry(store_id).or_default());
+        }
+        store.data.accounts = Mrc::new(merged_accounts(&store.store_accounts));
+        store
+    }
+    //the unlocked store an account shown on the page comes from
+    pub fn account_store_id(&self, account: &Account) -> Option<String> {
+        self.store_accounts
+            .iter()
+            .find(|(_, accounts)| {
+                accounts
+                    .iter()
+                    .any(|store_account| std::ptr::eq(&**store_account, account))
+            })
+            .or_else(|| {
+                self.store_accounts.iter().find(|(_, accounts)| {
+                    accounts
+                        .iter()
+                        .any(|store_account| store_account.id == account.id)
+                })
+            })
+            .map(|(store_id, _)| store_id.clone())
+            .or_else(|| self.data.store_id.clone())
+    }
     pub fn load() {
         let acknowledgement = create_request_acknowledgement();
         let mut init_config = HashMap::new();
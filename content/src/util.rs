@@ -1,29 +1,61 @@
-use browser_rpass::{create_request_acknowledgement, response::RequestEnum, types::Resource};
+use browser_rpass::{
+    clipboard::copy_to_clipboard,
+    create_request_acknowledgement, get_domain_name,
+    request::{BrowserCommand, ContextMenuAction, ContextMenuRequest, DataFieldType},
+    response::{
+        GeneratePasswordResponse, LoginStepResponse, PendingCredentialResponse, RequestEnum,
+        ResponseEnum, Status,
+    },
+    store::{
+        add_never_save_domain, load_equivalent_domains, load_never_save_domains,
+        MESSAGE_ACKNOWLEDGEMENTS_POP_UP,
+    },
+    store_routing::{load_store_routes, route_for},
+    types::{Account, Resource},
+    url_match::account_matches_url,
+};
 use gloo_utils::{document, format::JsValueSerdeExt};
 use js_sys::Promise;
-use std::time::Duration;
+#[allow(unused_imports)]
+use log::*;
+use secrecy::ExposeSecret;
+use serde_json::json;
+use std::{cell::RefCell, collections::HashMap, rc::Rc, time::Duration};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::JsFuture;
-use web_sys::{window, HtmlDataListElement, HtmlInputElement, HtmlOptionElement};
+use web_sys::{
+    window, HtmlDataListElement, HtmlElement, HtmlInputElement, HtmlOptionElement,
+    HtmlTextAreaElement,
+};
 use yewdux::prelude::Dispatch;
 
-use crate::store::{ContentScriptStore, DataAction, EXTENSION_PORT};
-const USERNAME_INPUT_ELEMENT_ID_LIST: &[&str] = &[
-    "username",
-    "email",
-    "user",
-    "login",
-    "account",
-    "customer_email",
-    "user_id",
-    "id",
-    "user_name",
-    "user_email",
-    "user",
-    "identifierId",
+use crate::{
+    form_detection::{
+        find_change_password_form, find_login_forms, is_visible, query_selector_all_deep,
+        ChangePasswordForm,
+    },
+    store::{
+        ContentScriptAction, ContentScriptStore, DataAction, PasswordChange, PendingCredential,
+        EXTENSION_PORT,
+    },
+};
+thread_local! {
+    //the input the page's context menu was last opened on
+    static CONTEXT_MENU_TARGET: RefCell<Option<HtmlInputElement>> = RefCell::new(None);
+}
+const ONE_TIME_CODE_INPUT_ELEMENT_ID_LIST: &[&str] = &[
+    "otp",
+    "totp",
+    "one-time-code",
+    "one_time_code",
+    "otc",
+    "mfa",
+    "2fa",
+    "two_factor_code",
+    "verification_code",
+    "verificationCode",
+    "authenticator_code",
 ];
-const PASSWORD_INPUT_ELEMENT_ID_LIST: &[&str] =
-    &["password", "pass", "pwd", "secret", "token", "auth"];
 
 #[allow(dead_code)]
 pub async fn sleep(duration: Duration) {
@@ -47,59 +79,93 @@ macro_rules! log {
     }
 }
 
-pub enum InputElementType {
-    #[allow(dead_code)]
-    Text,
-    Email,
-    Password,
-}
-impl InputElementType {
-    fn to_string(&self) -> &'static str {
-        match self {
-            InputElementType::Text => "text",
-            InputElementType::Email => "email",
-            InputElementType::Password => "password",
-        }
-    }
-}
 pub fn find_input_element_with_candidate_ids(id_list: &[&str]) -> Option<HtmlInputElement> {
     id_list
         .into_iter()
-        .find_map(|candidate| document().get_element_by_id(candidate))
+        .find_map(|candidate| {
+            query_selector_all_deep(&format!("[id='{}']", candidate))
+                .into_iter()
+                .next()
+        })
         .and_then(|element| element.dyn_into::<HtmlInputElement>().ok())
 }
-pub fn find_email_type_input_element() -> Option<Vec<HtmlInputElement>> {
-    return find_input_element_with_type(InputElementType::Email);
-}
-pub fn find_input_element_with_type(input_type: InputElementType) -> Option<Vec<HtmlInputElement>> {
-    let input_elements = document().get_elements_by_tag_name("input");
-    let length = input_elements.length();
-    let mut res = Vec::new();
-    for i in 0..length {
-        if let Some(input_element) = input_elements.get_with_index(i) {
-            let input_element = input_element.dyn_into::<HtmlInputElement>().ok();
-            if let Some(input_element) = input_element {
-                if input_element.type_() == input_type.to_string() {
-                    res.push(input_element);
-                }
-            }
-        }
-    }
-    if res.len() > 0 {
-        return Some(res);
-    }
-    return None;
+pub fn find_one_time_code_input_element() -> Option<HtmlInputElement> {
+    query_selector_all_deep("input[autocomplete='one-time-code']")
+        .into_iter()
+        .next()
+        .and_then(|element| element.dyn_into::<HtmlInputElement>().ok())
+        .or_else(|| find_input_element_with_candidate_ids(ONE_TIME_CODE_INPUT_ELEMENT_ID_LIST))
 }
-pub fn find_password_input_element() -> Option<HtmlInputElement> {
-    let by_type = find_input_element_with_type(InputElementType::Password);
-    if let Some(by_type) = by_type {
-        return Some(by_type[0].clone());
+//the domain of this frame, which is not the tab's for a cross-origin iframe
+pub fn current_domain() -> String {
+    get_domain_name(&current_url())
+}
+pub fn current_url() -> String {
+    window().unwrap().location().href().unwrap_or_default()
+}
+//whether an account belongs on this frame's page, by the account's url match mode
+pub fn matches_page(account: &Account) -> bool {
+    let state = Dispatch::<ContentScriptStore>::new().get();
+    account_matches_url(account, &current_url(), &state.equivalent_domains)
+}
+pub fn fetch_equivalent_domains() {
+    wasm_bindgen_futures::spawn_local(async {
+        let equivalent_domains = load_equivalent_domains().await;
+        Dispatch::<ContentScriptStore>::new().apply(ContentScriptAction::EquivalentDomainsLoaded(
+            equivalent_domains,
+        ));
+    });
+}
+pub fn fetch_store_routes() {
+    wasm_bindgen_futures::spawn_local(async {
+        let store_routes = load_store_routes().await;
+        Dispatch::<ContentScriptStore>::new()
+            .apply(ContentScriptAction::StoreRoutesLoaded(store_routes));
+    });
+}
+//accounts of the stores the site's rule lists come first, in the rule's order
+pub fn sort_by_route(state: &ContentScriptStore, accounts: &mut Vec<Rc<Account>>) {
+    if let Some(route) = route_for(&state.store_routes, &current_domain()) {
+        accounts.sort_by_cached_key(|account| route.rank(state.account_store_id(account).as_ref()));
     }
-    find_input_element_with_candidate_ids(PASSWORD_INPUT_ELEMENT_ID_LIST)
 }
-pub fn find_username_input_element() -> Option<HtmlInputElement> {
-    find_input_element_with_candidate_ids(USERNAME_INPUT_ELEMENT_ID_LIST)
-        .or(find_email_type_input_element().map(|mut v| v.remove(0)))
+//the unlocked store the site's rule saves to, the default store otherwise
+pub fn save_store_id(state: &ContentScriptStore) -> Option<String> {
+    route_for(&state.store_routes, &current_domain())
+        .and_then(|route| route.save_store(&state.store_ids))
+        .cloned()
+        .or_else(|| state.data.store_id.clone())
+}
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DetectedFields {
+    pub username: Option<HtmlInputElement>,
+    pub password: Option<HtmlInputElement>,
+    pub one_time_code: Option<HtmlInputElement>,
+    pub change_password: Option<ChangePasswordForm>,
+}
+//fields of the most likely login form on the page
+pub fn detect_fields() -> DetectedFields {
+    //the current password field of a change-password form would pass for a login form
+    if let Some(change_password) = find_change_password_form() {
+        return DetectedFields {
+            change_password: Some(change_password),
+            one_time_code: find_one_time_code_input_element(),
+            ..Default::default()
+        };
+    }
+    let login_form = find_login_forms().into_iter().next();
+    DetectedFields {
+        username: login_form
+            .as_ref()
+            .and_then(|login_form| login_form.username.as_ref())
+            .map(|field| field.element.clone()),
+        password: login_form
+            .as_ref()
+            .and_then(|login_form| login_form.password.as_ref())
+            .map(|field| field.element.clone()),
+        one_time_code: find_one_time_code_input_element(),
+        change_password: None,
+    }
 }
 pub fn _create_autocomplete_suggestion_element(
     id: &str,
@@ -167,3 +233,615 @@ pub fn fetch_accounts(store_id: Option<String>, path: Option<String>) -> String
         .post_message(<JsValue as JsValueSerdeExt>::from_serde(&fetch_request).unwrap());
     return acknowledgement;
 }
+
+//fill the one-time code input with the current code of the account. the code is generated by
+//the native app so that the secret never reaches the page
+pub fn fill_one_time_code(store_id: Option<String>, id: String) -> String {
+    request_one_time_code(store_id, id, |code| {
+        if let Some(input_element) = find_one_time_code_input_element() {
+            input_element.set_value(&code);
+        }
+    })
+}
+pub fn copy_one_time_code(store_id: Option<String>, id: String) -> String {
+    request_one_time_code(store_id, id, copy_text)
+}
+//copy to the clipboard, which the service worker clears again after the delay set in the options
+pub fn copy_text(text: String) {
+    let port = EXTENSION_PORT.lock().borrow().clone();
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Err(err) = copy_to_clipboard(&port, &text).await {
+            error!("failed to copy to the clipboard: {:?}", err);
+        }
+    });
+}
+fn request_one_time_code<F>(store_id: Option<String>, id: String, on_code: F) -> String
+where
+    F: FnOnce(String) + Send + 'static,
+{
+    let acknowledgement = create_request_acknowledgement();
+    let get_request = RequestEnum::create_get_request(
+        id,
+        Resource::Totp,
+        Some(acknowledgement.clone()),
+        None,
+        store_id,
+    );
+    MESSAGE_ACKNOWLEDGEMENTS_POP_UP.lock().unwrap().insert(
+        acknowledgement.clone(),
+        Box::new(move |response: ResponseEnum, _port| {
+            Box::pin(async move {
+                let ResponseEnum::GetResponse(response) = response else {
+                    debug!("failed to get one-time code: {:?}", response);
+                    return;
+                };
+                let code = response
+                    .data
+                    .get(&DataFieldType::Value)
+                    .and_then(|v| v.as_str().map(|v| v.to_owned()));
+                match (&response.status, code) {
+                    (Status::Success, Some(code)) => on_code(code),
+                    _ => {
+                        debug!("failed to get one-time code: {:?}", response.data);
+                    }
+                }
+            })
+        }),
+    );
+    EXTENSION_PORT
+        .lock()
+        .borrow()
+        .post_message(<JsValue as JsValueSerdeExt>::from_serde(&get_request).unwrap());
+    return acknowledgement;
+}
+
+//lets the service worker know whether this frame holds a login form, so fills from the popup
+//reach the frame with the form instead of the top frame
+pub fn report_frame_status(store_id: Option<String>, has_login_form: bool) {
+    let frame_status_request =
+        RequestEnum::create_frame_status_request(store_id, has_login_form, None);
+    EXTENSION_PORT
+        .lock()
+        .borrow()
+        .post_message(<JsValue as JsValueSerdeExt>::from_serde(&frame_status_request).unwrap());
+}
+
+//fill an account sent by the service worker. the service worker routes by the frame's url, but
+//the frame checks its own origin as well before handing out the password
+pub fn fill_account(store_id: Option<String>, account: &Account) {
+    if !matches_page(account) {
+        warn!("refused to fill an account of {:?}", account.domain);
+        return;
+    }
+    let fields = detect_fields();
+    if let Some(username_input_element) = fields.username.as_ref() {
+        username_input_element.set_value(&account.username);
+    }
+    if let Some(password_input_element) = fields.password.as_ref() {
+        password_input_element.set_value(
+            &account
+                .get_password()
+                .map(|s| s.expose_secret().clone())
+                .unwrap_or(String::new()),
+        );
+    } else {
+        //username-only page, the password comes on the next step
+        remember_login_step(store_id, current_domain(), account.id.clone());
+    }
+    fill_custom_fields(account, &fields);
+}
+
+//custom fields of the account go into the visible inputs named after them in the form being
+//filled, such as a customer number asked for next to the password. secret fields are only
+//copied from the popup, so a page can't collect them with inputs of the same name
+fn fill_custom_fields(account: &Account, fields: &DetectedFields) {
+    let Some(form) = fields
+        .password
+        .as_ref()
+        .or(fields.username.as_ref())
+        .and_then(|input_element| input_element.form())
+    else {
+        return;
+    };
+    let custom_field_prefix = Dispatch::<ContentScriptStore>::new()
+        .get()
+        .custom_field_prefix
+        .clone();
+    let custom_fields = account
+        .typed_custom_fields(custom_field_prefix.as_deref())
+        .into_iter()
+        .filter(|field| !field.value.is_empty() && !field.field_type.is_secret())
+        .collect::<Vec<_>>();
+    if custom_fields.is_empty() {
+        return;
+    }
+    let field_value = |name: String| {
+        custom_fields
+            .iter()
+            .find(|field| !name.is_empty() && field.name.eq_ignore_ascii_case(&name))
+            .map(|field| field.value.clone())
+    };
+    let Ok(elements) = form.query_selector_all("input, textarea") else {
+        return;
+    };
+    for index in 0..elements.length() {
+        let Some(element) = elements
+            .get(index)
+            .and_then(|element| element.dyn_into::<HtmlElement>().ok())
+        else {
+            continue;
+        };
+        if !is_visible(&element) {
+            continue;
+        }
+        if let Some(input_element) = element.dyn_ref::<HtmlInputElement>() {
+            if input_element.type_() == "hidden" {
+                continue;
+            }
+            if let Some(value) = field_value(input_element.name()) {
+                input_element.set_value(&value);
+            }
+        } else if let Some(textarea_element) = element.dyn_ref::<HtmlTextAreaElement>() {
+            if let Some(value) = field_value(textarea_element.name()) {
+                textarea_element.set_value(&value);
+            }
+        }
+    }
+}
+
+//remember the account picked on the username page of a multi-step login, so the next page of
+//the same site in this tab can fill its password
+pub fn remember_login_step(store_id: Option<String>, domain: String, account_id: String) -> String {
+    let acknowledgement = create_request_acknowledgement();
+    let login_step_request = RequestEnum::create_login_step_request(
+        store_id,
+        domain,
+        Some(account_id),
+        Some(acknowledgement.clone()),
+    );
+    EXTENSION_PORT
+        .lock()
+        .borrow()
+        .post_message(<JsValue as JsValueSerdeExt>::from_serde(&login_step_request).unwrap());
+    return acknowledgement;
+}
+
+pub fn recall_login_step(store_id: Option<String>, domain: String) -> String {
+    let acknowledgement = create_request_acknowledgement();
+    let login_step_request = RequestEnum::create_login_step_request(
+        store_id,
+        domain,
+        None,
+        Some(acknowledgement.clone()),
+    );
+    MESSAGE_ACKNOWLEDGEMENTS_POP_UP.lock().unwrap().insert(
+        acknowledgement.clone(),
+        Box::new(|response: ResponseEnum, _port| {
+            Box::pin(async move {
+                let account_id = match response {
+                    ResponseEnum::LoginStepResponse(LoginStepResponse {
+                        login_step: Some(login_step),
+                        ..
+                    }) => login_step.payload.account_id,
+                    _ => None,
+                };
+                let dispatch = Dispatch::<ContentScriptStore>::new();
+                dispatch.apply(DataAction::LoginStepRecalled(account_id));
+            })
+        }),
+    );
+    EXTENSION_PORT
+        .lock()
+        .borrow()
+        .post_message(<JsValue as JsValueSerdeExt>::from_serde(&login_step_request).unwrap());
+    return acknowledgement;
+}
+
+//hand the submitted credentials to the service worker as well, since the submission usually
+//navigates away before the user gets to answer the banner
+pub fn capture_credential(credential: PendingCredential) {
+    wasm_bindgen_futures::spawn_local(async move {
+        if load_never_save_domains().await.contains(&credential.domain) {
+            return;
+        }
+        let dispatch = Dispatch::<ContentScriptStore>::new();
+        if dispatch.get().pending_credential.as_ref() == Some(&credential) {
+            return;
+        }
+        let pending_credential_request = RequestEnum::create_pending_credential_request(
+            save_store_id(&dispatch.get()),
+            credential.domain.clone(),
+            credential.username.clone(),
+            Some(credential.password.clone()),
+            None,
+            None,
+        );
+        EXTENSION_PORT.lock().borrow().post_message(
+            <JsValue as JsValueSerdeExt>::from_serde(&pending_credential_request).unwrap(),
+        );
+        dispatch.apply(DataAction::CredentialCaptured(credential));
+    });
+}
+
+pub fn take_pending_credential(store_id: Option<String>, domain: String) -> String {
+    let acknowledgement = create_request_acknowledgement();
+    let pending_credential_request = RequestEnum::create_pending_credential_request(
+        store_id.clone(),
+        domain,
+        None,
+        None,
+        None,
+        Some(acknowledgement.clone()),
+    );
+    MESSAGE_ACKNOWLEDGEMENTS_POP_UP.lock().unwrap().insert(
+        acknowledgement.clone(),
+        Box::new(move |response: ResponseEnum, _port| {
+            Box::pin(async move {
+                if let ResponseEnum::PendingCredentialResponse(PendingCredentialResponse {
+                    credential: Some(pending_credential),
+                    ..
+                }) = response
+                {
+                    let pending_credential = pending_credential.payload;
+                    let credential = PendingCredential {
+                        domain: pending_credential.domain,
+                        username: pending_credential.username,
+                        password: pending_credential.password,
+                    };
+                    match pending_credential.account_id {
+                        //a change-password form that is shown again after submitting most
+                        //likely rejected the new password
+                        Some(account_id) => {
+                            if find_change_password_form().is_none() {
+                                update_password(
+                                    pending_credential.store_id.or(store_id),
+                                    account_id,
+                                    credential,
+                                );
+                            }
+                        }
+                        None => {
+                            let dispatch = Dispatch::<ContentScriptStore>::new();
+                            dispatch.apply(DataAction::CredentialCaptured(credential));
+                        }
+                    }
+                }
+            })
+        }),
+    );
+    EXTENSION_PORT.lock().borrow().post_message(
+        <JsValue as JsValueSerdeExt>::from_serde(&pending_credential_request).unwrap(),
+    );
+    return acknowledgement;
+}
+
+pub fn save_credential(store_id: Option<String>, credential: PendingCredential) -> String {
+    let acknowledgement = create_request_acknowledgement();
+    let create_request = RequestEnum::create_create_request(
+        store_id,
+        credential.username,
+        Some(credential.domain),
+        None,
+        None,
+        Resource::Account,
+        Some(credential.password),
+        Some(acknowledgement.clone()),
+        None,
+    );
+    EXTENSION_PORT
+        .lock()
+        .borrow()
+        .post_message(<JsValue as JsValueSerdeExt>::from_serde(&create_request).unwrap());
+    Dispatch::<ContentScriptStore>::new().apply(DataAction::CredentialDismissed);
+    return acknowledgement;
+}
+
+//only the password is sent, so the other fields of the entry are left as they are. the old
+//password goes to the entry's history, in case the site didn't take the new one after all
+pub fn update_password(
+    store_id: Option<String>,
+    id: String,
+    credential: PendingCredential,
+) -> String {
+    let acknowledgement = create_request_acknowledgement();
+    let mut data = HashMap::new();
+    data.insert(DataFieldType::ResourceID, json!(id.clone()));
+    data.insert(DataFieldType::Password, json!(credential.password));
+    data.insert(DataFieldType::KeepPasswordHistory, json!(true));
+    let edit_request = RequestEnum::create_edit_request(
+        id,
+        Resource::Account,
+        Some(credential.domain),
+        data,
+        Some(acknowledgement.clone()),
+        None,
+        store_id,
+    );
+    EXTENSION_PORT
+        .lock()
+        .borrow()
+        .post_message(<JsValue as JsValueSerdeExt>::from_serde(&edit_request).unwrap());
+    Dispatch::<ContentScriptStore>::new().apply(DataAction::CredentialDismissed);
+    return acknowledgement;
+}
+
+pub fn never_save_credential(domain: String) {
+    Dispatch::<ContentScriptStore>::new().apply(DataAction::CredentialDismissed);
+    wasm_bindgen_futures::spawn_local(add_never_save_domain(domain));
+}
+
+//the account a form on this page is for: the one with the username typed in the form, the one
+//picked on the previous login step, or the only account of the site
+pub fn match_account(username: Option<String>) -> Option<Rc<Account>> {
+    let state = Dispatch::<ContentScriptStore>::new().get();
+    let accounts = site_accounts();
+    let matched = match username.filter(|username| !username.is_empty()) {
+        Some(username) => accounts
+            .iter()
+            .find(|account| account.username == username)
+            .cloned(),
+        None => state.remembered_account_id.as_ref().and_then(|account_id| {
+            accounts
+                .iter()
+                .find(|account| &account.id == account_id)
+                .cloned()
+        }),
+    };
+    match matched {
+        Some(account) => Some(account),
+        None if accounts.len() == 1 => accounts.into_iter().next(),
+        None => None,
+    }
+}
+
+//accounts of the site this frame is on, the one picked on the previous login step first
+pub fn site_accounts() -> Vec<Rc<Account>> {
+    let state = Dispatch::<ContentScriptStore>::new().get();
+    let mut accounts = state
+        .data
+        .accounts
+        .borrow()
+        .iter()
+        .filter(|account| matches_page(account))
+        .cloned()
+        .collect::<Vec<Rc<Account>>>();
+    sort_by_route(&state, &mut accounts);
+    if let Some(remembered_account_id) = state.remembered_account_id.as_ref() {
+        accounts.sort_by_key(|account| &account.id != remembered_account_id);
+    }
+    accounts
+}
+
+pub fn run_command(command: BrowserCommand) {
+    let dispatch = Dispatch::<ContentScriptStore>::new();
+    let username = detect_fields()
+        .username
+        .map(|element| element.value())
+        .filter(|username| !username.is_empty());
+    match command {
+        BrowserCommand::FillLogin => match match_account(username) {
+            Some(account) => fill_account(dispatch.get().account_store_id(&account), &account),
+            None if !site_accounts().is_empty() => {
+                dispatch.apply(ContentScriptAction::PickerRequested)
+            }
+            None => debug!("no account for {}", current_domain()),
+        },
+        BrowserCommand::CycleLogin => {
+            let accounts = site_accounts();
+            let current = username.and_then(|username| {
+                accounts
+                    .iter()
+                    .position(|account| account.username == username)
+            });
+            let next = current.map_or(0, |index| index + 1) % accounts.len().max(1);
+            if let Some(account) = accounts.get(next) {
+                fill_account(dispatch.get().account_store_id(account), account);
+            }
+        }
+    }
+}
+
+pub fn generate_password(store_id: Option<String>) -> String {
+    request_generated_password(store_id, |password| {
+        let dispatch = Dispatch::<ContentScriptStore>::new();
+        dispatch.apply(DataAction::PasswordGenerated(password));
+    })
+}
+fn request_generated_password<F>(store_id: Option<String>, on_generated: F) -> String
+where
+    F: FnOnce(String) + Send + 'static,
+{
+    let acknowledgement = create_request_acknowledgement();
+    let generate_password_request = RequestEnum::create_generate_password_request(
+        store_id,
+        None,
+        Some(acknowledgement.clone()),
+        None,
+    );
+    MESSAGE_ACKNOWLEDGEMENTS_POP_UP.lock().unwrap().insert(
+        acknowledgement.clone(),
+        Box::new(move |response: ResponseEnum, _port| {
+            Box::pin(async move {
+                match response {
+                    ResponseEnum::GeneratePasswordResponse(GeneratePasswordResponse {
+                        generated: Some(generated),
+                        ..
+                    }) => on_generated(generated.payload.password),
+                    response => {
+                        debug!("failed to generate password: {:?}", response);
+                    }
+                }
+            })
+        }),
+    );
+    EXTENSION_PORT.lock().borrow().post_message(
+        <JsValue as JsValueSerdeExt>::from_serde(&generate_password_request).unwrap(),
+    );
+    return acknowledgement;
+}
+
+//the entry is only updated once the change-password form is gone, either on this page or on
+//the page that follows the submission, so the change is handed to the service worker as well
+pub fn capture_password_change(change_password: &ChangePasswordForm) {
+    let new_password = change_password.new_password.value();
+    let confirmed = change_password
+        .confirm_password
+        .as_ref()
+        .map_or(true, |confirm| confirm.value() == new_password);
+    if new_password.is_empty()
+        || !confirmed
+        || change_password.current_password.value() == new_password
+    {
+        return;
+    }
+    let username = change_password
+        .username
+        .as_ref()
+        .map(|username| username.value());
+    let account = match match_account(username) {
+        Some(account) => account,
+        None => return,
+    };
+    let dispatch = Dispatch::<ContentScriptStore>::new();
+    let password_change = PasswordChange {
+        store_id: dispatch.get().account_store_id(&account),
+        account_id: account.id.clone(),
+        //the next page looks the change up by its own domain, which may not be the account's
+        credential: PendingCredential {
+            domain: current_domain(),
+            username: Some(account.username.clone()),
+            password: new_password,
+        },
+    };
+    if dispatch.get().pending_password_change.as_ref() == Some(&password_change) {
+        return;
+    }
+    let pending_credential_request = RequestEnum::create_pending_credential_request(
+        password_change.store_id.clone(),
+        password_change.credential.domain.clone(),
+        password_change.credential.username.clone(),
+        Some(password_change.credential.password.clone()),
+        Some(password_change.account_id.clone()),
+        None,
+    );
+    EXTENSION_PORT.lock().borrow().post_message(
+        <JsValue as JsValueSerdeExt>::from_serde(&pending_credential_request).unwrap(),
+    );
+    dispatch.apply(DataAction::PasswordChangeSubmitted(password_change));
+}
+
+pub fn commit_password_change(password_change: PasswordChange) {
+    let store_id = password_change.store_id.clone();
+    //take back the copy kept by the service worker, so the next page doesn't commit it again
+    let pending_credential_request = RequestEnum::create_pending_credential_request(
+        store_id.clone(),
+        password_change.credential.domain.clone(),
+        None,
+        None,
+        None,
+        None,
+    );
+    EXTENSION_PORT.lock().borrow().post_message(
+        <JsValue as JsValueSerdeExt>::from_serde(&pending_credential_request).unwrap(),
+    );
+    update_password(
+        store_id,
+        password_change.account_id,
+        password_change.credential,
+    );
+    Dispatch::<ContentScriptStore>::new().apply(DataAction::PasswordChangeSettled);
+}
+
+pub fn set_context_menu_target(target: Option<HtmlInputElement>) {
+    CONTEXT_MENU_TARGET.with(|context_menu_target| *context_menu_target.borrow_mut() = target);
+}
+fn context_menu_target() -> Option<HtmlInputElement> {
+    CONTEXT_MENU_TARGET.with(|context_menu_target| context_menu_target.borrow().clone())
+}
+
+//an entry of the right-click menu. entries of an account act on the input the menu was opened
+//on, falling back to the detected login form
+pub fn handle_context_menu(request: ContextMenuRequest) {
+    let domain = current_domain();
+    if let Some(account) = request.account.as_ref() {
+        if !matches_page(account) {
+            warn!(
+                "refused {:?} of an account of {:?}",
+                request.action, account.domain
+            );
+            return;
+        }
+    }
+    let fields = detect_fields();
+    let store_id = request.store_id.clone().or_else(|| {
+        Dispatch::<ContentScriptStore>::new()
+            .get()
+            .data
+            .store_id
+            .clone()
+    });
+    match (request.action, request.account) {
+        (ContextMenuAction::FillUsername, Some(account)) => {
+            if let Some(target) = context_menu_target().or(fields.username) {
+                target.set_value(&account.username);
+            }
+        }
+        (ContextMenuAction::FillPassword, Some(account)) => {
+            if let Some(target) = context_menu_target().or(fields.password) {
+                target.set_value(
+                    &account
+                        .get_password()
+                        .map(|s| s.expose_secret().clone())
+                        .unwrap_or(String::new()),
+                );
+            }
+        }
+        (ContextMenuAction::GeneratePassword, account) => {
+            let username = account.map(|account| account.username);
+            request_generated_password(store_id, move |password| {
+                let fields = detect_fields();
+                let target = context_menu_target().or(fields.password.clone());
+                if let Some(target) = target.as_ref() {
+                    target.set_value(&password);
+                }
+                //a change-password form asks for the new password twice
+                if let Some(change_password) = fields.change_password.as_ref() {
+                    if target.as_ref() == Some(&change_password.new_password) {
+                        if let Some(confirm_password) = change_password.confirm_password.as_ref() {
+                            confirm_password.set_value(&password);
+                        }
+                    }
+                }
+                if let (Some(username), Some(username_input_element)) =
+                    (username, fields.username.as_ref())
+                {
+                    if username_input_element.value().is_empty() {
+                        username_input_element.set_value(&username);
+                    }
+                }
+            });
+        }
+        (ContextMenuAction::CopyTotp, Some(account)) => {
+            copy_one_time_code(store_id, account.id);
+        }
+        (ContextMenuAction::SaveLogin, _) => {
+            let value = |element: Option<HtmlInputElement>| {
+                element
+                    .map(|element| element.value())
+                    .filter(|value| !value.is_empty())
+            };
+            match value(fields.password) {
+                //asked for explicitly, so the never-save list does not apply
+                Some(password) => Dispatch::<ContentScriptStore>::new().apply(
+                    DataAction::CredentialCaptured(PendingCredential {
+                        domain,
+                        username: value(fields.username),
+                        password,
+                    }),
+                ),
+                None => debug!("no password to save on {}", domain),
+            }
+        }
+        (action, None) => debug!("{:?} needs an account", action),
+    }
+}
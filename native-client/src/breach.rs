@@ -0,0 +1,306 @@
+use std::{
+    cmp::Ordering,
+    fs::File,
+    io::{BufRead, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use md4::Md4;
+use rpass::pass;
+use sha1::{Digest, Sha1};
+
+/// Environment variable pointing at the local copy of the "Pwned Passwords" hash list.
+pub const BREACH_LIST_ENV: &str = "PASSWORD_STORE_BREACH_LIST";
+const RANGE_PREFIX_LENGTH: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BreachHashType {
+    Sha1,
+    Ntlm,
+}
+impl BreachHashType {
+    fn from_hash_length(length: usize) -> Option<Self> {
+        match length {
+            40 => Some(BreachHashType::Sha1),
+            32 => Some(BreachHashType::Ntlm),
+            _ => None,
+        }
+    }
+    pub fn hash(&self, password: &str) -> String {
+        match self {
+            BreachHashType::Sha1 => hex::encode_upper(Sha1::digest(password.as_bytes())),
+            BreachHashType::Ntlm => {
+                let utf16 = password
+                    .encode_utf16()
+                    .flat_map(|unit| unit.to_le_bytes())
+                    .collect::<Vec<u8>>();
+                hex::encode_upper(Md4::digest(&utf16))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+enum BreachListSource {
+    //a single file of `HASH:COUNT` lines sorted by hash, as written by the HIBP downloader
+    SortedFile { path: PathBuf, length: u64 },
+    //a directory of range files named after the first five hex digits of the hash, each
+    //holding sorted `SUFFIX:COUNT` lines like the responses of the range API
+    RangeDirectory { path: PathBuf },
+}
+
+/// Local, offline copy of the HIBP "Pwned Passwords" list. Lookups binary search the sorted
+/// list on disk, so the list is never loaded into memory and nothing is sent over the network.
+#[derive(Debug)]
+pub struct BreachList {
+    source: BreachListSource,
+    pub hash_type: BreachHashType,
+}
+
+impl BreachList {
+    pub fn open(path: &Path) -> pass::Result<Self> {
+        if path.is_dir() {
+            let hash_type = detect_range_directory_hash_type(path)?;
+            return Ok(BreachList {
+                source: BreachListSource::RangeDirectory {
+                    path: path.to_path_buf(),
+                },
+                hash_type,
+            });
+        }
+        let file = File::open(path)?;
+        let length = file.metadata()?.len();
+        let mut first_line = String::new();
+        BufReader::new(file).read_line(&mut first_line)?;
+        let hash_type = BreachHashType::from_hash_length(line_hash(&first_line).len()).ok_or(
+            pass::Error::from(
+                "breach list must hold sorted SHA-1 or NTLM hashes in the `HASH:COUNT` format",
+            ),
+        )?;
+        Ok(BreachList {
+            source: BreachListSource::SortedFile {
+                path: path.to_path_buf(),
+                length,
+            },
+            hash_type,
+        })
+    }
+    /// Opens the list configured through `PASSWORD_STORE_BREACH_LIST`, falling back to
+    /// `pwned-passwords.txt` next to the native client's logs. Returns None if neither exists.
+    pub fn from_env(home: &Option<PathBuf>) -> pass::Result<Option<Self>> {
+        let path = match std::env::var(BREACH_LIST_ENV) {
+            Ok(path) => PathBuf::from(path),
+            Err(_) => match home {
+                Some(home) => home.join("rpass/browser-rpass/native-client/pwned-passwords.txt"),
+                None => return Ok(None),
+            },
+        };
+        if !path.exists() {
+            return Ok(None);
+        }
+        BreachList::open(&path).map(Some)
+    }
+    /// Returns how many times the password appears in the breach corpus, 0 if it doesn't.
+    pub fn occurrences(&self, password: &str) -> pass::Result<u64> {
+        let hash = self.hash_type.hash(password);
+        match &self.source {
+            BreachListSource::SortedFile { path, length } => {
+                search_sorted_file(path, *length, &hash)
+            }
+            BreachListSource::RangeDirectory { path } => {
+                let (prefix, suffix) = hash.split_at(RANGE_PREFIX_LENGTH);
+                let range_path = path.join(format!("{}.txt", prefix));
+                if !range_path.exists() {
+                    return Ok(0);
+                }
+                let mut range = String::new();
+                File::open(range_path)?.read_to_string(&mut range)?;
+                let lines = range
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .collect::<Vec<&str>>();
+                Ok(lines
+                    .binary_search_by(|line| compare_hash(line_hash(line), suffix))
+                    .map(|index| line_count(lines[index]))
+                    .unwrap_or(0))
+            }
+        }
+    }
+}
+
+fn detect_range_directory_hash_type(path: &Path) -> pass::Result<BreachHashType> {
+    for entry in std::fs::read_dir(path)? {
+        let entry_path = entry?.path();
+        if entry_path.extension().is_some_and(|ext| ext == "txt") {
+            let mut first_line = String::new();
+            BufReader::new(File::open(&entry_path)?).read_line(&mut first_line)?;
+            if let Some(hash_type) =
+                BreachHashType::from_hash_length(RANGE_PREFIX_LENGTH + line_hash(&first_line).len())
+            {
+                return Ok(hash_type);
+            }
+        }
+    }
+    Err(pass::Error::from(
+        "breach list directory holds no SHA-1 or NTLM range files",
+    ))
+}
+
+fn line_hash(line: &str) -> &str {
+    line.split(':').next().unwrap_or_default().trim()
+}
+fn line_count(line: &str) -> u64 {
+    line.split(':')
+        .nth(1)
+        .and_then(|count| count.trim().parse::<u64>().ok())
+        .unwrap_or(1)
+}
+fn compare_hash(line_hash: &str, hash: &str) -> Ordering {
+    line_hash
+        .bytes()
+        .map(|b| b.to_ascii_uppercase())
+        .cmp(hash.bytes())
+}
+
+//binary search over byte offsets: for any offset, the line that matters is the first one starting
+//at or after it, which keeps the search correct even though lines differ in length
+fn search_sorted_file(path: &Path, length: u64, hash: &str) -> pass::Result<u64> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut low = 0;
+    let mut high = length;
+    while low < high {
+        let middle = low + (high - low) / 2;
+        match read_line_from(&mut reader, middle)? {
+            Some(line) if compare_hash(line_hash(&line), hash) == Ordering::Less => {
+                low = middle + 1
+            }
+            _ => high = middle,
+        }
+    }
+    match read_line_from(&mut reader, low)? {
+        Some(line) if compare_hash(line_hash(&line), hash) == Ordering::Equal => {
+            Ok(line_count(&line))
+        }
+        _ => Ok(0),
+    }
+}
+
+fn read_line_from<R: BufRead + Seek>(reader: &mut R, offset: u64) -> pass::Result<Option<String>> {
+    let mut line = String::new();
+    if offset == 0 {
+        reader.seek(SeekFrom::Start(0))?;
+    } else {
+        //skip the rest of the line the byte before the offset belongs to
+        reader.seek(SeekFrom::Start(offset - 1))?;
+        reader.read_line(&mut line)?;
+        line.clear();
+    }
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_passwords_like_the_hibp_lists() {
+        assert_eq!(
+            BreachHashType::Sha1.hash("password"),
+            "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8"
+        );
+        assert_eq!(
+            BreachHashType::Ntlm.hash("password"),
+            "8846F7EAEE8FB117AD06BDD830B7586C"
+        );
+    }
+
+    //a few sorted sha-1 lines around the hash of "password"
+    const SORTED_LIST: &str = "\
+000000005AD76BD555C1D6D771DE417A4B87E4B4:10
+5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD7:2
+5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8:9659365
+5baa61e4c9b93f3f0682250b6cf8331b7ee68fd9:3
+FFFFFFFF8A4B8E4B2E9A3D7E4B1C0C1E5A2B3C4D:1
+";
+
+    fn fixture_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "browser-rpass-breach-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = std::fs::remove_dir_all(&path);
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn looks_up_passwords_in_a_sorted_file() {
+        let path = fixture_path("sorted.txt");
+        std::fs::write(&path, SORTED_LIST).unwrap();
+        let list = BreachList::open(&path).unwrap();
+        assert_eq!(list.hash_type, BreachHashType::Sha1);
+        assert_eq!(list.occurrences("password").unwrap(), 9659365);
+        assert_eq!(list.occurrences("not in the list").unwrap(), 0);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn looks_up_passwords_in_a_range_directory() {
+        let path = fixture_path("ranges");
+        std::fs::create_dir(&path).unwrap();
+        std::fs::write(
+            path.join("5BAA6.txt"),
+            "\
+1E4C9B93F3F0682250B6CF8331B7EE68FD7:2
+1E4C9B93F3F0682250B6CF8331B7EE68FD8:9659365
+1E4C9B93F3F0682250B6CF8331B7EE68FD9:3
+",
+        )
+        .unwrap();
+        let list = BreachList::open(&path).unwrap();
+        assert_eq!(list.hash_type, BreachHashType::Sha1);
+        assert_eq!(list.occurrences("password").unwrap(), 9659365);
+        //no range file for the prefix of this hash
+        assert_eq!(list.occurrences("not in the list").unwrap(), 0);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn finds_the_first_and_last_lines_of_a_sorted_file() {
+        let path = fixture_path("edges.txt");
+        std::fs::write(&path, SORTED_LIST).unwrap();
+        assert_eq!(
+            search_sorted_file(
+                &path,
+                SORTED_LIST.len() as u64,
+                "000000005AD76BD555C1D6D771DE417A4B87E4B4"
+            )
+            .unwrap(),
+            10
+        );
+        assert_eq!(
+            search_sorted_file(
+                &path,
+                SORTED_LIST.len() as u64,
+                "FFFFFFFF8A4B8E4B2E9A3D7E4B1C0C1E5A2B3C4D"
+            )
+            .unwrap(),
+            1
+        );
+        //lower case lines compare like upper case ones
+        assert_eq!(
+            search_sorted_file(
+                &path,
+                SORTED_LIST.len() as u64,
+                "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD9"
+            )
+            .unwrap(),
+            3
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
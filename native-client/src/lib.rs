@@ -6,6 +6,8 @@ pub type PasswordStoreType = Arc<Mutex<Arc<Mutex<PasswordStore>>>>;
 /// The list of stores that the user have.
 pub type StoreListType = Arc<Mutex<Vec<Arc<Mutex<PasswordStore>>>>>;
 
+pub mod breach;
 pub mod request_handler;
 pub mod store_api;
+pub mod totp;
 pub mod util;
@@ -12,17 +12,61 @@ use std::{
 
 pub use super::util::*;
 
-use browser_rpass::{request::*, response::*};
+use browser_rpass::{
+    audit::{audit_accounts, AuditFinding, BreachCheck},
+    password_generator::{generate_password, PolicyViolation},
+    payload::{
+        AccountPayload, AuditPayload, DeletePayload, FailurePayload, FetchPayload,
+        GeneratedPasswordPayload, UpdatedFields, Versioned,
+    },
+    request::*,
+    response::*,
+    types::Account,
+};
 use log::*;
 use rpass::pass::{self, Error, PasswordEntry, PasswordStore};
+use secrecy::ExposeSecret;
 use serde_json::json;
 
-use crate::{store_api::*, util::*, StoreListType};
+use crate::{breach::BreachList, store_api::*, totp::*, util::*, StoreListType};
+
+fn policy_violation_failure(violations: &[PolicyViolation]) -> Versioned<FailurePayload> {
+    FailurePayload {
+        message: Some(format!(
+            "password violates the store's password policy: {}",
+            violations
+                .iter()
+                .map(|violation| violation.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        )),
+        code: Some(ErrorCode::PolicyViolation),
+        policy_violations: violations.to_vec(),
+    }
+    .into()
+}
+
+//breach check results reported in the meta of create/edit responses. the check is skipped when no
+//breach list is available, and a failing lookup never fails the request itself
+fn breach_meta(
+    password: Option<&str>,
+    breach_list: &Option<BreachList>,
+) -> Option<serde_json::Value> {
+    let (password, breach_list) = (password?, breach_list.as_ref()?);
+    match breach_list.occurrences(password) {
+        Ok(occurrences) => Some(json!({"breach_count":occurrences})),
+        Err(err) => {
+            error!("failed to check password against breach list: {:?}", err);
+            None
+        }
+    }
+}
 
 pub fn handle_edit_request(
     request: EditRequest,
     store: &Arc<Mutex<PasswordStore>>,
     passphrase_provider: Option<Handler>,
+    breach_list: &Option<BreachList>,
 ) -> pass::Result<EditResponse> {
     let value = &request.value;
     let resource = &request.resource;
@@ -44,32 +88,96 @@ pub fn handle_edit_request(
                 .get(&DataFieldType::Note)
                 .map(|v| v.as_str())
                 .flatten();
-            let custom_fields = value
+            let mut custom_fields = value
                 .get(&DataFieldType::CustomField)
                 .map(|v| v.as_object())
-                .flatten();
+                .flatten()
+                .cloned();
+            validate_totp_fields(|field| {
+                custom_fields.as_ref().and_then(|fields| fields.get(field))
+            })
+            .map_err(pass::Error::GenericDyn)?;
+            validate_url_fields(|field| {
+                custom_fields.as_ref().and_then(|fields| fields.get(field))
+            })?;
+            //password changes made from the page ask for the old password to be kept, in case
+            //the site didn't accept the new one
+            let keep_history = value
+                .get(&DataFieldType::KeepPasswordHistory)
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            if let Some(password) = password {
+                let (policy, _) = effective_password_policy(store);
+                if policy.is_some() || keep_history {
+                    let history = {
+                        let locked_store = store.lock()?;
+                        let entry = locked_store.get_entry(&request.id)?;
+                        let decrypted = entry.secret(&locked_store, passphrase_provider.clone())?;
+                        get_password_history(&serde_json::from_str(&decrypted)?)
+                    };
+                    //an entry without a password yet counts as changed as well
+                    let password_changed =
+                        history.first().is_none_or(|current| current != password);
+                    let violations = match policy.as_ref() {
+                        Some(policy) if password_changed => policy.validate(password, &history),
+                        _ => vec![],
+                    };
+                    if !violations.is_empty() {
+                        return Ok(EditResponse {
+                            store_id: store.lock()?.get_name().clone(),
+                            acknowledgement: request.acknowledgement,
+                            updated_fields: None,
+                            error: Some(policy_violation_failure(&violations)),
+                            status: Status::Failure,
+                            resource: Resource::Account,
+                            id: request.id,
+                            meta: None,
+                        });
+                    }
+                    let history_size = policy
+                        .and_then(|policy| policy.history_size)
+                        .or(keep_history.then_some(DEFAULT_PASSWORD_HISTORY_SIZE));
+                    if let Some(history_size) = history_size {
+                        if password_changed {
+                            custom_fields.get_or_insert_with(Default::default).insert(
+                                PASSWORD_HISTORY_FIELD.to_owned(),
+                                json!(history.into_iter().take(history_size).collect::<Vec<_>>()),
+                            );
+                        }
+                    }
+                }
+            }
             let updated_data = store.lock()?.update_default_entry_fields(
                 &request.id,
                 domain,
                 username,
                 password,
                 note,
-                custom_fields,
+                custom_fields.as_ref(),
                 passphrase_provider,
             );
 
-            let mut data = HashMap::new();
             match updated_data {
-                Ok(updated_data) => {
-                    data.insert(DataFieldType::UpdatedFields, updated_data.clone());
+                Ok(mut updated_data) => {
+                    redact_totp_secret(&mut updated_data);
+                    redact_password_history(&mut updated_data);
+                    unprefix_url_fields(&mut updated_data);
+                    //the entry is already written, so a payload that doesn't parse must not
+                    //turn the edit into a failure
+                    let updated_fields: UpdatedFields = serde_json::from_value(updated_data)
+                        .unwrap_or_else(|err| {
+                            error!("failed to parse updated fields: {:?}", err);
+                            UpdatedFields::default()
+                        });
                     let edit_response = EditResponse {
                         store_id: store.lock()?.get_name().clone(),
                         acknowledgement: request.acknowledgement,
-                        data,
+                        updated_fields: Some(updated_fields.into()),
+                        error: None,
                         status: Status::Success,
                         resource: Resource::Account,
                         id: request.id,
-                        meta: None,
+                        meta: breach_meta(password, breach_list),
                     };
                     Ok(edit_response)
                 }
@@ -146,6 +254,34 @@ pub fn handle_get_request(
             };
             return Ok(get_response);
         }
+        Resource::Totp => {
+            let totp = {
+                let locked_store = store.lock()?;
+                let entry = locked_store.get_entry(&id)?;
+                let decrypted: serde_json::Value =
+                    serde_json::from_str(&entry.secret(&locked_store, passphrase_provider)?)?;
+                let totp_field = get_totp_field(&decrypted).ok_or(pass::Error::GenericDyn(
+                    format!("entry {} has no one-time code configured", id),
+                ))?;
+                Totp::parse(totp_field).map_err(pass::Error::GenericDyn)?
+            };
+            let timestamp = unix_timestamp();
+            let mut data = HashMap::new();
+            data.insert(DataFieldType::Value, json!(totp.generate(timestamp)));
+            data.insert(DataFieldType::TotpPeriod, json!(totp.period));
+            data.insert(
+                DataFieldType::TotpRemaining,
+                json!(totp.remaining_seconds(timestamp)),
+            );
+            return Ok(GetResponse {
+                data,
+                //TODO don't use magic string for field names
+                meta: Some(json!({"id":id})),
+                resource,
+                acknowledgement,
+                status: Status::Success,
+            });
+        }
         _ => {
             return Err(pass::Error::from(
                 "Currently only resource type of Account is supported",
@@ -264,6 +400,7 @@ pub fn handle_fetch_request(
                 }
             }
 
+            let (password_policy, password_policy_error) = effective_password_policy(store);
             let mut locked_store = store.lock()?;
             let encrypted_password_entries = locked_store.get_entries(None)?;
             let decrypted_password_entries = encrypted_password_entries
@@ -287,6 +424,9 @@ pub fn handle_fetch_request(
                                         serde_json::from_str::<serde_json::Value>(&decrypted)
                                     {
                                         merge_json(&mut json_value, &decrypted);
+                                        redact_totp_secret(&mut json_value);
+                                        redact_password_history(&mut json_value);
+                                        unprefix_url_fields(&mut json_value);
                                         Some(json_value)
                                     } else {
                                         error!("failed to parse decrypted json string into serde::Value. Json String: {:?}", decrypted);
@@ -301,41 +441,31 @@ pub fn handle_fetch_request(
                             }
                         })
                         .collect::<Vec<serde_json::Value>>();
-            let fetch_response = {
-                let mut data = HashMap::new();
-                if let Ok(entries) = serde_json::to_value(decrypted_password_entries) {
-                    if let Some(entry_arr) = entries.as_array().cloned() {
-                        data.insert(DataFieldType::Data, json!(entry_arr));
-                        data.insert(DataFieldType::SubStore, json!(substores));
-                        FetchResponse {
-                            store_id: locked_store.get_name().clone(),
-                            data,
-                            //TODO don't use magic string for field names
-                            meta: Some(json!({"custom_field_prefix":CUSTOM_FIELD_PREFIX})),
-                            resource,
-                            acknowledgement,
-                            status: Status::Success,
-                        }
-                    } else {
-                        FetchResponse {
-                            store_id: locked_store.get_name().clone(),
-                            data,
-                            meta: None,
-                            resource,
-                            acknowledgement,
-                            status: Status::Failure,
-                        }
-                    }
-                } else {
-                    FetchResponse {
-                        store_id: locked_store.get_name().clone(),
-                        data,
-                        meta: None,
-                        resource,
-                        acknowledgement,
-                        status: Status::Failure,
+            let accounts = decrypted_password_entries
+                .into_iter()
+                .filter_map(|entry| {
+                    serde_json::from_value::<Account>(entry)
+                        .map_err(|err| error!("failed to parse password entry: {:?}", err))
+                        .ok()
+                })
+                .collect::<Vec<Account>>();
+            let fetch_response = FetchResponse {
+                store_id: locked_store.get_name().clone(),
+                error: None,
+                fetched: Some(
+                    FetchPayload {
+                        accounts,
+                        sub_stores: substores,
+                        password_policy,
+                        password_policy_error,
                     }
-                }
+                    .into(),
+                ),
+                //TODO don't use magic string for field names
+                meta: Some(json!({"custom_field_prefix":CUSTOM_FIELD_PREFIX})),
+                resource,
+                acknowledgement,
+                status: Status::Success,
             };
             return Ok(fetch_response);
         }
@@ -612,6 +742,7 @@ pub fn handle_create_request(
     request: CreateRequest,
     store: &Arc<Mutex<PasswordStore>>,
     passphrase_provider: Option<Handler>,
+    breach_list: &Option<BreachList>,
 ) -> pass::Result<CreateResponse> {
     let username = request.username;
     let domain = request.domain;
@@ -623,8 +754,31 @@ pub fn handle_create_request(
     let status;
     match resource {
         Resource::Account => {
+            validate_totp_fields(|field| {
+                custom_fields.as_ref().and_then(|fields| fields.get(field))
+            })
+            .map_err(pass::Error::GenericDyn)?;
+            validate_url_fields(|field| {
+                custom_fields.as_ref().and_then(|fields| fields.get(field))
+            })?;
+            if let Some(password) = password.as_ref() {
+                if let (Some(policy), _) = effective_password_policy(store) {
+                    let violations = policy.validate(password, &[]);
+                    if !violations.is_empty() {
+                        return Ok(CreateResponse {
+                            store_id: store.lock()?.get_name().clone(),
+                            acknowledgement,
+                            created: None,
+                            error: Some(policy_violation_failure(&violations)),
+                            meta: None,
+                            resource: Resource::Account,
+                            status: Status::Failure,
+                        });
+                    }
+                }
+            }
             let mut locked_store = store.lock()?;
-            let mut data = HashMap::new();
+            let mut created = None;
             let status = match locked_store.create_entry(
                 username.as_deref(),
                 password.as_deref(),
@@ -643,8 +797,19 @@ pub fn handle_create_request(
                             (&entry).try_into();
                         if let Ok(entry_meta) = entry_meta_res.as_ref() {
                             merge_json(&mut entry_data, entry_meta);
-                            status = Status::Success;
-                            data.insert(DataFieldType::Data, entry_data);
+                            redact_totp_secret(&mut entry_data);
+                            redact_password_history(&mut entry_data);
+                            unprefix_url_fields(&mut entry_data);
+                            match serde_json::from_value::<Account>(entry_data) {
+                                Ok(account) => {
+                                    created = Some(AccountPayload { account }.into());
+                                    status = Status::Success;
+                                }
+                                Err(err) => {
+                                    error!("failed to parse created entry: {:?}", err);
+                                    status = Status::Failure;
+                                }
+                            }
                             status
                         } else {
                             error!(
@@ -665,11 +830,19 @@ pub fn handle_create_request(
                     status
                 }
             };
+            let (meta, error) = match status {
+                Status::Success => (breach_meta(password.as_deref(), breach_list), None),
+                _ => (
+                    None,
+                    Some(FailurePayload::new("failed to create the account").into()),
+                ),
+            };
             let create_response: CreateResponse = CreateResponse {
                 store_id: locked_store.get_name().clone(),
                 acknowledgement,
-                data,
-                meta: None,
+                created,
+                error,
+                meta,
                 resource: Resource::Account,
                 status,
             };
@@ -689,20 +862,18 @@ pub fn handle_delete_request(
 ) -> pass::Result<DeleteResponse> {
     let id = request.id;
     let acknowledgement = request.acknowledgement;
-    let mut data = HashMap::new();
+    let mut deleted = None;
+    let mut error = None;
     let status = {
         let res = store.lock()?.delete_entry(&(id), passphrase_provider);
         match res {
-            Ok(entry_data) => {
-                data.insert(
-                    DataFieldType::ResourceID,
-                    serde_json::to_value(entry_data.id.clone()).unwrap_or_default(),
-                );
-                data.insert(DataFieldType::Data, serde_json::to_value(entry_data)?);
+            Ok(_) => {
+                deleted = Some(DeletePayload { id: id.clone() }.into());
                 Status::Success
             }
             Err(e) => {
                 error!("Failed to delete entry: {:?}", e);
+                error = Some(FailurePayload::new(error_message(&e)).into());
                 Status::Failure
             }
         }
@@ -710,8 +881,135 @@ pub fn handle_delete_request(
     let delete_response = DeleteResponse {
         deleted_resource_id: id,
         acknowledgement,
-        data,
+        deleted,
+        error,
         status,
     };
     Ok(delete_response)
 }
+pub fn handle_generate_password_request(
+    request: GeneratePasswordRequest,
+    store: &Option<Arc<Mutex<PasswordStore>>>,
+) -> pass::Result<GeneratePasswordResponse> {
+    let policy = match store {
+        Some(store) => effective_password_policy(store).0,
+        None => None,
+    };
+    let mut options = request.options.clone().unwrap_or_default();
+    if let Some(policy) = policy.as_ref() {
+        options = options.apply_policy(policy);
+    }
+    let password = generate_password(&options).map_err(|e| Error::GenericDyn(e))?;
+    //the same check as on create and edit, so a generated password is never refused on save
+    if let Some(policy) = policy.as_ref() {
+        let violations = policy.validate(&password, &[]);
+        if !violations.is_empty() {
+            return Ok(GeneratePasswordResponse {
+                store_id: request.store_id,
+                acknowledgement: request.acknowledgement,
+                generated: None,
+                error: Some(policy_violation_failure(&violations)),
+                status: Status::Failure,
+                meta: None,
+            });
+        }
+    }
+    Ok(GeneratePasswordResponse {
+        store_id: request.store_id,
+        acknowledgement: request.acknowledgement,
+        generated: Some(
+            GeneratedPasswordPayload {
+                password,
+                options,
+                password_policy: policy,
+            }
+            .into(),
+        ),
+        error: None,
+        status: Status::Success,
+        meta: None,
+    })
+}
+
+pub fn handle_audit_request(
+    request: AuditRequest,
+    store: &Arc<Mutex<PasswordStore>>,
+    passphrase_provider: Option<Handler>,
+    breach_list: &Option<BreachList>,
+) -> pass::Result<AuditResponse> {
+    let options = request.options.clone().unwrap_or_default();
+    let accounts = {
+        let mut locked_store = store.lock()?;
+        if let Some(passphrase_provider) = passphrase_provider.clone() {
+            if locked_store.get_login_recipient().is_none() {
+                locked_store.try_passphrase(Some(passphrase_provider))?;
+            }
+        }
+        let encrypted_password_entries = locked_store.get_entries(None)?;
+        let decrypt = |encrypted_password_entry: &PasswordEntry| -> pass::Result<Account> {
+            let mut json_value: serde_json::Value = encrypted_password_entry.try_into()?;
+            let decrypted =
+                encrypted_password_entry.secret(&locked_store, passphrase_provider.clone())?;
+            merge_json(
+                &mut json_value,
+                &serde_json::from_str::<serde_json::Value>(&decrypted)?,
+            );
+            Ok(serde_json::from_value::<Account>(json_value)?)
+        };
+        //an entry that can't be read is left out rather than failing the whole audit
+        encrypted_password_entries
+            .iter()
+            .filter_map(|encrypted_password_entry| {
+                decrypt(encrypted_password_entry)
+                    .map_err(|err| {
+                        error!(
+                            "failed to read password entry {:?} for the audit: {:?}",
+                            encrypted_password_entry, err
+                        )
+                    })
+                    .ok()
+            })
+            .collect::<Vec<Account>>()
+    };
+    let mut report = audit_accounts(&accounts, &options, chrono::Utc::now());
+    if let Some(breach_list) = breach_list {
+        //a failing lookup only leaves the breach findings out of the report
+        let breached = accounts
+            .iter()
+            .filter_map(|account| {
+                let password = account
+                    .get_password()
+                    .filter(|password| !password.expose_secret().is_empty())?;
+                Some(
+                    breach_list
+                        .occurrences(password.expose_secret())
+                        .map(|occurrences| (account, occurrences)),
+                )
+            })
+            .collect::<pass::Result<Vec<(&Account, u64)>>>();
+        match breached {
+            Ok(breached) => {
+                for (account, occurrences) in breached {
+                    if occurrences > 0 {
+                        report.add_finding(account, AuditFinding::BreachedPassword { occurrences });
+                    }
+                }
+                report.breach_check = BreachCheck::Checked;
+            }
+            Err(err) => {
+                error!("failed to check passwords against breach list: {:?}", err);
+                report.breach_check = BreachCheck::Unavailable {
+                    reason: format!("{:?}", err),
+                };
+            }
+        }
+    }
+    Ok(AuditResponse {
+        store_id: store.lock()?.get_name().clone().into(),
+        acknowledgement: request.acknowledgement,
+        audit: Some(AuditPayload { report, options }.into()),
+        error: None,
+        status: Status::Success,
+        meta: None,
+    })
+}
@@ -6,7 +6,11 @@ use std::{
 
 pub use super::util::*;
 
-use browser_rpass::{request::*, response::*};
+use browser_rpass::{
+    payload::{FailurePayload, Versioned},
+    request::*,
+    response::*,
+};
 use log::*;
 use rpass::{
     crypto::Handler,
@@ -14,7 +18,12 @@ use rpass::{
 };
 use serde_json::json;
 
-use crate::{request_handler::*, util::ToJson, StoreListType};
+use crate::{
+    breach::BreachList,
+    request_handler::*,
+    util::{error_message, ToJson},
+    StoreListType,
+};
 fn get_store(request: &RequestEnum, stores: &StoreListType) -> Option<Arc<Mutex<PasswordStore>>> {
     let store_id = request.get_store_id();
     if store_id.is_none() {
@@ -31,12 +40,19 @@ fn get_store(request: &RequestEnum, stores: &StoreListType) -> Option<Arc<Mutex<
         None
     }
 }
+fn failure(err: pass::Error) -> Versioned<FailurePayload> {
+    FailurePayload::new(error_message(&err)).into()
+}
 pub fn listen_to_native_messaging(
     stores: StoreListType,
     passphrase_provider: Option<Handler>,
     home: Option<PathBuf>,
     config_file_location: PathBuf,
 ) -> pass::Result<()> {
+    let breach_list = BreachList::from_env(&home).unwrap_or_else(|err| {
+        error!("failed to open breach list: {:?}", err);
+        None
+    });
     loop {
         let received_message_res = get_message();
         if received_message_res.is_err() {
@@ -179,8 +195,8 @@ pub fn listen_to_native_messaging(
                                 status: Status::Failure,
                                 acknowledgement: request.acknowledgement.clone(),
                                 data,
-                                resource: Resource::Password,
-                                meta: None,
+                                resource: request.resource.clone(),
+                                meta: Some(json!({"id":request.id})),
                             });
                             send_as_json(&response)?;
                             Err(response)
@@ -227,13 +243,12 @@ pub fn listen_to_native_messaging(
                             send_as_json(&response)?;
                             Ok(response)
                         } else {
-                            let mut data = HashMap::new();
-                            data.insert(DataFieldType::Data, json!([]));
                             let response = ResponseEnum::FetchResponse(FetchResponse {
                                 store_id: request.store_id.clone().unwrap(),
                                 status: Status::Failure,
                                 acknowledgement: request.acknowledgement.clone(),
-                                data,
+                                fetched: None,
+                                error: Some(failure(response.unwrap_err())),
                                 resource: request.resource,
                                 meta: None,
                             });
@@ -310,22 +325,19 @@ pub fn listen_to_native_messaging(
                             request.clone(),
                             &store,
                             passphrase_provider.clone(),
+                            &breach_list,
                         );
-                        let mut data = HashMap::new();
                         if response.is_ok() {
                             let response = ResponseEnum::CreateResponse(response?);
                             send_as_json(&response)?;
                             Ok(response)
                         } else {
-                            data.insert(
-                                DataFieldType::ErrorMessage,
-                                serde_json::to_value(response.unwrap_err()).unwrap(),
-                            );
                             let response = ResponseEnum::CreateResponse(CreateResponse {
                                 status: Status::Failure,
                                 store_id: request.store_id.clone().unwrap(),
                                 acknowledgement: request.acknowledgement.clone(),
-                                data,
+                                created: None,
+                                error: Some(failure(response.unwrap_err())),
                                 resource: request.resource,
                                 meta: None,
                             });
@@ -340,22 +352,18 @@ pub fn listen_to_native_messaging(
                             &store,
                             passphrase_provider.clone(),
                         );
-                        let mut data = HashMap::new();
                         if response.is_ok() {
                             let response = ResponseEnum::DeleteResponse(response?);
                             send_as_json(&response)?;
                             Ok(response)
                         } else {
-                            data.insert(
-                                DataFieldType::ErrorMessage,
-                                serde_json::to_value(response.unwrap_err()).unwrap(),
-                            );
                             let response = ResponseEnum::DeleteResponse(DeleteResponse {
                                 status: Status::Failure,
                                 // store_id: request.store_id.clone(),
                                 deleted_resource_id: request.id,
                                 acknowledgement: request.acknowledgement.clone(),
-                                data,
+                                deleted: None,
+                                error: Some(failure(response.unwrap_err())),
                             });
                             send_as_json(&response)?;
                             Err(response)
@@ -367,23 +375,20 @@ pub fn listen_to_native_messaging(
                             request.clone(),
                             &store,
                             passphrase_provider.clone(),
+                            &breach_list,
                         );
-                        let mut data = HashMap::new();
                         if response.is_ok() {
                             let response = ResponseEnum::EditResponse(response?);
                             send_as_json(&response)?;
                             Ok(response)
                         } else {
-                            data.insert(
-                                DataFieldType::ErrorMessage,
-                                serde_json::to_value(response.unwrap_err()).unwrap(),
-                            );
                             let response = ResponseEnum::EditResponse(EditResponse {
                                 store_id: request.store_id.clone().unwrap(),
                                 id: request.id.clone(),
                                 status: Status::Failure,
                                 acknowledgement: request.acknowledgement.clone(),
-                                data,
+                                updated_fields: None,
+                                error: Some(failure(response.unwrap_err())),
                                 resource: request.resource,
                                 meta: None,
                             });
@@ -391,6 +396,52 @@ pub fn listen_to_native_messaging(
                             Err(response)
                         }
                     }
+                    RequestEnum::GeneratePassword(request) => {
+                        let response =
+                            handle_generate_password_request(request.clone(), &target_store);
+                        if response.is_ok() {
+                            let response = ResponseEnum::GeneratePasswordResponse(response?);
+                            send_as_json(&response)?;
+                            Ok(response)
+                        } else {
+                            let response =
+                                ResponseEnum::GeneratePasswordResponse(GeneratePasswordResponse {
+                                    store_id: request.store_id.clone(),
+                                    status: Status::Failure,
+                                    acknowledgement: request.acknowledgement.clone(),
+                                    generated: None,
+                                    error: Some(failure(response.unwrap_err())),
+                                    meta: None,
+                                });
+                            send_as_json(&response)?;
+                            Err(response)
+                        }
+                    }
+                    RequestEnum::Audit(request) if target_store.is_some() => {
+                        let store = target_store.unwrap();
+                        let response = handle_audit_request(
+                            request.clone(),
+                            &store,
+                            passphrase_provider.clone(),
+                            &breach_list,
+                        );
+                        if response.is_ok() {
+                            let response = ResponseEnum::AuditResponse(response?);
+                            send_as_json(&response)?;
+                            Ok(response)
+                        } else {
+                            let response = ResponseEnum::AuditResponse(AuditResponse {
+                                store_id: request.store_id.clone(),
+                                status: Status::Failure,
+                                acknowledgement: request.acknowledgement.clone(),
+                                audit: None,
+                                error: Some(failure(response.unwrap_err())),
+                                meta: None,
+                            });
+                            send_as_json(&response)?;
+                            Err(response)
+                        }
+                    }
                     _ => {
                         let mut data = HashMap::new();
                         data.insert(DataFieldType::ErrorMessage, json!("Unknown request"));
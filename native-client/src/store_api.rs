@@ -4,9 +4,25 @@ use std::{
     sync::{Arc, Mutex},
 };
 
+pub use browser_rpass::custom_field::PASSWORD_HISTORY_FIELD;
+use browser_rpass::{
+    password_generator::PasswordPolicy,
+    url_match::{AccountUrl, MatchMode, URLS_FIELD, URL_MATCH_FIELD},
+};
 #[allow(unused_imports)]
 use log::*;
-use rpass::pass::{self, PasswordEntry, PasswordStore};
+use rpass::{
+    crypto::{self, SignatureStatus},
+    pass::{self, PasswordEntry, PasswordStore, CUSTOM_FIELD_PREFIX},
+};
+use serde_json::Value;
+
+use crate::util::error_message;
+
+pub const PASSWORD_POLICY_FILE: &str = ".password-policy.json";
+pub const PASSWORD_POLICY_SIGNATURE_FILE: &str = ".password-policy.json.sig";
+//passwords kept on edits that ask for history when the store's policy doesn't set a size
+pub const DEFAULT_PASSWORD_HISTORY_SIZE: usize = 5;
 
 /// Validates the config for password stores.
 /// Returns a list of paths that the new store wizard should be run for
@@ -65,3 +81,247 @@ pub fn filter_entries(
     let result = matching.cloned().collect();
     Ok(result)
 }
+
+/// Reads the password policy committed at the root of the store, if there is one.
+/// Like `.gpg-id`, the policy has to carry a valid detached signature from one of the store's
+/// signing keys whenever the store has signing keys configured.
+pub fn load_password_policy(
+    store: &Arc<Mutex<PasswordStore>>,
+) -> pass::Result<Option<PasswordPolicy>> {
+    let locked_store = store.lock()?;
+    let policy_path = locked_store.get_store_path().join(PASSWORD_POLICY_FILE);
+    if !policy_path.exists() {
+        return Ok(None);
+    }
+    let policy_bytes = std::fs::read(&policy_path)?;
+    let signature_path = locked_store
+        .get_store_path()
+        .join(PASSWORD_POLICY_SIGNATURE_FILE);
+    let signature = if signature_path.exists() {
+        Some(std::fs::read(&signature_path)?)
+    } else {
+        None
+    };
+    let valid_signing_keys = locked_store.get_valid_gpg_signing_keys();
+    let policy = parse_password_policy(
+        &policy_bytes,
+        signature.as_deref(),
+        !valid_signing_keys.is_empty(),
+        |policy_bytes, signature| {
+            let crypto = crypto::CryptoImpl::GpgMe
+                .get_crypto_type()
+                .map_err(|err| format!("{:?}", err))?;
+            match crypto.verify_sign(policy_bytes, signature, valid_signing_keys) {
+                Ok(SignatureStatus::Good) => Ok(()),
+                verification_result => Err(format!("{:?}", verification_result)),
+            }
+        },
+    )
+    .map_err(|err| {
+        pass::Error::GenericDyn(format!("password policy at {:?} {}", policy_path, err))
+    })?;
+    Ok(Some(policy))
+}
+
+/// Parses a password policy read from a store. In a store with signing keys, the policy only
+/// counts with a signature that `verify` accepts.
+fn parse_password_policy(
+    policy_bytes: &[u8],
+    signature: Option<&[u8]>,
+    signed_store: bool,
+    verify: impl FnOnce(&[u8], &[u8]) -> Result<(), String>,
+) -> Result<PasswordPolicy, String> {
+    if signed_store {
+        let signature = signature.ok_or_else(|| "is not signed".to_owned())?;
+        verify(policy_bytes, signature)
+            .map_err(|err| format!("has a signature that could not be verified: {}", err))?;
+    }
+    serde_json::from_slice::<PasswordPolicy>(policy_bytes)
+        .map_err(|err| format!("could not be parsed: {}", err))
+}
+
+/// The password policy create, edit, generate and fetch requests apply. A policy that can't be
+/// loaded is ignored rather than refusing every save, and the reason is returned so the popup
+/// can show it.
+pub fn effective_password_policy(
+    store: &Arc<Mutex<PasswordStore>>,
+) -> (Option<PasswordPolicy>, Option<String>) {
+    ignore_unusable_policy(load_password_policy(store))
+}
+
+fn ignore_unusable_policy(
+    policy: pass::Result<Option<PasswordPolicy>>,
+) -> (Option<PasswordPolicy>, Option<String>) {
+    match policy {
+        Ok(policy) => (policy, None),
+        Err(err) => {
+            warn!("ignoring password policy: {:?}", err);
+            (None, Some(error_message(&err)))
+        }
+    }
+}
+
+/// Checks the url fields of an account before they are saved, so a malformed one can't hide the
+/// account from every page.
+pub fn validate_url_fields<'a>(field: impl Fn(&str) -> Option<&'a Value>) -> pass::Result<()> {
+    if let Some(match_mode) = field(URL_MATCH_FIELD) {
+        serde_json::from_value::<MatchMode>(match_mode.clone()).map_err(|err| {
+            pass::Error::GenericDyn(format!("invalid {}: {}", URL_MATCH_FIELD, err))
+        })?;
+    }
+    if let Some(urls) = field(URLS_FIELD) {
+        let urls = serde_json::from_value::<Vec<AccountUrl>>(urls.clone())
+            .map_err(|err| pass::Error::GenericDyn(format!("invalid {}: {}", URLS_FIELD, err)))?;
+        if let Some(account_url) = urls
+            .iter()
+            .find(|account_url| account_url.url.trim().is_empty())
+        {
+            return Err(pass::Error::GenericDyn(format!(
+                "empty url in {}: {:?}",
+                URLS_FIELD, account_url
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Gives the url fields of an entry the names the extension reads, whether they were saved as
+/// custom fields or not. Entries without them are left as they are.
+pub fn unprefix_url_fields(entry: &mut Value) {
+    if let Some(entry) = entry.as_object_mut() {
+        for field in [URL_MATCH_FIELD, URLS_FIELD] {
+            if let Some(value) = entry.remove(&format!("{}{}", CUSTOM_FIELD_PREFIX, field)) {
+                entry.insert(field.to_owned(), value);
+            }
+        }
+    }
+}
+
+/// Removes the previous passwords from an entry before it leaves the native client. Only the
+/// native app checks new passwords against them.
+pub fn redact_password_history(entry: &mut Value) {
+    if let Some(entry) = entry.as_object_mut() {
+        entry.remove(&format!(
+            "{}{}",
+            CUSTOM_FIELD_PREFIX, PASSWORD_HISTORY_FIELD
+        ));
+        entry.remove(PASSWORD_HISTORY_FIELD);
+    }
+}
+
+/// Returns the current password of a decrypted entry followed by its previous passwords,
+/// most recent first.
+pub fn get_password_history(entry: &Value) -> Vec<String> {
+    let mut history = vec![];
+    //TODO don't use magic string for field names
+    if let Some(password) = entry.get("password").and_then(|v| v.as_str()) {
+        history.push(password.to_owned());
+    }
+    let previous = entry
+        .get(format!("{}{}", CUSTOM_FIELD_PREFIX, PASSWORD_HISTORY_FIELD))
+        .or(entry.get(PASSWORD_HISTORY_FIELD))
+        .and_then(|v| v.as_array());
+    if let Some(previous) = previous {
+        history.extend(
+            previous
+                .iter()
+                .filter_map(|v| v.as_str().map(|v| v.to_owned())),
+        );
+    }
+    history
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: &[u8] = br#"{"min_length":16,"require_digits":true}"#;
+
+    fn expected_policy() -> PasswordPolicy {
+        PasswordPolicy {
+            min_length: Some(16),
+            require_digits: true,
+            ..Default::default()
+        }
+    }
+
+    fn accept(_: &[u8], _: &[u8]) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn reject(_: &[u8], _: &[u8]) -> Result<(), String> {
+        Err("BadSignature".to_owned())
+    }
+
+    #[test]
+    fn unsigned_policy_in_unsigned_store_applies() {
+        assert_eq!(
+            parse_password_policy(POLICY, None, false, reject),
+            Ok(expected_policy())
+        );
+    }
+
+    #[test]
+    fn unsigned_policy_in_signed_store_is_refused() {
+        assert_eq!(
+            parse_password_policy(POLICY, None, true, accept),
+            Err("is not signed".to_owned())
+        );
+    }
+
+    #[test]
+    fn policy_with_bad_signature_is_refused() {
+        assert!(parse_password_policy(POLICY, Some(b"signature"), true, reject).is_err());
+    }
+
+    #[test]
+    fn signed_policy_in_signed_store_applies() {
+        let verify = |policy: &[u8], signature: &[u8]| {
+            assert_eq!((policy, signature), (POLICY, &b"signature"[..]));
+            Ok(())
+        };
+        assert_eq!(
+            parse_password_policy(POLICY, Some(b"signature"), true, verify),
+            Ok(expected_policy())
+        );
+    }
+
+    #[test]
+    fn malformed_policy_is_refused() {
+        assert!(parse_password_policy(b"{\"min_length\":", None, false, accept).is_err());
+    }
+
+    #[test]
+    fn password_history_is_redacted() {
+        let mut entry = serde_json::json!({
+            "password": "current",
+            "username": "user",
+            format!("{}{}", CUSTOM_FIELD_PREFIX, PASSWORD_HISTORY_FIELD): ["previous"],
+            PASSWORD_HISTORY_FIELD: ["older"],
+        });
+        assert_eq!(get_password_history(&entry), vec!["current", "previous"]);
+        redact_password_history(&mut entry);
+        assert_eq!(
+            entry,
+            serde_json::json!({"password": "current", "username": "user"})
+        );
+    }
+
+    #[test]
+    fn unusable_policy_is_ignored_with_its_reason() {
+        let refused = Err(pass::Error::GenericDyn(
+            "password policy at \".password-policy.json\" is not signed".to_owned(),
+        ));
+        assert_eq!(
+            ignore_unusable_policy(refused),
+            (
+                None,
+                Some("password policy at \".password-policy.json\" is not signed".to_owned())
+            )
+        );
+        assert_eq!(
+            ignore_unusable_policy(Ok(Some(expected_policy()))),
+            (Some(expected_policy()), None)
+        );
+    }
+}
@@ -0,0 +1,280 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use browser_rpass::custom_field::{custom_field_types, CustomFieldType};
+pub use browser_rpass::types::TOTP_FIELD;
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use rpass::pass::CUSTOM_FIELD_PREFIX;
+use serde_json::Value;
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use url::Url;
+
+/// Field added to entries sent to the extension in place of the redacted TOTP secret.
+pub const HAS_TOTP_FIELD: &str = "has_totp";
+
+const DEFAULT_DIGITS: u32 = 6;
+const DEFAULT_PERIOD: u64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TotpAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Totp {
+    secret: Vec<u8>,
+    pub algorithm: TotpAlgorithm,
+    pub digits: u32,
+    pub period: u64,
+}
+
+impl Totp {
+    /// Parses either a full `otpauth://totp/...` URI as written by authenticator apps or a bare
+    /// base32 secret, in which case the RFC 6238 defaults (SHA-1, 6 digits, 30 seconds) apply.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        if !value.to_lowercase().starts_with("otpauth://") {
+            return Ok(Totp {
+                secret: decode_secret(value)?,
+                algorithm: TotpAlgorithm::Sha1,
+                digits: DEFAULT_DIGITS,
+                period: DEFAULT_PERIOD,
+            });
+        }
+        let uri = Url::parse(value).map_err(|e| format!("invalid otpauth uri: {}", e))?;
+        if uri.host_str().map(|host| host.to_lowercase()) != Some("totp".to_owned()) {
+            return Err(format!(
+                "unsupported otpauth type: {}",
+                uri.host_str().unwrap_or_default()
+            ));
+        }
+        let mut totp = Totp {
+            secret: vec![],
+            algorithm: TotpAlgorithm::Sha1,
+            digits: DEFAULT_DIGITS,
+            period: DEFAULT_PERIOD,
+        };
+        for (key, value) in uri.query_pairs() {
+            match key.to_lowercase().as_str() {
+                "secret" => totp.secret = decode_secret(&value)?,
+                "algorithm" => {
+                    totp.algorithm = match value.to_uppercase().as_str() {
+                        "SHA1" => TotpAlgorithm::Sha1,
+                        "SHA256" => TotpAlgorithm::Sha256,
+                        "SHA512" => TotpAlgorithm::Sha512,
+                        _ => return Err(format!("unsupported otp algorithm: {}", value)),
+                    }
+                }
+                "digits" => {
+                    totp.digits = value
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|digits| (6..=8).contains(digits))
+                        .ok_or(format!("unsupported number of otp digits: {}", value))?
+                }
+                "period" => {
+                    totp.period = value
+                        .parse::<u64>()
+                        .ok()
+                        .filter(|period| *period > 0)
+                        .ok_or(format!("invalid otp period: {}", value))?
+                }
+                _ => {}
+            }
+        }
+        if totp.secret.is_empty() {
+            return Err("otpauth uri has no secret".to_owned());
+        }
+        Ok(totp)
+    }
+    /// Computes the code for the given unix timestamp as described in RFC 6238.
+    pub fn generate(&self, timestamp: u64) -> String {
+        let counter = (timestamp / self.period).to_be_bytes();
+        let hash = match self.algorithm {
+            TotpAlgorithm::Sha1 => hmac_digest::<Hmac<Sha1>>(&self.secret, &counter),
+            TotpAlgorithm::Sha256 => hmac_digest::<Hmac<Sha256>>(&self.secret, &counter),
+            TotpAlgorithm::Sha512 => hmac_digest::<Hmac<Sha512>>(&self.secret, &counter),
+        };
+        //dynamic truncation from RFC 4226
+        let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+        let binary = u32::from_be_bytes([
+            hash[offset] & 0x7f,
+            hash[offset + 1],
+            hash[offset + 2],
+            hash[offset + 3],
+        ]);
+        format!(
+            "{:0width$}",
+            binary % 10u32.pow(self.digits),
+            width = self.digits as usize
+        )
+    }
+    /// Seconds until the code generated at the given timestamp expires.
+    pub fn remaining_seconds(&self, timestamp: u64) -> u64 {
+        self.period - timestamp % self.period
+    }
+}
+
+fn hmac_digest<M: Mac + hmac::digest::KeyInit>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = <M as Mac>::new_from_slice(key).expect("hmac accepts keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn decode_secret(secret: &str) -> Result<Vec<u8>, String> {
+    let normalized = secret
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-' && *c != '=')
+        .collect::<String>()
+        .to_uppercase();
+    BASE32_NOPAD
+        .decode(normalized.as_bytes())
+        .map_err(|e| format!("otp secret is not valid base32: {}", e))
+}
+
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+/// Names of the fields of an entry holding a TOTP secret: the usual one first, followed by the
+/// custom fields typed as one-time codes.
+fn totp_field_names(entry: &Value) -> Vec<String> {
+    let typed = custom_field_types(CUSTOM_FIELD_PREFIX, |name| entry.get(name))
+        .into_iter()
+        .filter(|(_, field_type)| *field_type == CustomFieldType::Totp)
+        .map(|(name, _)| name);
+    std::iter::once(TOTP_FIELD.to_owned())
+        .chain(typed)
+        .collect()
+}
+
+/// Returns the TOTP secret of a decrypted entry, whether it was saved as a custom field or not.
+/// Entries without the usual field fall back to their first one-time code custom field.
+pub fn get_totp_field(entry: &Value) -> Option<&str> {
+    totp_field_names(entry).iter().find_map(|name| {
+        entry
+            .get(format!("{}{}", CUSTOM_FIELD_PREFIX, name))
+            .or(entry.get(name))
+            .and_then(|v| v.as_str())
+            .filter(|v| !v.trim().is_empty())
+    })
+}
+
+/// Checks the TOTP secrets of an account before they are saved, so codes can be generated
+/// for every field that is meant to hold one.
+pub fn validate_totp_fields<'a>(field: impl Fn(&str) -> Option<&'a Value>) -> Result<(), String> {
+    let typed = custom_field_types(CUSTOM_FIELD_PREFIX, &field)
+        .into_iter()
+        .filter(|(_, field_type)| *field_type == CustomFieldType::Totp)
+        .map(|(name, _)| name);
+    for name in std::iter::once(TOTP_FIELD.to_owned()).chain(typed) {
+        if let Some(secret) = field(&name)
+            .and_then(|v| v.as_str())
+            .filter(|v| !v.trim().is_empty())
+        {
+            Totp::parse(secret)?;
+        }
+    }
+    Ok(())
+}
+
+/// Removes the TOTP secrets from an entry before it leaves the native client, leaving only a
+/// flag that codes can be requested for it.
+pub fn redact_totp_secret(entry: &mut Value) {
+    let names = totp_field_names(entry);
+    if let Some(entry) = entry.as_object_mut() {
+        let mut has_totp = false;
+        for name in names {
+            let prefixed = entry.remove(&format!("{}{}", CUSTOM_FIELD_PREFIX, name));
+            let plain = entry.remove(&name);
+            has_totp |= prefixed
+                .or(plain)
+                .and_then(|v| v.as_str().map(|v| !v.trim().is_empty()))
+                .unwrap_or(false);
+        }
+        entry.insert(HAS_TOTP_FIELD.to_owned(), Value::Bool(has_totp));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //seeds and expected codes of RFC 6238 appendix B
+    const SHA1_SEED: &[u8] = b"12345678901234567890";
+    const SHA256_SEED: &[u8] = b"12345678901234567890123456789012";
+    const SHA512_SEED: &[u8] = b"1234567890123456789012345678901234567890123456789012345678901234";
+    const VECTORS: &[(u64, &str, &str, &str)] = &[
+        (59, "94287082", "46119246", "90693936"),
+        (1111111109, "07081804", "68084774", "25091201"),
+        (1111111111, "14050471", "67062674", "99943326"),
+        (1234567890, "89005924", "91819424", "93441116"),
+        (2000000000, "69279037", "90698825", "38618901"),
+        (20000000000, "65353130", "77737706", "47863826"),
+    ];
+
+    fn totp(seed: &[u8], algorithm: TotpAlgorithm) -> Totp {
+        Totp {
+            secret: seed.to_vec(),
+            algorithm,
+            digits: 8,
+            period: 30,
+        }
+    }
+
+    #[test]
+    fn generates_rfc_6238_codes() {
+        let sha1 = totp(SHA1_SEED, TotpAlgorithm::Sha1);
+        let sha256 = totp(SHA256_SEED, TotpAlgorithm::Sha256);
+        let sha512 = totp(SHA512_SEED, TotpAlgorithm::Sha512);
+        for (timestamp, sha1_code, sha256_code, sha512_code) in VECTORS {
+            assert_eq!(sha1.generate(*timestamp), *sha1_code);
+            assert_eq!(sha256.generate(*timestamp), *sha256_code);
+            assert_eq!(sha512.generate(*timestamp), *sha512_code);
+        }
+    }
+
+    #[test]
+    fn parses_otpauth_uris_and_bare_secrets() {
+        let secret = BASE32_NOPAD.encode(SHA256_SEED);
+        let uri = format!(
+            "otpauth://totp/Example:alice@example.com?secret={}&issuer=Example&algorithm=SHA256&digits=8&period=30",
+            secret
+        );
+        assert_eq!(
+            Totp::parse(&uri).unwrap(),
+            totp(SHA256_SEED, TotpAlgorithm::Sha256)
+        );
+        assert_eq!(Totp::parse(&uri).unwrap().generate(59), "46119246");
+
+        let bare = Totp::parse(&BASE32_NOPAD.encode(SHA1_SEED).to_lowercase()).unwrap();
+        assert_eq!(bare.algorithm, TotpAlgorithm::Sha1);
+        assert_eq!(bare.digits, DEFAULT_DIGITS);
+        assert_eq!(bare.period, DEFAULT_PERIOD);
+        //the last six digits of the eight digit code
+        assert_eq!(bare.generate(59), "287082");
+    }
+
+    #[test]
+    fn rejects_invalid_otpauth_uris() {
+        assert!(Totp::parse("otpauth://hotp/Example?secret=GEZDGNBV").is_err());
+        assert!(Totp::parse("otpauth://totp/Example?issuer=Example").is_err());
+        assert!(Totp::parse("otpauth://totp/Example?secret=GEZDGNBV&algorithm=MD5").is_err());
+        assert!(Totp::parse("otpauth://totp/Example?secret=GEZDGNBV&digits=4").is_err());
+        assert!(Totp::parse("otpauth://totp/Example?secret=GEZDGNBV&period=0").is_err());
+        assert!(Totp::parse("not base32!").is_err());
+    }
+
+    #[test]
+    fn counts_down_to_the_next_period() {
+        let totp = totp(SHA1_SEED, TotpAlgorithm::Sha1);
+        assert_eq!(totp.remaining_seconds(59), 1);
+        assert_eq!(totp.remaining_seconds(60), 30);
+    }
+}
@@ -64,6 +64,13 @@ pub fn setup_logger() -> std::result::Result<(), fern::InitError> {
             .apply()?;
     Ok(())
 }
+//message of an error as it is shown to the user
+pub fn error_message(err: &pass::Error) -> String {
+    match err {
+        pass::Error::GenericDyn(message) => message.clone(),
+        err => format!("{:?}", err),
+    }
+}
 pub fn merge_json(a: &mut Value, b: &Value) {
     match (a, b) {
         (&mut Value::Object(ref mut a), &Value::Object(ref b)) => {
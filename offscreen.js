@@ -0,0 +1,46 @@
+//reaches the clipboard for the service worker of chrome, which has no clipboard of its own. the
+//clipboard is only cleared while it still holds the copied value, which is only known by its
+//salted digest
+function writeClipboard(value) {
+  document.addEventListener(
+    "copy",
+    (event) => {
+      event.preventDefault();
+      event.clipboardData.setData("text/plain", value);
+    },
+    { once: true },
+  );
+  return document.execCommand("copy");
+}
+
+//the same digest as `CopiedDigest` of the extension: sha-256 of the salt followed by the value
+async function saltedDigest(salt, value) {
+  const data = new TextEncoder().encode(salt + value);
+  const digest = await crypto.subtle.digest("SHA-256", data);
+  return Array.from(new Uint8Array(digest), (byte) =>
+    byte.toString(16).padStart(2, "0"),
+  ).join("");
+}
+
+chrome.runtime.onMessage.addListener((message, _sender, sendResponse) => {
+  switch (message.type) {
+    case "copy_to_clipboard": {
+      sendResponse({ copied: writeClipboard(message.value) });
+      return false;
+    }
+    case "clear_clipboard": {
+      const textarea = document.getElementById("clipboard");
+      textarea.value = "";
+      textarea.focus();
+      document.execCommand("paste");
+      const current = textarea.value;
+      textarea.value = "";
+      saltedDigest(message.salt, current).then((digest) => {
+        sendResponse({ cleared: digest === message.digest && writeClipboard("") });
+      });
+      return true;
+    }
+    default:
+      return false;
+  }
+});
@@ -3,11 +3,23 @@ use std::collections::HashMap;
 use crate::{store::LoginAction, Resource};
 
 use browser_rpass::{
+    audit::AuditOptions,
+    clipboard::copy_to_clipboard,
+    custom_field::{custom_fields_request_value, CustomField},
+    password_generator::PasswordGeneratorOptions,
+    payload::FailurePayload,
     request::{DataFieldType, RequestEnum},
-    store::MESSAGE_CONTEXT_POPUP,
+    response::{
+        AuditResponse, ErrorResponse, GeneratePasswordResponse, ResponseEnum, ResponseEnumTrait,
+        Status,
+    },
+    store::{MESSAGE_ACKNOWLEDGEMENTS_POP_UP, MESSAGE_CONTEXT_POPUP},
+    types::TOTP_FIELD,
+    url_match::{AccountUrl, MatchMode, URLS_FIELD, URL_MATCH_FIELD},
     util::create_request_acknowledgement,
 };
 use gloo_utils::format::JsValueSerdeExt;
+use log::{debug, error};
 use serde_json::{json, Value};
 use wasm_bindgen::JsValue;
 use yewdux;
@@ -33,14 +45,13 @@ pub fn fetch_accounts(store_id: Option<String>, path: Option<String>) -> String
         .post_message(<JsValue as JsValueSerdeExt>::from_serde(&fetch_request).unwrap());
     return acknowledgement;
 }
-pub fn login(store_id: String, is_default: bool, prev_store_id: Option<String>) {
+pub fn login(store_id: String, is_default: bool) {
     let dispatch = Dispatch::<PopupStore>::new();
     dispatch.apply(LoginAction::LoginStarted(store_id.clone(), HashMap::new()));
     let acknowledgement = create_request_acknowledgement();
     let login_request = RequestEnum::create_login_request(
         Some(acknowledgement.clone()),
         Some(store_id.clone()),
-        prev_store_id,
         is_default,
     );
     MESSAGE_CONTEXT_POPUP
@@ -90,6 +101,9 @@ pub fn create_account(
     username: Option<String>,
     password: Option<String>,
     note: Option<String>,
+    totp: Option<String>,
+    urls: Vec<AccountUrl>,
+    custom_fields: Vec<CustomField>,
 ) -> String {
     let dispatch = Dispatch::<PopupStore>::new();
     let mut data = HashMap::new();
@@ -108,12 +122,24 @@ pub fn create_account(
     data.insert(DataFieldType::Note, Value::String(note.clone().unwrap()));
     dispatch.apply(DataAction::ResourceCreationStarted(Resource::Account, data));
     let acknowledgement = create_request_acknowledgement();
+    let user_fields = custom_fields_request_value(&custom_fields, &[]);
+    let mut custom_fields = totp_custom_fields(totp);
+    if !urls.is_empty() {
+        custom_fields
+            .get_or_insert_with(HashMap::new)
+            .insert(URLS_FIELD.to_owned(), json!(urls));
+    }
+    if !user_fields.is_empty() {
+        custom_fields
+            .get_or_insert_with(HashMap::new)
+            .extend(user_fields);
+    }
     let create_request = RequestEnum::create_create_request(
         Some(store_id),
         username.clone(),
         domain.clone(),
         note.clone(),
-        None,
+        custom_fields,
         Resource::Account,
         password.clone().into(),
         Some(acknowledgement.clone()),
@@ -132,6 +158,10 @@ pub fn edit_account(
     username: Option<String>,
     password: Option<String>,
     note: Option<String>,
+    totp: Option<String>,
+    match_mode: Option<MatchMode>,
+    urls: Option<Vec<AccountUrl>>,
+    user_fields: Option<(Vec<CustomField>, Vec<String>)>,
     store_id: Option<String>,
 ) -> String {
     let dispatch = Dispatch::<PopupStore>::new();
@@ -189,6 +219,28 @@ pub fn edit_account(
         DataFieldType::ResourceID,
         serde_json::to_value(id.clone()).unwrap_or_default(),
     );
+    //an empty secret keeps the one already stored, since the popup never receives it
+    let mut custom_fields = totp_custom_fields(totp);
+    if let Some(match_mode) = match_mode {
+        custom_fields.get_or_insert_with(HashMap::new).insert(
+            URL_MATCH_FIELD.to_owned(),
+            serde_json::to_value(match_mode).unwrap_or_default(),
+        );
+    }
+    if let Some(urls) = urls {
+        custom_fields
+            .get_or_insert_with(HashMap::new)
+            .insert(URLS_FIELD.to_owned(), json!(urls));
+    }
+    //the fields removed in the editor are cleared along with the changed ones
+    if let Some((user_fields, removed)) = user_fields {
+        custom_fields
+            .get_or_insert_with(HashMap::new)
+            .extend(custom_fields_request_value(&user_fields, &removed));
+    }
+    if let Some(custom_fields) = custom_fields {
+        data.insert(DataFieldType::CustomField, json!(custom_fields));
+    }
     let edit_request = RequestEnum::create_edit_request(
         id,
         Resource::Account,
@@ -255,3 +307,185 @@ pub fn create_store(
         .post_message(<JsValue as JsValueSerdeExt>::from_serde(&create_request).unwrap());
     return acknowledgement;
 }
+
+fn totp_custom_fields(totp: Option<String>) -> Option<HashMap<String, Value>> {
+    totp.filter(|totp| !totp.trim().is_empty()).map(|totp| {
+        let mut custom_fields = HashMap::new();
+        custom_fields.insert(TOTP_FIELD.to_owned(), Value::String(totp.trim().to_owned()));
+        custom_fields
+    })
+}
+
+pub fn generate_password(
+    store_id: Option<String>,
+    options: Option<PasswordGeneratorOptions>,
+) -> String {
+    let acknowledgement = create_request_acknowledgement();
+    let generate_password_request = RequestEnum::create_generate_password_request(
+        store_id,
+        options,
+        Some(acknowledgement.clone()),
+        None,
+    );
+    MESSAGE_ACKNOWLEDGEMENTS_POP_UP.lock().unwrap().insert(
+        acknowledgement.clone(),
+        Box::new(|response: ResponseEnum, _port| {
+            Box::pin(async move {
+                let dispatch = Dispatch::<PopupStore>::new();
+                match response {
+                    ResponseEnum::GeneratePasswordResponse(GeneratePasswordResponse {
+                        generated: Some(generated),
+                        ..
+                    }) => {
+                        dispatch.apply(DataAction::PasswordGenerated(generated.payload));
+                    }
+                    ResponseEnum::GeneratePasswordResponse(response) => {
+                        dispatch.apply(DataAction::PasswordGenerationFailed(
+                            response
+                                .error
+                                .map(|error| error.payload)
+                                .unwrap_or_default(),
+                        ));
+                    }
+                    ResponseEnum::ErrorResponse(ErrorResponse { message, code, .. }) => {
+                        dispatch.apply(DataAction::PasswordGenerationFailed(FailurePayload {
+                            message,
+                            code,
+                            ..Default::default()
+                        }));
+                    }
+                    _ => {
+                        dispatch.apply(DataAction::PasswordGenerationFailed(
+                            FailurePayload::default(),
+                        ));
+                    }
+                }
+            })
+        }),
+    );
+    EXTENSION_PORT.lock().borrow().post_message(
+        <JsValue as JsValueSerdeExt>::from_serde(&generate_password_request).unwrap(),
+    );
+    return acknowledgement;
+}
+
+//request the current one-time code of an account from the native app. the secret stays there
+pub fn get_one_time_code(store_id: Option<String>, id: String) -> String {
+    let acknowledgement = create_request_acknowledgement();
+    let get_request = RequestEnum::create_get_request(
+        id.clone(),
+        Resource::Totp,
+        Some(acknowledgement.clone()),
+        None,
+        store_id,
+    );
+    MESSAGE_ACKNOWLEDGEMENTS_POP_UP.lock().unwrap().insert(
+        acknowledgement.clone(),
+        Box::new(|response: ResponseEnum, _port| {
+            Box::pin(async move {
+                let dispatch = Dispatch::<PopupStore>::new();
+                match response {
+                    ResponseEnum::GetResponse(response)
+                        if matches!(response.status, Status::Success) =>
+                    {
+                        dispatch.apply(DataAction::OneTimeCodeReceived(id, response.data));
+                    }
+                    ResponseEnum::GetResponse(response) => {
+                        dispatch.apply(DataAction::OneTimeCodeFailed(id, response.data));
+                    }
+                    _ => {
+                        dispatch.apply(DataAction::OneTimeCodeFailed(id, HashMap::new()));
+                    }
+                }
+            })
+        }),
+    );
+    EXTENSION_PORT
+        .lock()
+        .borrow()
+        .post_message(<JsValue as JsValueSerdeExt>::from_serde(&get_request).unwrap());
+    return acknowledgement;
+}
+
+//ask the native app to audit every entry of the store. the result is kept in the popup store
+pub fn audit_store(store_id: Option<String>, options: Option<AuditOptions>) -> String {
+    let dispatch = Dispatch::<PopupStore>::new();
+    dispatch.apply(DataAction::AuditStarted);
+    let acknowledgement = create_request_acknowledgement();
+    let audit_request =
+        RequestEnum::create_audit_request(store_id, options, Some(acknowledgement.clone()), None);
+    MESSAGE_ACKNOWLEDGEMENTS_POP_UP.lock().unwrap().insert(
+        acknowledgement.clone(),
+        Box::new(|response: ResponseEnum, _port| {
+            Box::pin(async move {
+                let dispatch = Dispatch::<PopupStore>::new();
+                match response {
+                    ResponseEnum::AuditResponse(AuditResponse {
+                        audit: Some(audit), ..
+                    }) => {
+                        dispatch.apply(DataAction::AuditCompleted(audit.payload.report));
+                    }
+                    ResponseEnum::AuditResponse(AuditResponse {
+                        error: Some(error), ..
+                    }) => {
+                        dispatch.apply(DataAction::AuditFailed(
+                            error.payload.message.unwrap_or("audit failed".to_owned()),
+                        ));
+                    }
+                    ResponseEnum::ErrorResponse(ErrorResponse {
+                        message: Some(message),
+                        ..
+                    }) => {
+                        dispatch.apply(DataAction::AuditFailed(message));
+                    }
+                    _ => {
+                        dispatch.apply(DataAction::AuditFailed("audit failed".to_owned()));
+                    }
+                }
+            })
+        }),
+    );
+    EXTENSION_PORT
+        .lock()
+        .borrow()
+        .post_message(<JsValue as JsValueSerdeExt>::from_serde(&audit_request).unwrap());
+    return acknowledgement;
+}
+
+//fill the account into the login form of the current tab, including forms inside iframes. the
+//popup closes once a frame of the account's site took it
+pub fn fill_account(store_id: Option<String>, id: String) -> String {
+    let acknowledgement = create_request_acknowledgement();
+    let fill_request =
+        RequestEnum::create_fill_request(store_id, id, Some(acknowledgement.clone()));
+    MESSAGE_ACKNOWLEDGEMENTS_POP_UP.lock().unwrap().insert(
+        acknowledgement.clone(),
+        Box::new(|response: ResponseEnum, _port| {
+            Box::pin(async move {
+                match response.get_status() {
+                    Status::Success => {
+                        gloo_utils::window().close().unwrap();
+                    }
+                    _ => {
+                        debug!("no login form of the account's site in this tab");
+                    }
+                }
+            })
+        }),
+    );
+    EXTENSION_PORT
+        .lock()
+        .borrow()
+        .post_message(<JsValue as JsValueSerdeExt>::from_serde(&fill_request).unwrap());
+    return acknowledgement;
+}
+
+//copy to the clipboard, which the service worker clears again after the delay set in the options
+pub fn copy_text(text: String) {
+    let port = EXTENSION_PORT.lock().borrow().clone();
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Err(err) = copy_to_clipboard(&port, &text).await {
+            error!("failed to copy to the clipboard: {:?}", err);
+        }
+    });
+}
@@ -3,16 +3,22 @@ mod account_entry_list;
 mod close_button;
 mod create_account_popup;
 mod create_store_popup;
+mod custom_field_list_input;
 mod delete_store_popup;
 mod dropdown_search;
 mod edit_account_popup;
+mod equivalent_domains_popup;
 mod form_input;
 mod loading_indicator;
 mod multi_select;
+mod one_time_code;
+mod password_generator;
+mod password_policy_rules;
 mod search_input;
 mod simple_popup;
 mod store_switcher;
 mod toast;
+mod url_list_input;
 use std::{cell::RefCell, rc::Rc};
 
 pub use account_entry::*;
@@ -20,17 +26,23 @@ pub use account_entry_list::*;
 pub use close_button::*;
 pub use create_account_popup::*;
 pub use create_store_popup::*;
+pub use custom_field_list_input::*;
 pub use delete_store_popup::*;
 pub use dropdown_search::*;
 pub use edit_account_popup::*;
+pub use equivalent_domains_popup::*;
 pub use form_input::*;
 pub use loading_indicator::*;
 use log::debug;
 pub use multi_select::*;
+pub use one_time_code::*;
+pub use password_generator::*;
+pub use password_policy_rules::*;
 pub use search_input::*;
 pub use simple_popup::*;
 pub use store_switcher::*;
 pub use toast::*;
+pub use url_list_input::*;
 use web_sys::MouseEvent;
 use yew::{
     classes, function_component, html, AttrValue, Callback, Classes, Html, NodeRef, Properties,
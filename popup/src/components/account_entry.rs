@@ -1,12 +1,13 @@
+use crate::api::extension_api::copy_text;
 use crate::components::*;
+use crate::store::PopupStore;
 use crate::Account;
-use browser_rpass::js_binding::clipboard_copy;
 use secrecy::ExposeSecret;
 use std::rc::Rc;
-use wasm_bindgen_futures;
 use yew;
 
 use yew::prelude::*;
+use yewdux::functional::use_selector;
 
 #[derive(Debug, PartialEq, Properties)]
 pub struct AccountEntryProps {
@@ -19,6 +20,10 @@ pub fn account_entry_component(props: &AccountEntryProps) -> Html {
     let account = props.account.clone();
     let password = account.get_password();
     let reveal_password = use_state(|| false);
+    let breach_count = {
+        let id = account.id.clone();
+        use_selector(move |state: &PopupStore| state.breach_counts.get(&id).cloned())
+    };
     let on_reveal = {
         let reveal_password = reveal_password.clone();
         Callback::from(move |_: MouseEvent| {
@@ -35,9 +40,7 @@ pub fn account_entry_component(props: &AccountEntryProps) -> Html {
             move |_: MouseEvent| {
                 let domain = domain.clone();
                 if let Some(domain) = domain {
-                    wasm_bindgen_futures::spawn_local(async move {
-                        let _ = clipboard_copy(&domain).await;
-                    });
+                    copy_text(domain);
                 }
             }
         })
@@ -46,10 +49,7 @@ pub fn account_entry_component(props: &AccountEntryProps) -> Html {
         let username = username.clone();
         Callback::from({
             move |_: MouseEvent| {
-                let username = username.clone();
-                wasm_bindgen_futures::spawn_local(async move {
-                    let _ = clipboard_copy(&username).await;
-                });
+                copy_text(username.clone());
             }
         })
     };
@@ -60,14 +60,38 @@ pub fn account_entry_component(props: &AccountEntryProps) -> Html {
             move |_: MouseEvent| {
                 let password = password.clone();
                 if let Some(password) = password.map(|p| p.expose_secret().clone()) {
-                    let password = password.clone();
-                    wasm_bindgen_futures::spawn_local(async move {
-                        _ = clipboard_copy(&password).await;
-                    });
+                    copy_text(password);
                 }
             }
         })
     };
+    let custom_field_prefix =
+        use_selector(|state: &PopupStore| state.data.custom_field_prefix.clone());
+    //the secrets of one-time code fields are not sent to the extension, so those are left out
+    let custom_fields = account
+        .typed_custom_fields(custom_field_prefix.as_deref())
+        .into_iter()
+        .filter(|field| !field.value.is_empty())
+        .map(|field| {
+            let copy_field = {
+                let value = field.value.clone();
+                Callback::from(move |_: MouseEvent| copy_text(value.clone()))
+            };
+            //hidden fields are revealed along with the password
+            let value_text = if field.field_type.is_secret() && !*reveal_password {
+                "************".to_string()
+            } else {
+                field.value.clone()
+            };
+            html! {
+                <div class="text-xs" key={field.name.clone()}>
+                    <span class="text-gray-500">{format!("{}: ", field.name)}</span>
+                    <span class="peer cursor-copy whitespace-pre-wrap" onclick={copy_field}>{value_text}</span>
+                    <Tooltip text={format!("click to copy {}", field.name)} class="bottom-tooltip"/>
+                </div>
+            }
+        })
+        .collect::<Html>();
     let password_cell = |revealed: bool| -> Html {
         let (eye_tooltip_text, password_text, eye_icon) = if revealed {
             (
@@ -128,6 +152,14 @@ pub fn account_entry_component(props: &AccountEntryProps) -> Html {
                         <div class="password">
                         {password_cell(*reveal_password)}
                         </div>
+                        if let Some(breach_count) = *breach_count {
+                            <div class="text-xs text-red-600 dark:text-red-400">
+                                {format!("found in {} breaches", breach_count)}
+                            </div>
+                        }
+                        if account.has_totp {
+                            <OneTimeCodeCell account_id={account.id.clone()}/>
+                        }
                     </td>
                     <td scope="row" class="font-medium text-gray-900 whitespace-nowrap dark:text-white">
                         <div>
@@ -138,6 +170,7 @@ pub fn account_entry_component(props: &AccountEntryProps) -> Html {
                                  }
                                 </span>
                             </div>
+                            {custom_fields}
                         </div>
                     </td>
                 </>
@@ -10,7 +10,7 @@ use yew;
 use yew::prelude::*;
 use yewdux::dispatch::Dispatch;
 
-use crate::api::extension_api::delete_resource;
+use crate::api::extension_api::{delete_resource, fill_account};
 
 use super::account_entry::AccountEntry;
 
@@ -58,9 +58,23 @@ pub fn account_entry_list_component(props: &AccountEntryListProps) -> Html {
             let id = account.id.clone();
             let account = account.clone();
             let on_edit_account = on_edit_account.clone();
+            let store_id = props.store_id.clone();
             html! {
                 <tr key={id.clone()} class="table-row">
                     <AccountEntry id={i} account={account.clone()}></AccountEntry>
+                    <td class="px-1 py-0.5 text-center">
+                        <a href="#"
+                            onclick={
+                                let id=id.clone();
+                                move |e:MouseEvent|{
+                                    e.prevent_default();
+                                    fill_account(Some(store_id.clone()),id.clone());
+                                }
+                            }
+                            class="font-medium text-blue-600 dark:text-blue-500 hover:underline">
+                            { "Fill" }
+                        </a>
+                    </td>
                     <td class="px-1 py-0.5 text-center">
                         <a href="#"
                             onclick={
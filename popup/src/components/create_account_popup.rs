@@ -5,6 +5,7 @@ use crate::{
     components::*,
     store::{DataAction, PopupStore, StoreDataStatus},
 };
+use browser_rpass::{custom_field::CustomField, url_match::AccountUrl};
 #[allow(unused_imports)]
 use log::*;
 use wasm_bindgen::JsCast;
@@ -26,12 +27,18 @@ pub fn create_account_popup(props: &Props) -> Html {
     let reveal_password = use_state(|| false);
     let password_input = use_state(|| String::new());
     let username_input = use_state(|| String::new());
+    let totp_input = use_state(|| String::new());
     let note_input = use_state(|| String::new());
     let domain_input = use_state(|| props.domain.clone().unwrap());
+    let urls_input = use_state(|| Vec::<AccountUrl>::new());
+    let custom_fields_input = use_state(|| Vec::<CustomField>::new());
     let on_create_submit = Callback::from({
+        let urls_input = urls_input.clone();
+        let custom_fields_input = custom_fields_input.clone();
         let password_input = password_input.clone();
         let username_input = username_input.clone();
         let note_input = note_input.clone();
+        let totp_input = totp_input.clone();
         let domain_input = domain_input.clone();
         let store_id = props.store_id.clone();
         move |event: SubmitEvent| {
@@ -42,9 +49,20 @@ pub fn create_account_popup(props: &Props) -> Html {
                 Some((*username_input).clone()),
                 Some((*password_input).clone()),
                 Some((*note_input).clone()),
+                Some((*totp_input).clone()),
+                (*urls_input).clone(),
+                (*custom_fields_input).clone(),
             );
         }
     });
+    let on_urls_change = {
+        let urls_input = urls_input.clone();
+        Callback::from(move |urls: Vec<AccountUrl>| urls_input.set(urls))
+    };
+    let on_custom_fields_change = {
+        let custom_fields_input = custom_fields_input.clone();
+        Callback::from(move |fields: Vec<CustomField>| custom_fields_input.set(fields))
+    };
     let store_status = use_selector(|state: &PopupStore| state.data_status.clone());
     let store_dispatch = Dispatch::<PopupStore>::new();
     let close_error = {
@@ -61,6 +79,19 @@ pub fn create_account_popup(props: &Props) -> Html {
             }
         }
     });
+    let generated_password = use_selector(|state: &PopupStore| state.generated_password.clone());
+    use_effect_with(generated_password.clone(), {
+        let password_input = password_input.clone();
+        let reveal_password = reveal_password.clone();
+        let dispatch = Dispatch::<PopupStore>::new();
+        move |generated_password: &Rc<Option<String>>| {
+            if let Some(generated_password) = generated_password.as_ref() {
+                password_input.set(generated_password.clone());
+                reveal_password.set(true);
+                dispatch.apply(DataAction::GeneratedPasswordCleared);
+            }
+        }
+    });
     let on_reveal = {
         let reveal_password = reveal_password.clone();
         Callback::from(move |e: MouseEvent| {
@@ -97,6 +128,20 @@ pub fn create_account_popup(props: &Props) -> Html {
             );
         })
     };
+    let on_totp_input = {
+        let totp_input = totp_input.clone();
+        Callback::from(move |event: InputEvent| {
+            event.prevent_default();
+            totp_input.set(
+                event
+                    .target()
+                    .unwrap()
+                    .dyn_into::<HtmlInputElement>()
+                    .unwrap()
+                    .value(),
+            );
+        })
+    };
     let on_username_input = {
         let username_input = username_input.clone();
         Callback::from(move |event: InputEvent| {
@@ -148,6 +193,8 @@ pub fn create_account_popup(props: &Props) -> Html {
                     style={format!("margin:-0.5rem;transform:translate(-100%,-100%);
                                    top:{top};left:{left};",top="81%",left="100%")}/>
                 </div>
+                <PasswordGeneratorButton store_id={Some(props.store_id.clone())}/>
+                <PasswordPolicyRules password={(*password_input).clone()}/>
             </div>
         }
     };
@@ -186,6 +233,18 @@ pub fn create_account_popup(props: &Props) -> Html {
                                 <label for="note" class="form-label">{"Note"}</label>
                                 <textarea  name="note" id="note" class="form-input" placeholder="Note" required={false} value={(*note_input).clone()} oninput={on_note_input.clone()}/>
                             </div>
+                            <div class="col-span-2">
+                                <label class="form-label">{ "Other URLs" }</label>
+                                <UrlListInput urls={(*urls_input).clone()} on_change={on_urls_change}/>
+                            </div>
+                            <div class="col-span-2">
+                                <label for="totp" class="form-label">{"One-time code"}</label>
+                                <input type="text" name="totp" id="totp" class="form-input" placeholder="otpauth:// URI or secret (optional)" required={false} value={(*totp_input).clone()} oninput={on_totp_input.clone()}/>
+                            </div>
+                            <div class="col-span-2">
+                                <label class="form-label">{ "Custom fields" }</label>
+                                <CustomFieldListInput fields={(*custom_fields_input).clone()} on_change={on_custom_fields_change}/>
+                            </div>
                         </div>
                         <button type="submit" class="accent-btn">
                         <PlusSign/>
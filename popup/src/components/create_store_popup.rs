@@ -3,6 +3,7 @@ use crate::{
     store::{DataAction, PopupStore, StoreDataStatus},
     BoolState, BoolStateAction,
 };
+use browser_rpass::payload::StorePayload;
 
 use super::*;
 #[allow(unused_imports)]
@@ -217,7 +218,7 @@ pub fn create_store_popup(props: &CreateStorePopupProps) -> yew::Html {
     let store_status = use_selector(|state: &PopupStore| state.data_status.clone());
     use_effect_with((store_status.clone(), props.handle_close.clone()), {
         move |(store_status, handle_close): &(Rc<StoreDataStatus>, Callback<MouseEvent>)| {
-            if let StoreDataStatus::StoreCreated(_, _) = **store_status {
+            if let StoreDataStatus::StoreCreated(_) = **store_status {
                 handle_close.emit(MouseEvent::new("click").unwrap());
             }
         }
@@ -231,10 +232,10 @@ pub fn create_store_popup(props: &CreateStorePopupProps) -> yew::Html {
                         <h3 class="text-lg font-semibold text-gray-900 dark:text-white">
                         {"Create Store"}
                         </h3>
-                            if let StoreDataStatus::StoreCreationFailed(_,ref store_id)=*store_status{
+                            if let StoreDataStatus::StoreCreationFailed(StorePayload{ref store_id,..})=*store_status{
                                 <Toast toast_type={ToastType::Error} on_close_button_clicked={close_toast.clone()} text={format!("Failed to create store: {store_id}")} class="absolute right-0 top-5 z-10"/>
                             }
-                            if let StoreDataStatus::StoreCreated(_,ref store_id)=*store_status{
+                            if let StoreDataStatus::StoreCreated(StorePayload{ref store_id,..})=*store_status{
                                 <Toast toast_type={ToastType::Success} on_close_button_clicked={close_toast.clone()} text={format!("Successfully created store: {store_id}")} class="absolute right-0 top-5 z-10"/>
                             }
                         <CloseButton onclick={&props.handle_close}/>
@@ -0,0 +1,128 @@
+use browser_rpass::custom_field::{is_reserved_field_name, CustomField, CustomFieldType};
+#[allow(unused_imports)]
+use log::*;
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement};
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq, Clone, Debug)]
+pub struct CustomFieldListInputProps {
+    pub fields: Vec<CustomField>,
+    pub on_change: Callback<Vec<CustomField>>,
+}
+
+//the custom fields of an account, one row each. the input of a row follows the type of the field
+#[function_component(CustomFieldListInput)]
+pub fn custom_field_list_input(props: &CustomFieldListInputProps) -> Html {
+    let on_add = {
+        let fields = props.fields.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |event: MouseEvent| {
+            event.prevent_default();
+            let mut fields = fields.clone();
+            fields.push(CustomField::default());
+            on_change.emit(fields);
+        })
+    };
+    let rows = props.fields.iter().enumerate().map(|(index, field)| {
+        let on_name_input = {
+            let fields = props.fields.clone();
+            let on_change = props.on_change.clone();
+            Callback::from(move |event: InputEvent| {
+                let mut fields = fields.clone();
+                fields[index].name = event
+                    .target()
+                    .unwrap()
+                    .dyn_into::<HtmlInputElement>()
+                    .unwrap()
+                    .value();
+                on_change.emit(fields);
+            })
+        };
+        let on_type_change = {
+            let fields = props.fields.clone();
+            let on_change = props.on_change.clone();
+            Callback::from(move |event: Event| {
+                let field_type = event
+                    .target()
+                    .unwrap()
+                    .dyn_into::<HtmlSelectElement>()
+                    .unwrap()
+                    .value();
+                let mut fields = fields.clone();
+                if let Some(field_type) = CustomFieldType::from_name(&field_type) {
+                    fields[index].field_type = field_type;
+                    on_change.emit(fields);
+                }
+            })
+        };
+        let on_value_input = {
+            let fields = props.fields.clone();
+            let on_change = props.on_change.clone();
+            Callback::from(move |event: InputEvent| {
+                let target = event.target().unwrap();
+                let value = match target.dyn_ref::<HtmlTextAreaElement>() {
+                    Some(textarea_element) => textarea_element.value(),
+                    None => target.dyn_into::<HtmlInputElement>().unwrap().value(),
+                };
+                let mut fields = fields.clone();
+                fields[index].value = value;
+                on_change.emit(fields);
+            })
+        };
+        let on_remove = {
+            let fields = props.fields.clone();
+            let on_change = props.on_change.clone();
+            Callback::from(move |event: MouseEvent| {
+                event.prevent_default();
+                let mut fields = fields.clone();
+                fields.remove(index);
+                on_change.emit(fields);
+            })
+        };
+        let value_input = match field.field_type {
+            CustomFieldType::Multiline => html! {
+                <textarea class="form-input" placeholder="Value" value={field.value.clone()} oninput={on_value_input}/>
+            },
+            field_type => {
+                let (input_type, placeholder) = match field_type {
+                    CustomFieldType::Hidden => ("password", "Value"),
+                    CustomFieldType::Url => ("url", "https://example.com"),
+                    CustomFieldType::Email => ("email", "name@example.com"),
+                    //the saved secret is never sent to the extension, an empty value keeps it
+                    CustomFieldType::Totp => ("text", "otpauth:// URI or secret, empty keeps the saved one"),
+                    _ => ("text", "Value"),
+                };
+                html! {
+                    <input type={input_type} class="form-input" placeholder={placeholder} value={field.value.clone()} oninput={on_value_input}/>
+                }
+            }
+        };
+        let reserved = is_reserved_field_name(&field.name);
+        html! {
+            <div class="mb-2" key={index}>
+                <div class="flex gap-2">
+                    <input type="text" class="form-input" placeholder="Name" required={true} value={field.name.clone()} oninput={on_name_input}/>
+                    <select class="form-input" style="width: auto;" onchange={on_type_change}>
+                        {
+                            CustomFieldType::ALL.iter().map(|field_type| html! {
+                                <option value={field_type.name()} selected={field.field_type == *field_type}>{field_type.label()}</option>
+                            }).collect::<Html>()
+                        }
+                    </select>
+                    <button type="button" class="warning-btn" onclick={on_remove}>{"x"}</button>
+                </div>
+                {value_input}
+                if reserved {
+                    <p class="text-xs text-red-600 dark:text-red-400">{"This name is used by the extension, the field will not be saved"}</p>
+                }
+            </div>
+        }
+    });
+    html! {
+        <div>
+            { for rows }
+            <button type="button" class="primary-btn" onclick={on_add}>{"Add field"}</button>
+        </div>
+    }
+}
@@ -5,6 +5,7 @@ use crate::{
     api::extension_api::delete_store,
     store::{DataAction, PopupStore, StoreDataStatus},
 };
+use browser_rpass::payload::StorePayload;
 #[allow(unused_imports)]
 use log::*;
 use serde::{Deserialize, Serialize};
@@ -107,7 +108,7 @@ pub fn delete_store_popup(props: &DeleteStorePopupProps) -> Html {
     use_effect_with((store_status.clone(), props.handle_close.clone()), {
         let dispatch = store_dispatch.clone();
         move |(store_status, handle_close): &(Rc<StoreDataStatus>, Callback<MouseEvent>)| {
-            if let StoreDataStatus::StoreDeleted(_, _) = **store_status {
+            if let StoreDataStatus::StoreDeleted(_) = **store_status {
                 handle_close.emit(MouseEvent::new("click").unwrap());
             }
         }
@@ -122,10 +123,10 @@ pub fn delete_store_popup(props: &DeleteStorePopupProps) -> Html {
                             <h3 class="text-lg font-semibold text-gray-900 dark:text-white">
                             {"Delete Store"}
                             </h3>
-                            if let StoreDataStatus::StoreDeletionFailed(_,ref store_id)=*store_status{
+                            if let StoreDataStatus::StoreDeletionFailed(StorePayload{ref store_id,..})=*store_status{
                                 <Toast toast_type={ToastType::Error} on_close_button_clicked={close_toast.clone()} text={format!("Failed to delete store: {store_id}")} class="absolute right-0 top-5 z-10"/>
                             }
-                            if let StoreDataStatus::StoreDeleted(_,ref store_id)=*store_status{
+                            if let StoreDataStatus::StoreDeleted(StorePayload{ref store_id,..})=*store_status{
                                 <Toast toast_type={ToastType::Success} on_close_button_clicked={close_toast.clone()} text={format!("Successfully deleted store: {store_id}")} class="absolute right-0 top-5 z-10"/>
                             }
                             <CloseButton onclick={&props.handle_close}/>
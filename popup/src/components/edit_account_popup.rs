@@ -5,14 +5,18 @@ use crate::{
     components::*,
     store::{DataAction, PopupStore, StoreDataStatus},
 };
-use browser_rpass::types::Account;
+use browser_rpass::{
+    custom_field::CustomField,
+    types::Account,
+    url_match::{AccountUrl, MatchMode, URLS_FIELD, URL_MATCH_FIELD},
+};
 #[allow(unused_imports)]
 use log::*;
 use secrecy::ExposeSecret;
 use wasm_bindgen::JsCast;
 use yew;
 
-use web_sys::{HtmlInputElement, HtmlTextAreaElement};
+use web_sys::{HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement};
 use yew::prelude::*;
 use yewdux::{dispatch::Dispatch, functional::use_selector};
 
@@ -34,17 +38,60 @@ pub fn edit_account_popup(props: &Props) -> Html {
             .unwrap_or_default()
     });
     let username_input = use_state(|| account.username.clone());
+    let totp_input = use_state(|| String::new());
     let note_input = use_state(|| account.note.clone().unwrap_or_default());
     let domain_input = use_state(|| account.domain.clone().unwrap());
+    let match_mode_input = use_state(|| account.match_mode().name().to_owned());
+    let match_pattern_input =
+        use_state(|| account.match_mode().pattern().cloned().unwrap_or_default());
+    let urls_input = use_state(|| account.urls.clone());
+    let custom_field_prefix =
+        use_selector(|state: &PopupStore| state.data.custom_field_prefix.clone());
+    let saved_custom_fields = account.typed_custom_fields(custom_field_prefix.as_deref());
+    let custom_fields_input = use_state(|| saved_custom_fields.clone());
     let on_edit_submit = Callback::from({
         let account = account.clone();
+        let urls_input = urls_input.clone();
+        let custom_fields_input = custom_fields_input.clone();
         let password_input = password_input.clone();
         let username_input = username_input.clone();
         let note_input = note_input.clone();
+        let totp_input = totp_input.clone();
         let domain_input = domain_input.clone();
+        let match_mode_input = match_mode_input.clone();
+        let match_pattern_input = match_pattern_input.clone();
         let store_id = props.store_id.clone();
         move |event: SubmitEvent| {
             event.prevent_default();
+            //accounts without the field keep matching on the base domain without one being added
+            let match_mode =
+                MatchMode::from_name(&match_mode_input, (*match_pattern_input).clone()).filter(
+                    |match_mode| {
+                        *match_mode != account.match_mode()
+                            || account
+                                .custom_fields
+                                .as_ref()
+                                .is_some_and(|fields| fields.contains_key(URL_MATCH_FIELD))
+                    },
+                );
+            let urls = Some((*urls_input).clone()).filter(|urls| {
+                *urls != account.urls
+                    || account
+                        .custom_fields
+                        .as_ref()
+                        .is_some_and(|fields| fields.contains_key(URLS_FIELD))
+            });
+            //renamed fields are saved under the new name and cleared under the old one
+            let user_fields = Some((*custom_fields_input).clone())
+                .filter(|fields| *fields != saved_custom_fields)
+                .map(|fields| {
+                    let removed = saved_custom_fields
+                        .iter()
+                        .map(|field| field.name.clone())
+                        .filter(|name| !fields.iter().any(|field| field.name.trim() == name))
+                        .collect::<Vec<_>>();
+                    (fields, removed)
+                });
             let domain = Some((*domain_input).clone());
             let username = Some((*username_input).clone());
             let password = Some((*password_input).clone());
@@ -55,10 +102,27 @@ pub fn edit_account_popup(props: &Props) -> Html {
                 username,
                 password,
                 note,
+                Some((*totp_input).clone()),
+                match_mode,
+                urls,
+                user_fields,
                 Some(store_id.clone()),
             );
         }
     });
+    let generated_password = use_selector(|state: &PopupStore| state.generated_password.clone());
+    use_effect_with(generated_password.clone(), {
+        let password_input = password_input.clone();
+        let reveal_password = reveal_password.clone();
+        let dispatch = Dispatch::<PopupStore>::new();
+        move |generated_password: &Rc<Option<String>>| {
+            if let Some(generated_password) = generated_password.as_ref() {
+                password_input.set(generated_password.clone());
+                reveal_password.set(true);
+                dispatch.apply(DataAction::GeneratedPasswordCleared);
+            }
+        }
+    });
     let on_reveal = {
         let reveal_password = reveal_password.clone();
         Callback::from(move |e: MouseEvent| {
@@ -95,6 +159,20 @@ pub fn edit_account_popup(props: &Props) -> Html {
             );
         })
     };
+    let on_totp_input = {
+        let totp_input = totp_input.clone();
+        Callback::from(move |event: InputEvent| {
+            event.prevent_default();
+            totp_input.set(
+                event
+                    .target()
+                    .unwrap()
+                    .dyn_into::<HtmlInputElement>()
+                    .unwrap()
+                    .value(),
+            );
+        })
+    };
     let on_username_input = {
         let username_input = username_input.clone();
         Callback::from(move |event: InputEvent| {
@@ -123,6 +201,41 @@ pub fn edit_account_popup(props: &Props) -> Html {
             );
         })
     };
+    let on_match_mode_change = {
+        let match_mode_input = match_mode_input.clone();
+        Callback::from(move |event: Event| {
+            match_mode_input.set(
+                event
+                    .target()
+                    .unwrap()
+                    .dyn_into::<HtmlSelectElement>()
+                    .unwrap()
+                    .value(),
+            );
+        })
+    };
+    let on_match_pattern_input = {
+        let match_pattern_input = match_pattern_input.clone();
+        Callback::from(move |event: InputEvent| {
+            event.prevent_default();
+            match_pattern_input.set(
+                event
+                    .target()
+                    .unwrap()
+                    .dyn_into::<HtmlInputElement>()
+                    .unwrap()
+                    .value(),
+            );
+        })
+    };
+    let on_urls_change = {
+        let urls_input = urls_input.clone();
+        Callback::from(move |urls: Vec<AccountUrl>| urls_input.set(urls))
+    };
+    let on_custom_fields_change = {
+        let custom_fields_input = custom_fields_input.clone();
+        Callback::from(move |fields: Vec<CustomField>| custom_fields_input.set(fields))
+    };
     let store_status = use_selector(|state: &PopupStore| state.data_status.clone());
     let store_dispatch = Dispatch::<PopupStore>::new();
     use_effect_with((store_status.clone(), props.handle_close.clone()), {
@@ -161,6 +274,8 @@ pub fn edit_account_popup(props: &Props) -> Html {
                     style={format!("margin:-0.5rem;transform:translate(-100%,-100%);
                                    top:{top};left:{left};",top="81%",left="100%")}/>
                 </div>
+                <PasswordGeneratorButton store_id={Some(props.store_id.clone())}/>
+                <PasswordPolicyRules password={(*password_input).clone()}/>
             </div>
         }
     };
@@ -202,10 +317,38 @@ pub fn edit_account_popup(props: &Props) -> Html {
                                 <label for="url" class="form-label">{ "Domain/URL" }</label>
                                 <input type="text" name="url" id="url" class="form-input" placeholder="URL" required={true} value={(*domain_input).clone()}  oninput={on_domain_input.clone()}/>
                             </div>
+                            <div class="col-span-2 sm:col-span-1">
+                                <label for="url-match" class="form-label">{ "Match URLs by" }</label>
+                                <select name="url-match" id="url-match" class="form-input" onchange={on_match_mode_change}>
+                                    {
+                                        MATCH_MODE_OPTIONS.iter().map(|(value, label)| html! {
+                                            <option value={*value} selected={*match_mode_input == *value}>{*label}</option>
+                                        }).collect::<Html>()
+                                    }
+                                </select>
+                            </div>
+                            if *match_mode_input == "starts_with" || *match_mode_input == "regex" {
+                                <div class="col-span-2">
+                                    <label for="url-pattern" class="form-label">{ "URL pattern" }</label>
+                                    <input type="text" name="url-pattern" id="url-pattern" class="form-input" placeholder={if *match_mode_input == "regex" { "^https://(www\\.)?example\\.com/login" } else { "https://example.com/login" }} required={*match_mode_input == "regex"} value={(*match_pattern_input).clone()} oninput={on_match_pattern_input}/>
+                                </div>
+                            }
+                            <div class="col-span-2">
+                                <label class="form-label">{ "Other URLs" }</label>
+                                <UrlListInput urls={(*urls_input).clone()} on_change={on_urls_change}/>
+                            </div>
                             <div class="col-span-2">
                                 <label for="note" class="form-label">{"Note"}</label>
                                 <textarea  name="note" id="note" class="form-input" placeholder="Note" required={false} value={(*note_input).clone()} oninput={on_note_input.clone()}/>
                             </div>
+                            <div class="col-span-2">
+                                <label for="totp" class="form-label">{"One-time code"}</label>
+                                <input type="text" name="totp" id="totp" class="form-input" placeholder={if account.has_totp { "unchanged" } else { "otpauth:// URI or secret (optional)" }} required={false} value={(*totp_input).clone()} oninput={on_totp_input.clone()}/>
+                            </div>
+                            <div class="col-span-2">
+                                <label class="form-label">{ "Custom fields" }</label>
+                                <CustomFieldListInput fields={(*custom_fields_input).clone()} on_change={on_custom_fields_change}/>
+                            </div>
                         </div>
                         <button type="submit" class="accent-btn">
                             <EditIcon/>
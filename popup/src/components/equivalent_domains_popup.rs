@@ -0,0 +1,94 @@
+use super::*;
+use browser_rpass::store::{load_equivalent_domains, set_equivalent_domains};
+#[allow(unused_imports)]
+use log::*;
+use wasm_bindgen::JsCast;
+use web_sys::HtmlTextAreaElement;
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq, Clone, Debug)]
+pub struct EquivalentDomainsPopupProps {
+    #[prop_or_default]
+    pub handle_close: Callback<MouseEvent>,
+}
+
+//one group per line, the domains of a group separated by commas or spaces
+fn parse_groups(text: &str) -> Vec<Vec<String>> {
+    text.lines()
+        .map(|line| {
+            line.split(|c: char| c == ',' || c.is_whitespace())
+                .map(|domain| domain.trim().to_lowercase())
+                .filter(|domain| !domain.is_empty())
+                .collect::<Vec<String>>()
+        })
+        .filter(|group| group.len() > 1)
+        .collect()
+}
+
+#[function_component(EquivalentDomainsPopup)]
+pub fn equivalent_domains_popup(props: &EquivalentDomainsPopupProps) -> Html {
+    let groups_input = use_state(|| String::new());
+    use_effect_with((), {
+        let groups_input = groups_input.clone();
+        move |_| {
+            wasm_bindgen_futures::spawn_local(async move {
+                let groups = load_equivalent_domains().await;
+                groups_input.set(
+                    groups
+                        .iter()
+                        .map(|group| group.join(", "))
+                        .collect::<Vec<String>>()
+                        .join("\n"),
+                );
+            });
+        }
+    });
+    let on_groups_input = {
+        let groups_input = groups_input.clone();
+        Callback::from(move |event: InputEvent| {
+            event.prevent_default();
+            groups_input.set(
+                event
+                    .target()
+                    .unwrap()
+                    .dyn_into::<HtmlTextAreaElement>()
+                    .unwrap()
+                    .value(),
+            );
+        })
+    };
+    let on_submit = {
+        let groups_input = groups_input.clone();
+        let handle_close = props.handle_close.clone();
+        Callback::from(move |event: SubmitEvent| {
+            event.prevent_default();
+            let groups = parse_groups(&groups_input);
+            let handle_close = handle_close.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                set_equivalent_domains(&groups).await;
+                handle_close.emit(MouseEvent::new("click").unwrap());
+            });
+        })
+    };
+    html! {
+        <div id="equivalent-domains-popup" tabindex="-1" aria-hidden="true" class="overflow-y-auto overflow-x-hidden shadow-lg fixed top-0 right-0 left-0 z-50 justify-center items-center w-full md:inset-0 max-h-full">
+            <div class="relative w-full max-h-full">
+                <div class="relative bg-white rounded-lg shadow dark:bg-gray-900">
+                    <div class="flex items-center justify-between p-4 md:p-5 border-b rounded-t dark:border-gray-600">
+                        <h3 class="text-lg font-semibold text-gray-900 dark:text-white">
+                        {"Equivalent Domains"}
+                        </h3>
+                        <CloseButton onclick={&props.handle_close}/>
+                    </div>
+                    <form onsubmit={on_submit} class="p-4 md:p-5" autocomplete="off">
+                        <label for="equivalent-domains" class="form-label">{"Domains on a line share their accounts"}</label>
+                        <textarea name="equivalent-domains" id="equivalent-domains" class="form-input mb-4" rows="6" placeholder="amazon.com, amazon.de, amazon.co.uk" value={(*groups_input).clone()} oninput={on_groups_input}/>
+                        <button type="submit" class="accent-btn">
+                            {"Save"}
+                        </button>
+                    </form>
+                </div>
+            </div>
+        </div>
+    }
+}
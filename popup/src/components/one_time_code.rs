@@ -0,0 +1,73 @@
+use crate::{
+    api::extension_api::{copy_text, get_one_time_code},
+    components::*,
+    store::PopupStore,
+};
+use gloo::timers::callback::Interval;
+#[allow(unused_imports)]
+use log::*;
+use yew;
+use yew::prelude::*;
+use yewdux::functional::use_selector;
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub account_id: String,
+}
+
+//live one-time code of an account. a new code is requested from the native app whenever the
+//current one expires
+#[function_component(OneTimeCodeCell)]
+pub fn one_time_code_cell(props: &Props) -> Html {
+    let now = use_state(|| js_sys::Date::now());
+    let store_id = use_selector(|state: &PopupStore| state.persistent_data.store_id.clone());
+    let one_time_code = {
+        let account_id = props.account_id.clone();
+        use_selector(move |state: &PopupStore| state.one_time_codes.get(&account_id).cloned())
+    };
+    let one_time_code = (*one_time_code).clone();
+    use_effect_with((), {
+        let now = now.clone();
+        move |_| {
+            let interval = Interval::new(1000, move || now.set(js_sys::Date::now()));
+            move || drop(interval)
+        }
+    });
+    let expired = one_time_code
+        .as_ref()
+        .map_or(true, |one_time_code| *now >= one_time_code.expires_at);
+    use_effect_with(expired, {
+        let account_id = props.account_id.clone();
+        let store_id = store_id.clone();
+        move |expired: &bool| {
+            if *expired {
+                get_one_time_code((*store_id).clone(), account_id);
+            }
+        }
+    });
+    let copy_code = {
+        let one_time_code = one_time_code.clone();
+        Callback::from(move |_: MouseEvent| {
+            if let Some(one_time_code) = one_time_code.clone() {
+                copy_text(one_time_code.code);
+            }
+        })
+    };
+    match one_time_code {
+        Some(one_time_code) if !expired => {
+            let remaining = ((one_time_code.expires_at - *now) / 1000.0).ceil() as u64;
+            html! {
+                <div class="account-totp-cell text-xs">
+                    <span class="peer cursor-copy font-mono" onclick={copy_code}>
+                        {one_time_code.code.clone()}
+                    </span>
+                    <span class="text-gray-500 ms-1">{format!("{}s", remaining)}</span>
+                    <Tooltip text={"click to copy one-time code".to_string()} class="bottom-tooltip"/>
+                </div>
+            }
+        }
+        _ => html! {
+            <div class="account-totp-cell text-xs text-gray-500">{"------"}</div>
+        },
+    }
+}
@@ -0,0 +1,59 @@
+use crate::api::extension_api::generate_password;
+use browser_rpass::password_generator::{GeneratorMode, PasswordGeneratorOptions};
+#[allow(unused_imports)]
+use log::*;
+use wasm_bindgen::JsCast;
+use web_sys::HtmlInputElement;
+use yew;
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub store_id: Option<String>,
+    #[prop_or_default]
+    pub class: Classes,
+}
+
+#[function_component(PasswordGeneratorButton)]
+pub fn password_generator_button(props: &Props) -> Html {
+    let passphrase = use_state(|| false);
+    let on_generate = {
+        let store_id = props.store_id.clone();
+        let passphrase = passphrase.clone();
+        Callback::from(move |event: MouseEvent| {
+            event.prevent_default();
+            let options = PasswordGeneratorOptions {
+                mode: if *passphrase {
+                    GeneratorMode::Passphrase
+                } else {
+                    GeneratorMode::Characters
+                },
+                ..Default::default()
+            };
+            generate_password(store_id.clone(), Some(options));
+        })
+    };
+    let on_passphrase_toggle = {
+        let passphrase = passphrase.clone();
+        Callback::from(move |event: Event| {
+            let checked = event
+                .target()
+                .unwrap()
+                .dyn_into::<HtmlInputElement>()
+                .unwrap()
+                .checked();
+            passphrase.set(checked);
+        })
+    };
+    html! {
+        <div class={classes!("flex", "items-center", "gap-2", "mt-2", props.class.clone())}>
+            <button type="button" class="primary-btn" onclick={on_generate}>
+                {"Generate"}
+            </button>
+            <label class="form-label inline-flex items-center gap-1 mb-0">
+                <input type="checkbox" checked={*passphrase} onchange={on_passphrase_toggle}/>
+                {"Passphrase"}
+            </label>
+        </div>
+    }
+}
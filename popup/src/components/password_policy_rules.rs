@@ -0,0 +1,58 @@
+use crate::store::PopupStore;
+#[allow(unused_imports)]
+use log::*;
+use yew;
+use yew::prelude::*;
+use yewdux::functional::use_selector;
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub password: String,
+    #[prop_or_default]
+    pub class: Classes,
+}
+
+//lists the rules of the current store's password policy, marking the ones the password breaks.
+//reuse of previous passwords can only be checked by the native app, so that rule is marked
+//only once the native app has rejected the password. a policy the native app ignores, for
+//example because it isn't signed, is reported instead.
+#[function_component(PasswordPolicyRules)]
+pub fn password_policy_rules(props: &Props) -> Html {
+    let password_policy = use_selector(|state: &PopupStore| state.data.password_policy.clone());
+    let password_policy_error =
+        use_selector(|state: &PopupStore| state.data.password_policy_error.clone());
+    let policy_violations = use_selector(|state: &PopupStore| state.policy_violations.clone());
+    if let Some(error) = (*password_policy_error).clone() {
+        return html! {
+            <div class={classes!("text-xs", "mt-2", "text-yellow-600", "dark:text-yellow-400", props.class.clone())}>
+                {format!("The store's password policy is ignored: {}", error)}
+            </div>
+        };
+    }
+    let Some(password_policy) = (*password_policy).clone() else {
+        return html! {};
+    };
+    let rules = password_policy.rules();
+    if rules.is_empty() {
+        return html! {};
+    }
+    let violations = password_policy.validate(&props.password, &[]);
+    html! {
+        <ul class={classes!("text-xs", "mt-2", props.class.clone())}>
+            { rules.into_iter().map(|rule| {
+                let violated = violations.contains(&rule) || policy_violations.contains(&rule);
+                let class = if violated {
+                    "text-red-600 dark:text-red-400"
+                } else {
+                    "text-green-600 dark:text-green-400"
+                };
+                html! {
+                    <li class={class}>
+                        {if violated { "✗ " } else { "✓ " }}
+                        {format!("Password {}", rule)}
+                    </li>
+                }
+            }).collect::<Html>() }
+        </ul>
+    }
+}
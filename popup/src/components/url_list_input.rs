@@ -0,0 +1,121 @@
+use browser_rpass::url_match::{AccountUrl, MatchMode};
+#[allow(unused_imports)]
+use log::*;
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+pub const MATCH_MODE_OPTIONS: &[(&str, &str)] = &[
+    ("base_domain", "Base domain"),
+    ("host", "Exact host"),
+    ("host_port", "Host and port"),
+    ("starts_with", "URL starts with"),
+    ("regex", "Regular expression"),
+    ("never", "Never"),
+];
+
+#[derive(Properties, PartialEq, Clone, Debug)]
+pub struct UrlListInputProps {
+    pub urls: Vec<AccountUrl>,
+    pub on_change: Callback<Vec<AccountUrl>>,
+}
+
+//the other urls of an account, one row each. a "starts with" row matches urls starting with the
+//url itself, a regex row matches by its own pattern
+#[function_component(UrlListInput)]
+pub fn url_list_input(props: &UrlListInputProps) -> Html {
+    let on_add = {
+        let urls = props.urls.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |event: MouseEvent| {
+            event.prevent_default();
+            let mut urls = urls.clone();
+            urls.push(AccountUrl::default());
+            on_change.emit(urls);
+        })
+    };
+    let rows = props.urls.iter().enumerate().map(|(index, account_url)| {
+        let on_url_input = {
+            let urls = props.urls.clone();
+            let on_change = props.on_change.clone();
+            Callback::from(move |event: InputEvent| {
+                let mut urls = urls.clone();
+                urls[index].url = event
+                    .target()
+                    .unwrap()
+                    .dyn_into::<HtmlInputElement>()
+                    .unwrap()
+                    .value();
+                on_change.emit(urls);
+            })
+        };
+        let on_mode_change = {
+            let urls = props.urls.clone();
+            let on_change = props.on_change.clone();
+            Callback::from(move |event: Event| {
+                let mode = event
+                    .target()
+                    .unwrap()
+                    .dyn_into::<HtmlSelectElement>()
+                    .unwrap()
+                    .value();
+                let mut urls = urls.clone();
+                let pattern = urls[index].match_mode.pattern().cloned().unwrap_or_default();
+                if let Some(match_mode) = MatchMode::from_name(&mode, pattern) {
+                    urls[index].match_mode = match_mode;
+                    on_change.emit(urls);
+                }
+            })
+        };
+        let on_pattern_input = {
+            let urls = props.urls.clone();
+            let on_change = props.on_change.clone();
+            Callback::from(move |event: InputEvent| {
+                let pattern = event
+                    .target()
+                    .unwrap()
+                    .dyn_into::<HtmlInputElement>()
+                    .unwrap()
+                    .value();
+                let mut urls = urls.clone();
+                urls[index].match_mode = MatchMode::Regex(pattern);
+                on_change.emit(urls);
+            })
+        };
+        let on_remove = {
+            let urls = props.urls.clone();
+            let on_change = props.on_change.clone();
+            Callback::from(move |event: MouseEvent| {
+                event.prevent_default();
+                let mut urls = urls.clone();
+                urls.remove(index);
+                on_change.emit(urls);
+            })
+        };
+        let mode = account_url.match_mode.name();
+        html! {
+            <div class="mb-2" key={index}>
+                <div class="flex gap-2">
+                    <input type="text" class="form-input" placeholder="URL" required={true} value={account_url.url.clone()} oninput={on_url_input}/>
+                    <select class="form-input" style="width: auto;" onchange={on_mode_change}>
+                        {
+                            MATCH_MODE_OPTIONS.iter().map(|(value, label)| html! {
+                                <option value={*value} selected={mode == *value}>{*label}</option>
+                            }).collect::<Html>()
+                        }
+                    </select>
+                    <button type="button" class="warning-btn" onclick={on_remove}>{"x"}</button>
+                </div>
+                if let MatchMode::Regex(pattern) = &account_url.match_mode {
+                    <input type="text" class="form-input" placeholder="^https://(www\\.)?example\\.com/login" required={true} value={pattern.clone()} oninput={on_pattern_input}/>
+                }
+            </div>
+        }
+    });
+    html! {
+        <div>
+            { for rows }
+            <button type="button" class="primary-btn" onclick={on_add}>{"Add URL"}</button>
+        </div>
+    }
+}
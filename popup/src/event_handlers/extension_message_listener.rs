@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use browser_rpass::{
     js_binding::extension_api::Port,
-    request::{DataFieldType, SessionEventType},
+    request::SessionEventType,
     response::{MessageEnum, RequestEnum, ResponseEnumTrait},
     store::{MESSAGE_ACKNOWLEDGEMENTS_POP_UP, MESSAGE_CONTEXT_POPUP},
     types::Resource,
@@ -49,25 +49,31 @@ pub fn create_message_listener(port: &Port) -> Closure<dyn Fn(JsValue)> {
 
                             let resource = event_request.resource.clone().unwrap_or(vec![]);
                             match event_type {
-                                &SessionEventType::Login => {
-                                    let store_id = data
-                                        .get(&DataFieldType::StoreID)
-                                        .map(|v| v.as_str().unwrap().to_owned())
-                                        .unwrap();
+                                &SessionEventType::Login(ref payload) => {
+                                    let payload = payload.payload.clone();
                                     if let LoginStatus::LoginStarted(current_store_id) =
                                         dispatch.get().login_status.clone()
                                     {
-                                        if current_store_id == store_id {
-                                            dispatch.apply(LoginAction::LoginSucceeded(data));
-                                            fetch_accounts(Some(store_id.clone()), None);
+                                        if current_store_id == payload.store_id {
+                                            let store_id = payload.store_id.clone();
+                                            dispatch.apply(LoginAction::LoginSucceeded(payload));
+                                            fetch_accounts(Some(store_id), None);
                                         }
                                     } else {
-                                        dispatch.apply(LoginAction::Login(store_id.clone(), data));
+                                        dispatch.apply(LoginAction::Login(payload));
                                     }
                                 }
                                 &SessionEventType::NativeAppConnectionError => {
                                     dispatch.apply(DataAction::NativeAppConnectionError);
                                 }
+                                &SessionEventType::RequestTimedOut(ref timeout) => {
+                                    dispatch.apply(DataAction::RequestTimedOut(timeout.clone()));
+                                }
+                                &SessionEventType::NativeAppConnection(ref native_connection) => {
+                                    dispatch.apply(DataAction::NativeConnectionChanged(
+                                        native_connection.clone(),
+                                    ));
+                                }
                                 &SessionEventType::LoginError => {
                                     let context = contexts
                                         .get(event_request.acknowledgement.as_ref().unwrap())
@@ -80,104 +86,120 @@ pub fn create_message_listener(port: &Port) -> Closure<dyn Fn(JsValue)> {
                                         .to_string();
                                     dispatch.apply(LoginAction::LoginError(data, store_id));
                                 }
-                                &SessionEventType::Logout => {
-                                    let store_id = data
-                                        .get(&DataFieldType::StoreID)
-                                        .map(|v| v.as_str().unwrap().to_owned());
-                                    dispatch.apply(LoginAction::Logout(store_id, data));
+                                &SessionEventType::Logout(ref payload) => {
+                                    dispatch.apply(LoginAction::Logout(payload.store_id.clone()));
                                 }
-                                &SessionEventType::Delete => {
+                                &SessionEventType::Delete(ref payload) => {
                                     let resource = resource[0].clone();
                                     match resource {
                                         Resource::Account => {
-                                            dispatch
-                                                .apply(DataAction::ResourceDeleted(resource, data));
+                                            dispatch.apply(DataAction::ResourceDeleted(
+                                                resource,
+                                                payload.id.clone(),
+                                            ));
                                         }
                                         _ => {}
                                     }
                                 }
-                                &SessionEventType::StoreDeleted(ref data, ref store_id) => {
-                                    dispatch.apply(DataAction::StoreDeleted(
-                                        data.clone(),
-                                        store_id.clone(),
-                                    ));
+                                &SessionEventType::StoreDeleted(ref payload) => {
+                                    dispatch
+                                        .apply(DataAction::StoreDeleted(payload.payload.clone()));
                                 }
-                                &SessionEventType::StoreDeletionFailed(ref data, ref store_id) => {
+                                &SessionEventType::StoreDeletionFailed(ref payload) => {
                                     dispatch.apply(DataAction::StoreDeletionFailed(
-                                        data.clone(),
-                                        store_id.clone(),
+                                        payload.payload.clone(),
                                     ));
                                 }
-                                &SessionEventType::Create => {
+                                &SessionEventType::Create(ref payload) => {
                                     let resource = resource[0].clone();
                                     match resource {
                                         Resource::Account => {
+                                            let account = payload.account.clone();
+                                            if let Some(breach_count) =
+                                                meta.get("breach_count").and_then(|v| v.as_u64())
+                                            {
+                                                dispatch.apply(DataAction::BreachChecked(
+                                                    account.id.clone(),
+                                                    breach_count,
+                                                ));
+                                            }
                                             dispatch.apply(DataAction::ResourceCreated(
-                                                resource,
-                                                data.clone().into(),
+                                                resource, account,
                                             ));
                                         }
                                         _ => {}
                                     }
                                 }
-                                &SessionEventType::Update => {
+                                &SessionEventType::Update(ref payload) => {
                                     let resource = resource[0].clone();
                                     match resource {
                                         Resource::Account => {
+                                            let account = payload.account.clone();
+                                            if let Some(breach_count) =
+                                                meta.get("breach_count").and_then(|v| v.as_u64())
+                                            {
+                                                dispatch.apply(DataAction::BreachChecked(
+                                                    account.id.clone(),
+                                                    breach_count,
+                                                ));
+                                            }
                                             dispatch.apply(DataAction::ResourceEdited(
-                                                resource,
-                                                data.clone().into(),
-                                                meta.get("id")
-                                                    .unwrap()
-                                                    .as_str()
-                                                    .unwrap()
-                                                    .to_string(),
+                                                resource, account,
                                             ));
                                         }
                                         _ => {}
                                     }
                                 }
-                                &SessionEventType::CreationFailed => {
+                                &SessionEventType::CreationFailed(ref payload) => {
                                     let resource = resource[0].clone();
                                     match resource {
                                         Resource::Account => {
                                             dispatch.apply(DataAction::ResourceCreationFailed(
                                                 resource,
-                                                event_request,
+                                                payload.payload.clone(),
                                             ));
                                         }
                                         _ => {}
                                     }
                                 }
-                                &SessionEventType::StoreCreated(ref data, ref store_id) => {
-                                    dispatch.apply(DataAction::StoreCreated(
-                                        data.clone(),
-                                        store_id.clone(),
-                                    ));
+                                &SessionEventType::UpdateFailed(ref payload) => {
+                                    let resource = resource[0].clone();
+                                    match resource {
+                                        Resource::Account => {
+                                            dispatch.apply(DataAction::ResourceEditionFailed(
+                                                resource,
+                                                payload.payload.clone(),
+                                            ));
+                                        }
+                                        _ => {}
+                                    }
+                                }
+                                &SessionEventType::StoreCreated(ref payload) => {
+                                    dispatch
+                                        .apply(DataAction::StoreCreated(payload.payload.clone()));
                                 }
-                                &SessionEventType::StoreCreationFailed(ref data, ref store_id) => {
+                                &SessionEventType::StoreCreationFailed(ref payload) => {
                                     dispatch.apply(DataAction::StoreCreationFailed(
-                                        data.clone(),
-                                        store_id.clone(),
+                                        payload.payload.clone(),
                                     ));
                                 }
-                                &SessionEventType::Refreshed => match resource[0] {
+                                &SessionEventType::Refreshed(ref payload) => match resource[0] {
                                     Resource::Account => {
                                         match &dispatch.get().data.storage_status {
                                             _ => {
                                                 dispatch.apply(DataAction::ResourceFetched(
                                                     Resource::Account,
-                                                    data.clone().into(),
-                                                    None,
+                                                    payload.payload.clone(),
+                                                    Some(meta.clone()),
                                                 ));
                                             }
                                         }
                                     }
                                     _ => {}
                                 },
-                                &SessionEventType::Init(ref data) => {
+                                &SessionEventType::Init(ref payload) => {
                                     let store = dispatch.get();
-                                    dispatch.apply(DataAction::Init(data.clone()));
+                                    dispatch.apply(DataAction::Init(payload.payload.clone()));
                                     if let Some(store_id) = store.persistent_data.store_id.as_ref()
                                     {
                                         if store.persistent_data.store_activated {
@@ -95,7 +95,16 @@ pub async fn run_app() -> Result<(), JsValue> {
         .borrow()
         .post_message(<JsValue as JsValueSerdeExt>::from_serde(&init_request).unwrap());
 
-    yew::Renderer::<app::App>::new().render();
+    //the options page runs this same bundle, loaded from options.html
+    let pathname = gloo_utils::window()
+        .location()
+        .pathname()
+        .unwrap_or_default();
+    if pathname.ends_with("options.html") {
+        yew::Renderer::<pages::OptionsPage>::new().render();
+    } else {
+        yew::Renderer::<app::App>::new().render();
+    }
     Ok(())
 }
 
@@ -1,6 +1,10 @@
 pub mod account_page;
 pub mod home_page;
 pub mod login_page;
+pub mod options_page;
+pub mod security_dashboard_page;
 pub use account_page::*;
 pub use home_page::*;
 pub use login_page::*;
+pub use options_page::*;
+pub use security_dashboard_page::*;
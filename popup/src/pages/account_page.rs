@@ -1,5 +1,8 @@
+use crate::pages::SecurityDashboardPage;
 use crate::store::{DataAction, LoginAction, LoginStatus, PopupStore, StoreDataStatus};
 use crate::{components::*, BoolState, BoolStateAction};
+use browser_rpass::js_binding::extension_api::chrome;
+use browser_rpass::payload::StorePayload;
 use browser_rpass::types::Account;
 #[allow(unused_imports)]
 use log::*;
@@ -32,11 +35,15 @@ pub fn account_page(props: &Props) -> Html {
                     let mut result: BTreeMap<isize, Vec<Rc<Account>>> = BTreeMap::new();
                     let mut non_matched: Vec<Rc<Account>> = vec![];
                     accounts.borrow().iter().cloned().for_each(|account| {
-                        let domain = account.domain.as_ref();
                         let path = path.clone().unwrap_or("".to_owned());
-                        let m_res = best_match(&path, domain.unwrap_or(&String::new()));
-                        if let Some(m_res) = m_res {
-                            let score = m_res.score();
+                        //the best of the account's urls decides its place
+                        let score = account
+                            .all_urls()
+                            .iter()
+                            .filter_map(|account_url| best_match(&path, &account_url.url))
+                            .map(|m_res| m_res.score())
+                            .max();
+                        if let Some(score) = score {
                             result
                                 .entry(score)
                                 .and_modify(|ls| ls.push(account.clone()))
@@ -151,7 +158,7 @@ pub fn account_page(props: &Props) -> Html {
             }
         })
     };
-    let table_headers = ["username", "password", "note", "", ""];
+    let table_headers = ["username", "password", "note", "", "", ""];
     if *login_status == LoginStatus::LoginSuccess {
         store_dispatch.apply(LoginAction::LoggedIn);
         store_switcher_visible.dispatch(BoolStateAction::SetAction(false));
@@ -188,6 +195,42 @@ pub fn account_page(props: &Props) -> Html {
             }
         })
     };
+    let show_security_dashboard = use_reducer(|| BoolState::new(false));
+    let on_security_dashboard = Callback::from({
+        let show_security_dashboard = show_security_dashboard.clone();
+        move |event: MouseEvent| {
+            event.prevent_default();
+            show_security_dashboard.dispatch(BoolStateAction::ToggleAction);
+        }
+    });
+    let close_security_dashboard = {
+        let show_security_dashboard = show_security_dashboard.clone();
+        Callback::from({
+            move |_: MouseEvent| {
+                show_security_dashboard.dispatch(BoolStateAction::SetAction(false));
+            }
+        })
+    };
+    let show_equivalent_domains = use_reducer(|| BoolState::new(false));
+    let on_equivalent_domains = Callback::from({
+        let show_equivalent_domains = show_equivalent_domains.clone();
+        move |event: MouseEvent| {
+            event.prevent_default();
+            show_equivalent_domains.dispatch(BoolStateAction::ToggleAction);
+        }
+    });
+    let close_equivalent_domains = {
+        let show_equivalent_domains = show_equivalent_domains.clone();
+        Callback::from({
+            move |_: MouseEvent| {
+                show_equivalent_domains.dispatch(BoolStateAction::SetAction(false));
+            }
+        })
+    };
+    let on_options = Callback::from(|event: MouseEvent| {
+        event.prevent_default();
+        let _ = chrome.runtime().open_options_page();
+    });
     html! {
         <>
             <div class="relative overflow-hidden shadow-md sm:rounded-lg w-full h-full">
@@ -199,7 +242,20 @@ pub fn account_page(props: &Props) -> Html {
             </button>
             <button type="button" class="my-4 mx-2 accent-btn" onclick={on_create_store}>{"create store"}</button>
             <button type="button" class="my-4 mx-2 warning-btn" onclick={on_delete_store}>{"delete store"}</button>
+            <button type="button" class="my-4 mx-2 primary-btn" onclick={on_security_dashboard}>{"security"}</button>
+            <button type="button" class="my-4 mx-2 primary-btn" onclick={on_equivalent_domains}>{"domains"}</button>
+            <button type="button" class="my-4 mx-2 primary-btn" onclick={on_options}>{"options"}</button>
                 </div>
+                        if (*show_equivalent_domains).into(){
+                            <div class="fullscreen-container">
+                                <EquivalentDomainsPopup handle_close={close_equivalent_domains}/>
+                            </div>
+                        }
+                        if (*show_security_dashboard).into(){
+                            <div class="fullscreen-container">
+                                <SecurityDashboardPage store_id={props.store_id.clone()} handle_close={close_security_dashboard}/>
+                            </div>
+                        }
                         if (*show_create_store_popup).into(){
                             <div class="fullscreen-container">
                                 <CreateStorePopup handle_close={close_create_store_popup}/>
@@ -211,16 +267,16 @@ pub fn account_page(props: &Props) -> Html {
                             </div>
                         }
                         if show_create_store_popup.value == false && show_delete_store_popup.value==false {
-                            if let StoreDataStatus::StoreCreationFailed(_,ref store_id)=*store_status{
+                            if let StoreDataStatus::StoreCreationFailed(StorePayload{ref store_id,..})=*store_status{
                                 <Toast toast_type={ToastType::Error} on_close_button_clicked={close_toast.clone()} text={format!("Failed to create store: {store_id}")} class="absolute right-0 top-5 z-10"/>
                             }
-                            if let StoreDataStatus::StoreCreated(_,ref store_id)=*store_status{
+                            if let StoreDataStatus::StoreCreated(StorePayload{ref store_id,..})=*store_status{
                                 <Toast toast_type={ToastType::Success} on_close_button_clicked={close_toast.clone()} text={format!("Successfully created store: {store_id}")} class="absolute right-0 top-5 z-10"/>
                             }
-                            if let StoreDataStatus::StoreDeletionFailed(_,ref store_id)=*store_status{
+                            if let StoreDataStatus::StoreDeletionFailed(StorePayload{ref store_id,..})=*store_status{
                                 <Toast toast_type={ToastType::Error} on_close_button_clicked={close_toast.clone()} text={format!("Failed to delete store: {store_id}")} class="absolute right-0 top-5 z-10"/>
                             }
-                            if let StoreDataStatus::StoreDeleted(_,ref store_id)=*store_status{
+                            if let StoreDataStatus::StoreDeleted(StorePayload{ref store_id,..})=*store_status{
                                 <Toast toast_type={ToastType::Success} on_close_button_clicked={close_toast.clone()} text={format!("Successfully deleted store: {store_id}")} class="absolute right-0 top-5 z-10"/>
                             }
                         }
@@ -1,11 +1,12 @@
 use crate::{
-    api::extension_api::logout,
+    api::extension_api::{fetch_accounts, login, logout},
     pages::*,
     store::{DataAction, LoginStatus, PopupStore},
     store::{PopupAction, StoreDataStatus},
     BoolState, BoolStateAction,
 };
 use browser_rpass::js_binding::extension_api::chrome;
+use browser_rpass::request::NativeConnectionState;
 use gloo_utils::{format::JsValueSerdeExt, window};
 use log::*;
 use wasm_bindgen::JsValue;
@@ -32,12 +33,12 @@ pub fn home_page(_props: &Props) -> Html {
     let path = use_selector(|state: &PopupStore| state.path.clone());
     let store_id = use_selector(|state: &PopupStore| state.persistent_data.store_id.clone());
     let store_ids = use_selector(|state: &PopupStore| state.store_ids.clone());
+    //other unlocked stores stay unlocked
     let on_logout_click = Callback::from({
+        let store_id = store_id.clone();
         move |event: MouseEvent| {
             event.prevent_default();
-            // TODO logout for specific store only (currently logout is done for all stores)
-            // logout((*store_id).clone())
-            logout(None)
+            logout((*store_id).clone())
         }
     });
     let on_close = Callback::from(move |event: MouseEvent| {
@@ -50,6 +51,7 @@ pub fn home_page(_props: &Props) -> Html {
     });
     let show_create_store_popup = use_reducer(|| BoolState::new(false));
     let store_status = use_selector(|state: &PopupStore| state.data_status.clone());
+    let native_connection = use_selector(|state: &PopupStore| state.native_connection.clone());
     let close_create_store_popup = {
         let show_create_store_popup = show_create_store_popup.clone();
         Callback::from({
@@ -64,6 +66,30 @@ pub fn home_page(_props: &Props) -> Html {
             on_close.clone().emit(MouseEvent::new("click").unwrap());
         })
     };
+    //only fetching and logging in can be sent again as they were
+    let on_retry = Callback::from(move |event: MouseEvent| {
+        event.prevent_default();
+        let dispatch = Dispatch::<PopupStore>::new();
+        let state = dispatch.get();
+        if let StoreDataStatus::TimedOut(timeout) = &state.data_status {
+            let store_id = timeout.store_id.clone();
+            dispatch.apply(DataAction::TimeoutDismissed);
+            match (timeout.request_type.as_str(), store_id) {
+                ("fetch", store_id) => {
+                    fetch_accounts(store_id, None);
+                }
+                ("login", Some(store_id)) => {
+                    let is_default = state.default_store_id.as_ref() == Some(&store_id);
+                    login(store_id, is_default);
+                }
+                _ => {}
+            }
+        }
+    });
+    let on_timeout_dismissed = Callback::from(move |event: MouseEvent| {
+        event.prevent_default();
+        Dispatch::<PopupStore>::new().apply(DataAction::TimeoutDismissed);
+    });
     let create_store_clicked = {
         let show_create_store_popup = show_create_store_popup.clone();
         Callback::from(move |_: MouseEvent| {
@@ -102,6 +128,27 @@ pub fn home_page(_props: &Props) -> Html {
                                     </div>
                                 </div>
                             }
+                            if let StoreDataStatus::TimedOut(timeout) = &*store_status {
+                                <div class="absolute mb-4 z-50 critical-error center-position"
+                                    role="alert">
+                                    <ErrorIcon/>
+                                    <span class="sr-only">{"error"}</span>
+                                    <div>
+                                        <span class="font-medium">{"Timed out!"}</span>
+                                        {format!(" The native app did not answer the {} request in {}s", timeout.request_type.replace('_', " "), timeout.timeout_ms / 1000)}
+                                    </div>
+                                    if timeout.request_type == "fetch" || timeout.request_type == "login" {
+                                        <button class="primary-btn" onclick={on_retry}>{"Retry"}</button>
+                                    }
+                                    <CloseButton onclick={on_timeout_dismissed}/>
+                                </div>
+                            }
+                            if let NativeConnectionState::Reconnecting { attempt, retry_in_ms } = *native_connection {
+                                <div class="absolute mb-4 z-50 center-position" role="status">
+                                    <LoadingIndicator class={"mr-2 "}/>
+                                    <span>{format!("Reconnecting to the native app (attempt {}, next try in {}s)", attempt, (retry_in_ms + 999) / 1000)}</span>
+                                </div>
+                            }
                             <div style={
                                     "height:90%;".to_owned()+
                                     {
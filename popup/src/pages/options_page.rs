@@ -0,0 +1,193 @@
+use crate::store::PopupStore;
+use browser_rpass::clipboard::{load_clipboard_clear_seconds, set_clipboard_clear_seconds};
+use browser_rpass::store_routing::{load_store_routes, set_store_routes, StoreRoute};
+#[allow(unused_imports)]
+use log::*;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+use yewdux::prelude::use_selector;
+
+//stores of a rule are typed separated by commas or spaces
+fn parse_store_ids(text: &str) -> Vec<String> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .map(|store_id| store_id.trim().to_owned())
+        .filter(|store_id| !store_id.is_empty())
+        .collect()
+}
+
+//rules without a domain are dropped, and a rule per domain is kept
+fn clean_routes(routes: &[StoreRoute]) -> Vec<StoreRoute> {
+    let mut cleaned: Vec<StoreRoute> = Vec::new();
+    for route in routes {
+        let domain = route.domain.trim().trim_end_matches('.').to_lowercase();
+        if domain.is_empty() || cleaned.iter().any(|kept| kept.domain == domain) {
+            continue;
+        }
+        cleaned.push(StoreRoute {
+            domain,
+            ..route.clone()
+        });
+    }
+    cleaned
+}
+
+#[function_component(OptionsPage)]
+pub fn options_page() -> Html {
+    let store_ids = use_selector(|state: &PopupStore| state.store_ids.clone());
+    let routes = use_state(Vec::<StoreRoute>::new);
+    let clipboard_clear_seconds = use_state(|| None::<u32>);
+    let saved = use_state(|| false);
+    use_effect_with((), {
+        let routes = routes.clone();
+        let clipboard_clear_seconds = clipboard_clear_seconds.clone();
+        move |_| {
+            wasm_bindgen_futures::spawn_local(async move {
+                routes.set(load_store_routes().await);
+                clipboard_clear_seconds.set(Some(load_clipboard_clear_seconds().await));
+            });
+        }
+    });
+    let update_route = {
+        let routes = routes.clone();
+        let saved = saved.clone();
+        move |index: usize, update: Box<dyn Fn(&mut StoreRoute)>| {
+            let mut updated = (*routes).clone();
+            if let Some(route) = updated.get_mut(index) {
+                update(route);
+            }
+            routes.set(updated);
+            saved.set(false);
+        }
+    };
+    let on_add = {
+        let routes = routes.clone();
+        Callback::from(move |_: MouseEvent| {
+            let mut updated = (*routes).clone();
+            updated.push(StoreRoute::default());
+            routes.set(updated);
+        })
+    };
+    let on_clipboard_clear_seconds_input = {
+        let clipboard_clear_seconds = clipboard_clear_seconds.clone();
+        let saved = saved.clone();
+        Callback::from(move |event: InputEvent| {
+            let value = event.target_unchecked_into::<HtmlInputElement>().value();
+            //an emptied field leaves copies on the clipboard
+            clipboard_clear_seconds.set(Some(value.trim().parse::<u32>().unwrap_or(0)));
+            saved.set(false);
+        })
+    };
+    let on_save = {
+        let routes = routes.clone();
+        let clipboard_clear_seconds = clipboard_clear_seconds.clone();
+        let saved = saved.clone();
+        Callback::from(move |event: SubmitEvent| {
+            event.prevent_default();
+            let cleaned = clean_routes(&routes);
+            let seconds = *clipboard_clear_seconds;
+            let routes = routes.clone();
+            let saved = saved.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                set_store_routes(&cleaned).await;
+                if let Some(seconds) = seconds {
+                    set_clipboard_clear_seconds(seconds).await;
+                }
+                routes.set(cleaned);
+                saved.set(true);
+            });
+        })
+    };
+    html! {
+        <div class="p-4 md:p-5 bg-white dark:bg-gray-900 min-h-screen">
+            <h3 class="text-lg font-semibold text-gray-900 dark:text-white mb-2">{"Stores per site"}</h3>
+            <p class="text-sm text-gray-600 dark:text-gray-400 mb-4">
+                {"Accounts of the listed stores are suggested first on the domain and its subdomains, in the order given. New logins of the domain are saved to the chosen store, if it is unlocked."}
+            </p>
+            <datalist id="store-ids">
+            {
+                store_ids.iter().map(|store_id| html!(<option value={store_id.clone()}/>)).collect::<Html>()
+            }
+            </datalist>
+            <form onsubmit={on_save} autocomplete="off">
+            {
+                routes.iter().enumerate().map(|(index, route)| {
+                    let on_domain_input = {
+                        let update_route = update_route.clone();
+                        Callback::from(move |event: InputEvent| {
+                            let domain = event.target_unchecked_into::<HtmlInputElement>().value();
+                            update_route(index, Box::new(move |route| route.domain = domain.clone()));
+                        })
+                    };
+                    let on_stores_change = {
+                        let update_route = update_route.clone();
+                        Callback::from(move |event: Event| {
+                            let store_ids = parse_store_ids(&event.target_unchecked_into::<HtmlInputElement>().value());
+                            update_route(index, Box::new(move |route| route.store_ids = store_ids.clone()));
+                        })
+                    };
+                    let on_save_store_change = {
+                        let update_route = update_route.clone();
+                        Callback::from(move |event: Event| {
+                            let value = event.target_unchecked_into::<HtmlSelectElement>().value();
+                            let save_store_id = Some(value).filter(|value| !value.is_empty());
+                            update_route(index, Box::new(move |route| route.save_store_id = save_store_id.clone()));
+                        })
+                    };
+                    let on_remove = {
+                        let routes = routes.clone();
+                        let saved = saved.clone();
+                        Callback::from(move |_: MouseEvent| {
+                            let mut updated = (*routes).clone();
+                            updated.remove(index);
+                            routes.set(updated);
+                            saved.set(false);
+                        })
+                    };
+                    html! {
+                        <div class="flex items-end gap-2 mb-3" key={index}>
+                            <div>
+                                <label class="form-label">{"Domain"}</label>
+                                <input type="text" class="form-input" placeholder="example.com" value={route.domain.clone()} oninput={on_domain_input}/>
+                            </div>
+                            <div>
+                                <label class="form-label">{"Search first"}</label>
+                                <input type="text" class="form-input" list="store-ids" placeholder="work, personal" value={route.store_ids.join(", ")} onchange={on_stores_change}/>
+                            </div>
+                            <div>
+                                <label class="form-label">{"Save to"}</label>
+                                <select class="form-input" onchange={on_save_store_change}>
+                                    <option value="" selected={route.save_store_id.is_none()}>{"default store"}</option>
+                                    {
+                                        store_ids.iter().map(|store_id| {
+                                            let selected = route.save_store_id.as_ref() == Some(store_id);
+                                            html!(<option value={store_id.clone()} selected={selected}>{store_id.clone()}</option>)
+                                        }).collect::<Html>()
+                                    }
+                                </select>
+                            </div>
+                            <button type="button" class="warning-btn" onclick={on_remove}>{"remove"}</button>
+                        </div>
+                    }
+                }).collect::<Html>()
+            }
+                <div class="mb-6">
+                    <button type="button" class="primary-btn" onclick={on_add}>{"add rule"}</button>
+                </div>
+                <h3 class="text-lg font-semibold text-gray-900 dark:text-white mb-2">{"Clipboard"}</h3>
+                <p class="text-sm text-gray-600 dark:text-gray-400 mb-4">
+                    {"Copied usernames, passwords and one-time codes are cleared from the clipboard after this many seconds, unless something else was copied since. 0 keeps them."}
+                </p>
+                <div class="mb-6">
+                    <label class="form-label">{"Clear after (seconds)"}</label>
+                    <input type="number" min="0" class="form-input" value={clipboard_clear_seconds.map(|seconds| seconds.to_string()).unwrap_or_default()} oninput={on_clipboard_clear_seconds_input}/>
+                </div>
+                <div class="flex items-center gap-2">
+                    <button type="submit" class="accent-btn">{"Save"}</button>
+                    if *saved {
+                        <span class="text-sm text-gray-600 dark:text-gray-400">{"Saved"}</span>
+                    }
+                </div>
+            </form>
+        </div>
+    }
+}
@@ -0,0 +1,128 @@
+use crate::{
+    api::extension_api::audit_store,
+    components::*,
+    store::{AuditState, PopupStore},
+};
+use browser_rpass::audit::{AuditEntry, AuditFinding, BreachCheck};
+#[allow(unused_imports)]
+use log::*;
+use yew;
+use yew::prelude::*;
+use yewdux::functional::use_selector;
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub store_id: String,
+    pub handle_close: Callback<MouseEvent>,
+}
+
+fn describe_finding(finding: &AuditFinding) -> String {
+    match finding {
+        AuditFinding::ReusedPassword { shared_with } => {
+            format!("password reused by {}", shared_with.join(", "))
+        }
+        AuditFinding::BreachedPassword { occurrences } => {
+            format!(
+                "password found {} times in breached password lists",
+                occurrences
+            )
+        }
+        AuditFinding::WeakPassword { score } => format!("weak password (score {}/4)", score),
+        AuditFinding::MissingPassword => "no password".to_owned(),
+        AuditFinding::OldPassword { age_days } => {
+            format!("not updated for {} days", age_days)
+        }
+        AuditFinding::InsecureDomain => "domain or url saved as plain http://".to_owned(),
+        AuditFinding::MissingUsername => "no username".to_owned(),
+        AuditFinding::MissingDomain => "no domain".to_owned(),
+    }
+}
+
+#[function_component(SecurityDashboardPage)]
+pub fn security_dashboard_page(props: &Props) -> Html {
+    let audit = use_selector(|state: &PopupStore| state.audit.clone());
+    let run_audit = {
+        let store_id = props.store_id.clone();
+        Callback::from(move |_: MouseEvent| {
+            audit_store(Some(store_id.clone()), None);
+        })
+    };
+    use_effect_with(props.store_id.clone(), move |store_id: &String| {
+        audit_store(Some(store_id.clone()), None);
+    });
+    let entry_row = |entry: &AuditEntry| -> Html {
+        html! {
+            <tr class="border-b dark:border-gray-600">
+                <th scope="row" class="font-medium text-gray-900 dark:text-white">
+                    <div class="text-gray-500">{entry.domain.clone().unwrap_or_default()}</div>
+                    <div>{entry.username.clone()}</div>
+                </th>
+                <td>
+                    <ul>
+                    { entry.findings.iter().map(|finding| html! {
+                        <li>{describe_finding(finding)}</li>
+                    }).collect::<Html>() }
+                    </ul>
+                </td>
+            </tr>
+        }
+    };
+    let content = match &*audit {
+        AuditState::Idle | AuditState::Running => html! {
+            <div class="flex items-center justify-center h-48">
+                <LoadingIndicator class={"mr-2 "}/>
+            </div>
+        },
+        AuditState::Failed(message) => html! {
+            <div class="critical-error" role="alert">
+                <ErrorIcon/>
+                <span class="font-medium">{"Audit failed: "}</span>{message.clone()}
+            </div>
+        },
+        AuditState::Completed(report) => html! {
+            <>
+                <div class="grid grid-cols-3 gap-2 my-2">
+                    <div class="rounded-lg bg-gray-100 dark:bg-gray-800 p-2">
+                        <div class="text-xs text-gray-500">{"Entries audited"}</div>
+                        <div class="text-lg font-semibold dark:text-white">{report.total_entries}</div>
+                    </div>
+                    { report.summary().into_iter().map(|(category, count)| html! {
+                        <div class="rounded-lg bg-gray-100 dark:bg-gray-800 p-2">
+                            <div class="text-xs text-gray-500">{category}</div>
+                            <div class="text-lg font-semibold text-red-600 dark:text-red-400">{count}</div>
+                        </div>
+                    }).collect::<Html>() }
+                </div>
+                if let BreachCheck::Unavailable { reason } = &report.breach_check {
+                    <div class="text-sm text-yellow-600 dark:text-yellow-400 mb-2">
+                        {format!("Breached passwords could not be checked: {}", reason)}
+                    </div>
+                }
+                if report.entries.is_empty() {
+                    <div class="dark:text-white">{"No issues found."}</div>
+                } else {
+                    <div class="h-72 overflow-y-auto">
+                        <table class="dark:text-gray-400 text-gray-500 text-left text-sm w-full">
+                            <tbody>
+                                { report.entries.iter().map(|entry| entry_row(entry)).collect::<Html>() }
+                            </tbody>
+                        </table>
+                    </div>
+                }
+            </>
+        },
+    };
+    html! {
+        <div class="relative w-full h-full p-2">
+            <div class="flex items-center justify-between border-b dark:border-gray-600 pb-2">
+                <h3 class="text-lg font-semibold text-gray-900 dark:text-white">{"Security dashboard"}</h3>
+                <button type="button" class="primary-btn me-12" onclick={run_audit}
+                    disabled={*audit == AuditState::Running}>
+                    {"Run again"}
+                </button>
+                <CloseButton onclick={&props.handle_close}/>
+            </div>
+            {content}
+        </div>
+    }
+}
@@ -1,6 +1,12 @@
+use browser_rpass::audit::AuditReport;
 use browser_rpass::js_binding::extension_api::*;
+use browser_rpass::password_generator::{PasswordPolicy, PolicyViolation};
+use browser_rpass::payload::{
+    FailurePayload, FetchPayload, GeneratedPasswordPayload, InitPayload, LoginPayload, StorePayload,
+};
 use browser_rpass::request::DataFieldType;
-use browser_rpass::request::SessionEvent;
+use browser_rpass::request::NativeConnectionState;
+use browser_rpass::request::RequestTimeout;
 use browser_rpass::types::*;
 use gloo::storage::errors::StorageError;
 use gloo_utils::document;
@@ -68,11 +74,11 @@ pub enum StoreDataStatus {
     Loading,
     CreationStarted,
     StoreCreationStarted(Option<RequestEnum>, String),
-    StoreCreationFailed(HashMap<DataFieldType, Value>, String),
-    StoreCreated(HashMap<DataFieldType, Value>, String),
+    StoreCreationFailed(StorePayload),
+    StoreCreated(StorePayload),
     StoreDeletionStarted(Option<RequestEnum>, String),
-    StoreDeletionFailed(HashMap<DataFieldType, Value>, String),
-    StoreDeleted(HashMap<DataFieldType, Value>, String),
+    StoreDeletionFailed(StorePayload),
+    StoreDeleted(StorePayload),
     CreationSuccess,
     CreationFailed,
     DeletionStarted,
@@ -87,6 +93,7 @@ pub enum StoreDataStatus {
     Error,
     NativeAppConnectionError,
     InitError(String),
+    TimedOut(RequestTimeout),
 }
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
@@ -110,6 +117,10 @@ pub enum LoginStatus {
 pub struct StoreData {
     pub accounts: Mrc<Vec<Rc<Account>>>,
     pub storage_status: StorageStatus,
+    pub password_policy: Option<PasswordPolicy>,
+    pub password_policy_error: Option<String>,
+    //prefix the native app saves custom fields under
+    pub custom_field_prefix: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
@@ -129,23 +140,51 @@ pub struct PopupStore {
     pub login_status: LoginStatus,
     pub data: StoreData,
     pub store_ids: Vec<String>,
+    //stores the service worker holds unlocked, which may be more than the one shown
+    pub unlocked_store_ids: Vec<String>,
     pub keys: Vec<Key>,
     pub path: Option<String>,
     pub window_id: Option<String>,
     pub default_store_id: Option<String>,
+    pub generated_password: Option<String>,
+    pub policy_violations: Vec<PolicyViolation>,
+    //current one-time codes keyed by account id
+    pub one_time_codes: HashMap<String, OneTimeCode>,
+    pub audit: AuditState,
+    //how many times the password of an account was found in the native app's breach list,
+    //as reported when the account was last created or edited
+    pub breach_counts: HashMap<String, u64>,
+    pub native_connection: NativeConnectionState,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub enum AuditState {
+    #[default]
+    Idle,
+    Running,
+    Completed(AuditReport),
+    Failed(String),
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct OneTimeCode {
+    pub code: String,
+    pub period: u64,
+    //milliseconds since epoch, comparable with js_sys::Date::now()
+    pub expires_at: f64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum LoginAction {
     LoginStarted(String, HashMap<DataFieldType, Value>),
     LoginError(HashMap<DataFieldType, Value>, String),
-    LoginSucceeded(HashMap<DataFieldType, Value>),
+    LoginSucceeded(LoginPayload),
     LoginFailed(HashMap<DataFieldType, Value>),
     LogoutSucceeded(HashMap<DataFieldType, Value>),
     LogoutFailed(HashMap<DataFieldType, Value>),
     LogoutStarted(HashMap<DataFieldType, Value>),
-    Logout(Option<String>, HashMap<DataFieldType, Value>),
-    Login(String, HashMap<DataFieldType, Value>),
+    Logout(Option<String>),
+    Login(LoginPayload),
     LoggedIn,
     LogoutIdle,
     RememberMe(bool),
@@ -153,26 +192,38 @@ pub enum LoginAction {
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum DataAction {
-    StoreCreated(HashMap<DataFieldType, Value>, String),
-    StoreCreationFailed(HashMap<DataFieldType, Value>, String),
+    StoreCreated(StorePayload),
+    StoreCreationFailed(StorePayload),
     StoreCreationStarted(Option<RequestEnum>, String),
-    StoreDeleted(HashMap<DataFieldType, Value>, String),
-    StoreDeletionFailed(HashMap<DataFieldType, Value>, String),
+    StoreDeleted(StorePayload),
+    StoreDeletionFailed(StorePayload),
     StoreDeletionStarted(Option<RequestEnum>, String),
     StoreIdSet(String),
     ResourceFetchStarted(Resource),
-    Init(HashMap<DataFieldType, Value>),
+    Init(InitPayload),
     NativeAppConnectionError,
-    ResourceDeleted(Resource, HashMap<DataFieldType, Value>),
-    ResourceCreated(Resource, HashMap<DataFieldType, Value>),
-    ResourceEdited(Resource, HashMap<DataFieldType, Value>, String),
+    NativeConnectionChanged(NativeConnectionState),
+    RequestTimedOut(RequestTimeout),
+    TimeoutDismissed,
+    ResourceDeleted(Resource, String),
+    ResourceCreated(Resource, Account),
+    ResourceEdited(Resource, Account),
     ResourceDeletionFailed(Resource, HashMap<DataFieldType, Value>),
-    ResourceEditionFailed(Resource, SessionEvent),
-    ResourceCreationFailed(Resource, SessionEvent),
+    ResourceEditionFailed(Resource, FailurePayload),
+    ResourceCreationFailed(Resource, FailurePayload),
     ResourceDeletionStarted(Resource, HashMap<DataFieldType, Value>),
     ResourceEditionStarted(Resource, HashMap<DataFieldType, Value>),
     ResourceCreationStarted(Resource, HashMap<DataFieldType, Value>),
-    ResourceFetched(Resource, HashMap<DataFieldType, Value>, Option<Value>),
+    ResourceFetched(Resource, FetchPayload, Option<Value>),
+    PasswordGenerated(GeneratedPasswordPayload),
+    PasswordGenerationFailed(FailurePayload),
+    GeneratedPasswordCleared,
+    OneTimeCodeReceived(String, HashMap<DataFieldType, Value>),
+    OneTimeCodeFailed(String, HashMap<DataFieldType, Value>),
+    AuditStarted,
+    AuditCompleted(AuditReport),
+    AuditFailed(String),
+    BreachChecked(String, u64),
     Idle,
 }
 
@@ -247,12 +298,11 @@ impl Reducer<PopupStore> for DataAction {
                 }
             }
             .into(),
-            DataAction::ResourceFetched(resource, mut data, _meta) => match resource {
+            DataAction::ResourceFetched(resource, payload, meta) => match resource {
                 Resource::Account => {
                     let state_data = state.data.clone();
-                    let accounts = data.remove(&DataFieldType::Data).unwrap_or_default();
-                    let accounts = serde_json::from_value::<Vec<Account>>(accounts)
-                        .unwrap_or_default()
+                    let accounts = payload
+                        .accounts
                         .into_iter()
                         .map(|v| Rc::new(v))
                         .collect::<Vec<Rc<Account>>>();
@@ -261,14 +311,17 @@ impl Reducer<PopupStore> for DataAction {
                         data: StoreData {
                             storage_status: StorageStatus::Loaded,
                             accounts: Mrc::new(accounts),
+                            password_policy: payload.password_policy,
+                            password_policy_error: payload.password_policy_error,
+                            custom_field_prefix: meta
+                                .as_ref()
+                                .and_then(|meta| meta.get("custom_field_prefix"))
+                                .and_then(|prefix| prefix.as_str())
+                                .map(|prefix| prefix.to_owned())
+                                .or(state_data.custom_field_prefix.clone()),
                             ..state_data
                         },
                         persistent_data: PersistentStoreData {
-                            store_id: data
-                                .get(&DataFieldType::StoreID)
-                                .map_or(state.persistent_data.store_id.clone(), |v| {
-                                    Some(v.as_str().unwrap().to_string())
-                                }),
                             store_activated: true,
                             ..state.persistent_data.clone()
                         },
@@ -288,10 +341,8 @@ impl Reducer<PopupStore> for DataAction {
                     todo!();
                 }
             },
-            DataAction::ResourceCreated(resource, mut data) => match resource {
+            DataAction::ResourceCreated(resource, account) => match resource {
                 Resource::Account => {
-                    let account = data.remove(&DataFieldType::Data).unwrap_or_default();
-                    let account = serde_json::from_value::<Account>(account).unwrap();
                     let state_data = state.data.clone();
                     let mut accounts = state_data.accounts.borrow_mut();
                     accounts.push(Rc::new(account));
@@ -309,13 +360,11 @@ impl Reducer<PopupStore> for DataAction {
                 }
                 .into(),
             },
-            DataAction::ResourceEdited(resource, mut data, id) => match resource {
+            DataAction::ResourceEdited(resource, account) => match resource {
                 Resource::Account => {
-                    let account = data.remove(&DataFieldType::Data).unwrap_or_default();
-                    let account = serde_json::from_value::<Account>(account).unwrap();
                     let state_data = state.data.clone();
                     let mut accounts = state_data.accounts.borrow_mut();
-                    let idx = accounts.iter().position(|ac| ac.id == id).unwrap();
+                    let idx = accounts.iter().position(|ac| ac.id == account.id).unwrap();
                     accounts[idx] = Rc::new(account);
                     drop(accounts);
                     PopupStore {
@@ -337,13 +386,10 @@ impl Reducer<PopupStore> for DataAction {
                 ..state.deref().clone()
             }
             .into(),
-            DataAction::ResourceDeleted(resource, mut data) => {
+            DataAction::ResourceDeleted(resource, deleted_id) => {
                 let state_data = state.data.clone();
                 match resource {
                     Resource::Account => {
-                        let data = data.remove(&DataFieldType::Data).unwrap_or_default();
-                        let map = data.as_object().unwrap();
-                        let deleted_id = map.get("id").unwrap().as_str().unwrap().to_owned();
                         state_data
                             .accounts
                             .borrow_mut()
@@ -362,48 +408,37 @@ impl Reducer<PopupStore> for DataAction {
                     .into(),
                 }
             }
-            DataAction::Init(data) => {
-                let keys = data.get(&DataFieldType::Keys).map_or(vec![], |v| {
-                    v.as_array()
-                        .unwrap_or(&vec![])
-                        .iter()
-                        .map(|v| serde_json::from_value::<Key>(v.clone()).unwrap())
-                        .collect::<Vec<Key>>()
-                });
+            DataAction::Init(payload) => {
                 PopupStore {
                     persistent_data: PersistentStoreData {
                         ..state.persistent_data.clone()
                     },
                     page_loading: false,
-                    store_ids: data
-                        .get(&DataFieldType::StoreIDList)
-                        .map_or(vec![], |v| {
-                            v.as_array()
-                                .unwrap_or(&vec![])
-                                .iter()
-                                .map(|v| v.as_str().unwrap_or("").to_owned())
-                                .collect::<Vec<String>>()
-                        })
+                    store_ids: payload
+                        .store_ids
                         .into_iter()
                         .filter(|v| !v.is_empty())
                         .collect::<Vec<String>>(),
-                    keys,
+                    keys: payload.keys,
+                    unlocked_store_ids: payload.unlocked_store_ids,
                     ..state.deref().clone()
                 }
             }
             .into(),
-            DataAction::ResourceCreationFailed(_resource, _session_event) => {
+            DataAction::ResourceCreationFailed(_resource, failure) => {
                 PopupStore {
                     page_loading: false,
                     data_status: StoreDataStatus::CreationFailed,
+                    policy_violations: failure.policy_violations,
                     ..state.deref().clone()
                 }
             }
             .into(),
-            DataAction::ResourceEditionFailed(_resource, _session_event) => {
+            DataAction::ResourceEditionFailed(_resource, failure) => {
                 PopupStore {
                     page_loading: false,
                     data_status: StoreDataStatus::EditionFailed,
+                    policy_violations: failure.policy_violations,
                     ..state.deref().clone()
                 }
             }
@@ -417,6 +452,7 @@ impl Reducer<PopupStore> for DataAction {
             DataAction::Idle => PopupStore {
                 page_loading: false,
                 data_status: StoreDataStatus::Idle,
+                policy_violations: vec![],
                 ..state.deref().clone()
             }
             .into(),
@@ -426,20 +462,20 @@ impl Reducer<PopupStore> for DataAction {
                 ..state.deref().clone()
             }
             .into(),
-            DataAction::StoreCreated(data, store_id) => {
+            DataAction::StoreCreated(payload) => {
                 let mut store_ids = state.store_ids.clone();
-                store_ids.push(store_id.clone());
+                store_ids.push(payload.store_id.clone());
                 PopupStore {
                     page_loading: false,
                     store_ids,
-                    data_status: StoreDataStatus::StoreCreated(data, store_id),
+                    data_status: StoreDataStatus::StoreCreated(payload),
                     ..state.deref().clone()
                 }
             }
             .into(),
-            DataAction::StoreCreationFailed(data, acknowledgement) => PopupStore {
+            DataAction::StoreCreationFailed(payload) => PopupStore {
                 page_loading: false,
-                data_status: StoreDataStatus::StoreCreationFailed(data, acknowledgement),
+                data_status: StoreDataStatus::StoreCreationFailed(payload),
                 ..state.deref().clone()
             }
             .into(),
@@ -449,7 +485,8 @@ impl Reducer<PopupStore> for DataAction {
                 ..state.deref().clone()
             }
             .into(),
-            DataAction::StoreDeleted(data, store_id) => {
+            DataAction::StoreDeleted(payload) => {
+                let store_id = payload.store_id.clone();
                 let mut store_ids = state.store_ids.clone();
                 store_ids.retain(|v| v != &store_id);
                 let current_store_id = state.persistent_data.store_id.clone();
@@ -474,14 +511,99 @@ impl Reducer<PopupStore> for DataAction {
                     store_ids,
                     persistent_data,
                     data: store_data,
-                    data_status: StoreDataStatus::StoreDeleted(data, store_id.clone()),
+                    data_status: StoreDataStatus::StoreDeleted(payload),
                     ..state.deref().clone()
                 }
             }
             .into(),
-            DataAction::StoreDeletionFailed(data, acknowledgement) => PopupStore {
+            DataAction::StoreDeletionFailed(payload) => PopupStore {
                 page_loading: false,
-                data_status: StoreDataStatus::StoreDeletionFailed(data, acknowledgement),
+                data_status: StoreDataStatus::StoreDeletionFailed(payload),
+                ..state.deref().clone()
+            }
+            .into(),
+            DataAction::PasswordGenerated(generated) => PopupStore {
+                generated_password: Some(generated.password),
+                ..state.deref().clone()
+            }
+            .into(),
+            DataAction::PasswordGenerationFailed(failure) => {
+                debug!("password generation failed: {:?}", failure);
+                PopupStore {
+                    generated_password: None,
+                    ..state.deref().clone()
+                }
+                .into()
+            }
+            DataAction::OneTimeCodeReceived(id, data) => {
+                let code = data
+                    .get(&DataFieldType::Value)
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .to_owned();
+                let period = data
+                    .get(&DataFieldType::TotpPeriod)
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or_default();
+                let remaining = data
+                    .get(&DataFieldType::TotpRemaining)
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or_default();
+                let mut one_time_codes = state.one_time_codes.clone();
+                one_time_codes.insert(
+                    id,
+                    OneTimeCode {
+                        code,
+                        period,
+                        expires_at: js_sys::Date::now() + (remaining * 1000) as f64,
+                    },
+                );
+                PopupStore {
+                    one_time_codes,
+                    ..state.deref().clone()
+                }
+                .into()
+            }
+            DataAction::OneTimeCodeFailed(id, data) => {
+                debug!("failed to get one-time code for {}: {:?}", id, data);
+                let mut one_time_codes = state.one_time_codes.clone();
+                one_time_codes.remove(&id);
+                PopupStore {
+                    one_time_codes,
+                    ..state.deref().clone()
+                }
+                .into()
+            }
+            DataAction::AuditStarted => PopupStore {
+                audit: AuditState::Running,
+                ..state.deref().clone()
+            }
+            .into(),
+            DataAction::AuditCompleted(report) => PopupStore {
+                audit: AuditState::Completed(report),
+                ..state.deref().clone()
+            }
+            .into(),
+            DataAction::AuditFailed(message) => PopupStore {
+                audit: AuditState::Failed(message),
+                ..state.deref().clone()
+            }
+            .into(),
+            DataAction::BreachChecked(id, breach_count) => {
+                let mut breach_counts = state.breach_counts.clone();
+                if breach_count > 0 {
+                    breach_counts.insert(id, breach_count);
+                } else {
+                    breach_counts.remove(&id);
+                }
+                PopupStore {
+                    breach_counts,
+                    ..state.deref().clone()
+                }
+                .into()
+            }
+            DataAction::GeneratedPasswordCleared => PopupStore {
+                generated_password: None,
                 ..state.deref().clone()
             }
             .into(),
@@ -491,6 +613,55 @@ impl Reducer<PopupStore> for DataAction {
                 ..state.deref().clone()
             }
             .into(),
+            DataAction::RequestTimedOut(timeout) => {
+                let login_store_id = match &state.login_status {
+                    LoginStatus::LoginStarted(store_id) => Some(store_id),
+                    _ => None,
+                };
+                //the event goes to every popup, so leave out requests for other stores
+                let concerned = timeout.store_id.is_none()
+                    || timeout.store_id.as_ref() == state.persistent_data.store_id.as_ref()
+                    || timeout.store_id.as_ref() == login_store_id;
+                if !concerned {
+                    return state;
+                }
+                let login_status = if timeout.request_type == "login" && login_store_id.is_some() {
+                    LoginStatus::LoginFailed
+                } else {
+                    state.login_status.clone()
+                };
+                PopupStore {
+                    page_loading: false,
+                    data_status: StoreDataStatus::TimedOut(timeout),
+                    login_status,
+                    ..state.deref().clone()
+                }
+                .into()
+            }
+            DataAction::TimeoutDismissed => PopupStore {
+                data_status: StoreDataStatus::Idle,
+                ..state.deref().clone()
+            }
+            .into(),
+            DataAction::NativeConnectionChanged(native_connection) => {
+                let data_status = match native_connection {
+                    NativeConnectionState::Disconnected => {
+                        StoreDataStatus::NativeAppConnectionError
+                    }
+                    NativeConnectionState::Connected
+                        if state.data_status == StoreDataStatus::NativeAppConnectionError =>
+                    {
+                        StoreDataStatus::Idle
+                    }
+                    _ => state.data_status.clone(),
+                };
+                PopupStore {
+                    data_status,
+                    native_connection,
+                    ..state.deref().clone()
+                }
+                .into()
+            }
         }
     }
 }
@@ -525,14 +696,10 @@ impl Reducer<PopupStore> for LoginAction {
                 }
             }
             .into(),
-            LoginAction::LoginSucceeded(data) => PopupStore {
+            LoginAction::LoginSucceeded(payload) => PopupStore {
                 page_loading: false,
                 persistent_data: PersistentStoreData {
-                    store_id: data
-                        .get(&DataFieldType::StoreID)
-                        .map_or(store.persistent_data.store_id.clone(), |v| {
-                            Some(v.as_str().unwrap().to_string())
-                        }),
+                    store_id: Some(payload.store_id),
                     store_activated: true,
                     ..store.persistent_data.clone()
                 },
@@ -582,13 +749,24 @@ impl Reducer<PopupStore> for LoginAction {
                 ..store.deref().clone()
             }
             .into(),
-            LoginAction::Logout(store_id, _data) => {
+            LoginAction::Logout(store_id) => {
                 if let Some(store_id) = store_id {
+                    let unlocked_store_ids = store
+                        .unlocked_store_ids
+                        .iter()
+                        .filter(|v| **v != store_id)
+                        .cloned()
+                        .collect::<Vec<String>>();
                     let current_store_id = store.persistent_data.store_id.as_ref();
                     if current_store_id.is_some_and(|v| *v != store_id) {
-                        store
+                        PopupStore {
+                            unlocked_store_ids,
+                            ..store.deref().clone()
+                        }
+                        .into()
                     } else {
                         PopupStore {
+                            unlocked_store_ids,
                             page_loading: false,
                             persistent_data: PersistentStoreData {
                                 store_id: if store.persistent_data.remember_me {
@@ -621,18 +799,28 @@ impl Reducer<PopupStore> for LoginAction {
                             ..store.persistent_data
                         },
                         login_status: LoginStatus::LoggedOut,
+                        unlocked_store_ids: Vec::new(),
                         data: StoreData {
                             accounts: Mrc::new(vec![]),
                             storage_status: StorageStatus::Uninitialized,
+                            password_policy: None,
+                            password_policy_error: None,
+                            custom_field_prefix: None,
                         },
                         ..store.deref().clone()
                     }
                     .into()
                 }
             }
-            LoginAction::Login(store_id, data) => {
+            LoginAction::Login(payload) => {
+                let store_id = payload.store_id;
+                let mut unlocked_store_ids = store.unlocked_store_ids.clone();
+                if !unlocked_store_ids.contains(&store_id) {
+                    unlocked_store_ids.push(store_id.clone());
+                }
                 PopupStore {
                     page_loading: false,
+                    unlocked_store_ids,
                     persistent_data: PersistentStoreData {
                         store_activated: {
                             if let LoginStatus::LoginStarted(current_store_id) =
@@ -646,10 +834,7 @@ impl Reducer<PopupStore> for LoginAction {
                         ..store.persistent_data.clone()
                     },
                     default_store_id: {
-                        if data
-                            .get(&DataFieldType::IsDefault)
-                            .map_or(false, |v| v.as_bool().unwrap_or(false))
-                        {
+                        if payload.is_default {
                             Some(store_id)
                         } else {
                             store.default_store_id.clone()
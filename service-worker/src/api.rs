@@ -1 +1,5 @@
+pub mod badge;
+pub mod clipboard;
+pub mod context_menu;
 pub mod extension_api;
+pub mod native_connection;
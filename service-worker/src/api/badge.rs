@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Mutex;
+
+use browser_rpass::{
+    js_binding::extension_api::*, store::load_equivalent_domains, types::Account,
+    url_match::account_matches_url,
+};
+use gloo_utils::format::JsValueSerdeExt;
+use lazy_static::lazy_static;
+#[allow(unused_imports)]
+use log::*;
+use serde_json::json;
+use wasm_bindgen::JsValue;
+use yewdux::dispatch::Dispatch;
+
+use crate::store::{SessionStore, StoreData};
+
+const LOCKED_BADGE_TEXT: &str = "🔒";
+const MATCH_BADGE_COLOR: &str = "#1d4ed8";
+const LOCKED_BADGE_COLOR: &str = "#6b7280";
+
+lazy_static! {
+    //the url of every tab seen since the service worker started, and the badge it was given
+    static ref TAB_URLS: Mutex<HashMap<i32, String>> = Mutex::new(HashMap::new());
+    static ref TAB_BADGES: Mutex<HashMap<i32, String>> = Mutex::new(HashMap::new());
+}
+
+//the accounts of the logged in stores, or none when every store is locked
+pub fn badge_accounts(stores: &HashMap<String, StoreData>) -> Option<Vec<Rc<Account>>> {
+    let mut logged_in = false;
+    let accounts = stores
+        .values()
+        .filter(|store| store.verified)
+        .flat_map(|store| {
+            logged_in = true;
+            store.accounts.borrow().clone()
+        })
+        .collect::<Vec<Rc<Account>>>();
+    logged_in.then_some(accounts)
+}
+
+fn badge_text(
+    accounts: Option<&Vec<Rc<Account>>>,
+    url: &str,
+    equivalent_domains: &[Vec<String>],
+) -> String {
+    let Some(accounts) = accounts else {
+        return LOCKED_BADGE_TEXT.to_owned();
+    };
+    match accounts
+        .iter()
+        .filter(|account| account_matches_url(account, url, equivalent_domains))
+        .count()
+    {
+        0 => String::new(),
+        count => count.to_string(),
+    }
+}
+
+fn set_tab_badge(tab_id: i32, text: String) {
+    {
+        let mut badges = TAB_BADGES.lock().unwrap();
+        if badges.get(&tab_id) == Some(&text) {
+            return;
+        }
+        badges.insert(tab_id, text.clone());
+    }
+    let color = if text == LOCKED_BADGE_TEXT {
+        LOCKED_BADGE_COLOR
+    } else {
+        MATCH_BADGE_COLOR
+    };
+    let action = chrome.action();
+    let _ = action.set_badge_background_color(
+        <JsValue as JsValueSerdeExt>::from_serde(&json!({"color": color, "tabId": tab_id}))
+            .unwrap(),
+    );
+    let _ = action.set_badge_text(
+        <JsValue as JsValueSerdeExt>::from_serde(&json!({"text": text, "tabId": tab_id})).unwrap(),
+    );
+}
+
+//sets the badges of the given tabs from the accounts in the session store
+fn update_badges(tab_ids: Vec<i32>) {
+    wasm_bindgen_futures::spawn_local(async move {
+        let equivalent_domains = load_equivalent_domains().await;
+        let accounts = badge_accounts(&Dispatch::<SessionStore>::new().get().stores.borrow());
+        for tab_id in tab_ids {
+            let Some(url) = TAB_URLS.lock().unwrap().get(&tab_id).cloned() else {
+                continue;
+            };
+            set_tab_badge(
+                tab_id,
+                badge_text(accounts.as_ref(), &url, &equivalent_domains),
+            );
+        }
+    });
+}
+
+pub fn set_tab_url(tab_id: i32, url: String) {
+    TAB_URLS.lock().unwrap().insert(tab_id, url);
+    update_badges(vec![tab_id]);
+}
+
+pub fn forget_tab(tab_id: i32) {
+    TAB_URLS.lock().unwrap().remove(&tab_id);
+    TAB_BADGES.lock().unwrap().remove(&tab_id);
+}
+
+//for when accounts or stores changed
+pub fn refresh_badges() {
+    let tab_ids = TAB_URLS.lock().unwrap().keys().cloned().collect();
+    update_badges(tab_ids);
+}
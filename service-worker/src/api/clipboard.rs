@@ -0,0 +1,160 @@
+use browser_rpass::clipboard::{
+    clear_clipboard_if_unchanged, load_clipboard_clear_seconds, CopiedDigest,
+};
+use browser_rpass::js_binding::clipboard_copy;
+use browser_rpass::js_binding::extension_api::*;
+use browser_rpass::store::StorageArea;
+use gloo::timers::callback::Timeout;
+use gloo_utils::format::JsValueSerdeExt;
+use log::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+
+const OFFSCREEN_DOCUMENT_PATH: &str = "offscreen.html";
+pub const CLIPBOARD_CLEAR_ALARM: &str = "clear_clipboard";
+//kept in session storage, so the clear still happens when the service worker was suspended in
+//the meantime and the alarm starts a new one
+const PENDING_CLIPBOARD_CLEAR_KEY: &str = "pending_clipboard_clear";
+
+#[derive(Serialize, Deserialize, Debug)]
+struct PendingClipboardClear {
+    copied: CopiedDigest,
+    clear_at: f64,
+}
+
+//copies for requests that come from no page, like the address bar, so the value is never handed
+//to a content script
+pub async fn copy_from_service_worker(text: String) {
+    let result = match chrome.offscreen() {
+        Some(offscreen) => {
+            send_to_offscreen_document(
+                &offscreen,
+                json!({ "type": "copy_to_clipboard", "value": text }),
+                "copied",
+            )
+            .await
+        }
+        None => clipboard_copy(&text).await.map(|_| true),
+    };
+    match result {
+        Ok(true) => match CopiedDigest::of(&text).await {
+            Ok(copied) => schedule_clipboard_clear(copied),
+            Err(err) => error!("failed to hash the copied value: {:?}", err),
+        },
+        Ok(false) => warn!("the clipboard refused the copy"),
+        Err(err) => error!("failed to copy to the clipboard: {:?}", err),
+    }
+}
+
+//clear the copied value from the clipboard once the delay set by the user has passed. a later
+//copy replaces the pending clear and its alarm, so only the latest copy is cleared
+pub fn schedule_clipboard_clear(copied: CopiedDigest) {
+    wasm_bindgen_futures::spawn_local(async move {
+        let seconds = load_clipboard_clear_seconds().await;
+        if seconds == 0 {
+            return;
+        }
+        let clear_at = js_sys::Date::now() + f64::from(seconds) * 1000.0;
+        chrome
+            .storage()
+            .session()
+            .set_string_item(
+                PENDING_CLIPBOARD_CLEAR_KEY.to_owned(),
+                serde_json::to_string(&PendingClipboardClear { copied, clear_at }).unwrap(),
+                StorageArea::Session,
+            )
+            .await;
+        if let Err(err) = JsFuture::from(chrome.alarms().create(
+            CLIPBOARD_CLEAR_ALARM,
+            <JsValue as JsValueSerdeExt>::from_serde(&json!({ "when": clear_at })).unwrap(),
+        ))
+        .await
+        {
+            error!("failed to schedule the clipboard clear: {:?}", err);
+        }
+        //chrome fires alarms 30 seconds out at the earliest, so a shorter delay is kept by the
+        //timer as long as the service worker is running
+        Timeout::new(seconds.saturating_mul(1000), || {
+            wasm_bindgen_futures::spawn_local(clear_pending_clipboard());
+        })
+        .forget();
+    });
+}
+
+//run by the timer and the alarm alike, whichever comes first once the clear is due
+pub async fn clear_pending_clipboard() {
+    let session = chrome.storage().session();
+    let pending = session
+        .get_item(PENDING_CLIPBOARD_CLEAR_KEY, StorageArea::Session)
+        .await
+        .ok()
+        .and_then(|value| <JsValue as JsValueSerdeExt>::into_serde::<String>(&value).ok())
+        .and_then(|pending| serde_json::from_str::<PendingClipboardClear>(&pending).ok());
+    let Some(pending) = pending else {
+        return;
+    };
+    //the timer of an earlier copy
+    if pending.clear_at > js_sys::Date::now() {
+        return;
+    }
+    session.remove(PENDING_CLIPBOARD_CLEAR_KEY).await;
+    clear_clipboard(pending.copied).await;
+}
+
+async fn clear_clipboard(copied: CopiedDigest) {
+    let result =
+        match chrome.offscreen() {
+            Some(offscreen) => send_to_offscreen_document(
+                &offscreen,
+                json!({ "type": "clear_clipboard", "salt": copied.salt, "digest": copied.digest }),
+                "cleared",
+            )
+            .await,
+            None => clear_clipboard_if_unchanged(&copied).await,
+        };
+    match result {
+        Ok(true) => debug!("cleared the copied value from the clipboard"),
+        Ok(false) => debug!("clipboard was overwritten since the copy, left it alone"),
+        Err(err) => error!("failed to clear the clipboard: {:?}", err),
+    }
+}
+
+//the service worker of chrome has no clipboard, so an offscreen document reaches it instead. the
+//document answers whether it succeeded under `result_key`
+async fn send_to_offscreen_document(
+    offscreen: &Offscreen,
+    message: serde_json::Value,
+    result_key: &str,
+) -> Result<bool, JsValue> {
+    let parameters = json!({
+        "url": OFFSCREEN_DOCUMENT_PATH,
+        "reasons": ["CLIPBOARD"],
+        "justification": "copy passwords to and clear them from the clipboard",
+    });
+    //fails when the document is still open from an earlier request, which is fine
+    if let Err(err) = JsFuture::from(
+        offscreen.create_document(<JsValue as JsValueSerdeExt>::from_serde(&parameters).unwrap()),
+    )
+    .await
+    {
+        debug!("offscreen document not created: {:?}", err);
+    }
+    let response = JsFuture::from(
+        chrome
+            .runtime()
+            .send_message(<JsValue as JsValueSerdeExt>::from_serde(&message).unwrap()),
+    )
+    .await;
+    let _ = JsFuture::from(offscreen.close_document()).await;
+    let succeeded = <JsValue as JsValueSerdeExt>::into_serde::<serde_json::Value>(&response?)
+        .ok()
+        .and_then(|response| {
+            response
+                .get(result_key)
+                .and_then(|succeeded| succeeded.as_bool())
+        })
+        .unwrap_or(false);
+    Ok(succeeded)
+}
@@ -0,0 +1,224 @@
+use std::{collections::HashMap, net::Ipv4Addr};
+
+use browser_rpass::{
+    js_binding::extension_api::*,
+    request::ContextMenuAction,
+    types::Account,
+    url_match::{parse_saved_url, registrable_domain, MatchMode},
+};
+use gloo_utils::format::JsValueSerdeExt;
+#[allow(unused_imports)]
+use log::*;
+use serde_json::json;
+use wasm_bindgen::JsValue;
+
+use crate::store::StoreData;
+
+const EDITABLE_CONTEXTS: &[&str] = &["editable"];
+const PAGE_CONTEXTS: &[&str] = &["page", "editable"];
+
+//what the menu shows of an account, so the menu is only rebuilt when one of these changes
+#[derive(Debug, Clone, PartialEq)]
+pub struct MenuAccount {
+    pub store_id: String,
+    pub account_id: String,
+    pub username: String,
+    pub domain: String,
+    pub url_patterns: Vec<String>,
+    pub has_totp: bool,
+}
+
+pub fn menu_accounts(stores: &HashMap<String, StoreData>) -> Vec<MenuAccount> {
+    let mut menu_accounts = stores
+        .values()
+        .filter(|store| store.verified)
+        .flat_map(|store| {
+            store
+                .accounts
+                .borrow()
+                .iter()
+                .filter_map(|account| {
+                    let url_patterns = url_patterns(account);
+                    if url_patterns.is_empty() {
+                        return None;
+                    }
+                    Some(MenuAccount {
+                        store_id: store.store_id.clone(),
+                        account_id: account.id.clone(),
+                        username: account.username.clone(),
+                        domain: account.domain.clone()?,
+                        url_patterns,
+                        has_totp: account.has_totp,
+                    })
+                })
+                .collect::<Vec<MenuAccount>>()
+        })
+        .collect::<Vec<MenuAccount>>();
+    menu_accounts.sort_by(|a, b| (&a.domain, &a.username).cmp(&(&b.domain, &b.username)));
+    menu_accounts
+}
+
+fn action_id(action: ContextMenuAction) -> String {
+    serde_json::to_value(action)
+        .ok()
+        .and_then(|value| value.as_str().map(|value| value.to_owned()))
+        .unwrap_or_default()
+}
+pub fn account_menu_id(action: ContextMenuAction, store_id: &str, account_id: &str) -> String {
+    format!("{}|{}|{}", action_id(action), store_id, account_id)
+}
+//menu item id to the action and, for account entries, the store id and account id
+pub fn parse_menu_id(menu_id: &str) -> Option<(ContextMenuAction, Option<(String, String)>)> {
+    let mut parts = menu_id.splitn(3, '|');
+    let action = serde_json::from_value::<ContextMenuAction>(json!(parts.next()?)).ok()?;
+    let account = match (parts.next(), parts.next()) {
+        (Some(store_id), Some(account_id)) => Some((store_id.to_owned(), account_id.to_owned())),
+        _ => None,
+    };
+    Some((action, account))
+}
+//match patterns of the pages an account is offered on. they can't express every match mode, so
+//regex urls are offered on their saved host and the content script has the final say
+fn url_patterns(account: &Account) -> Vec<String> {
+    let host_of =
+        |url: &str| parse_saved_url(url).and_then(|url| url.host_str().map(|host| host.to_owned()));
+    let mut patterns = account
+        .all_urls()
+        .into_iter()
+        .flat_map(|account_url| {
+            let Some(host) = host_of(&account_url.url) else {
+                return Vec::new();
+            };
+            match account_url.match_mode {
+                MatchMode::BaseDomain => {
+                    let base = registrable_domain(&host);
+                    if base.parse::<Ipv4Addr>().is_ok() || base.starts_with('[') {
+                        vec![format!("*://{}/*", base)]
+                    } else {
+                        vec![format!("*://{}/*", base), format!("*://*.{}/*", base)]
+                    }
+                }
+                MatchMode::Host | MatchMode::HostPort | MatchMode::Regex(_) => {
+                    vec![format!("*://{}/*", host)]
+                }
+                MatchMode::StartsWith(prefix) => {
+                    let host = host_of(&prefix).unwrap_or(host);
+                    vec![format!("*://{}/*", host)]
+                }
+                MatchMode::Never => Vec::new(),
+            }
+        })
+        .collect::<Vec<String>>();
+    patterns.sort();
+    patterns.dedup();
+    patterns
+}
+
+fn create_menu_item(properties: serde_json::Value) {
+    chrome
+        .context_menus()
+        .create(<JsValue as JsValueSerdeExt>::from_serde(&properties).unwrap());
+}
+//an entry with a submenu of accounts, only shown on the sites of those accounts
+fn create_account_menu(
+    action: ContextMenuAction,
+    title: &str,
+    contexts: &[&str],
+    accounts: &[&MenuAccount],
+    show_store: bool,
+) {
+    if accounts.is_empty() {
+        return;
+    }
+    let mut patterns = accounts
+        .iter()
+        .flat_map(|account| account.url_patterns.clone())
+        .collect::<Vec<String>>();
+    patterns.sort();
+    patterns.dedup();
+    create_menu_item(json!({
+        "id": action_id(action),
+        "title": title,
+        "contexts": contexts,
+        "documentUrlPatterns": patterns,
+    }));
+    create_account_items(action, contexts, accounts, show_store);
+}
+fn create_account_items(
+    action: ContextMenuAction,
+    contexts: &[&str],
+    accounts: &[&MenuAccount],
+    show_store: bool,
+) {
+    for account in accounts {
+        let title = if show_store {
+            format!("{} ({})", account.username, account.store_id)
+        } else {
+            account.username.clone()
+        };
+        create_menu_item(json!({
+            "id": account_menu_id(action, &account.store_id, &account.account_id),
+            "parentId": action_id(action),
+            "title": title,
+            "contexts": contexts,
+            "documentUrlPatterns": account.url_patterns,
+        }));
+    }
+}
+
+//the generate and save entries are always there, the account entries only on the sites of the
+//accounts of unlocked stores
+pub async fn rebuild_context_menus(menu_accounts: Vec<MenuAccount>) {
+    let _ = wasm_bindgen_futures::JsFuture::from(chrome.context_menus().remove_all()).await;
+    let show_store = menu_accounts
+        .iter()
+        .any(|account| account.store_id != menu_accounts[0].store_id);
+    let accounts = menu_accounts.iter().collect::<Vec<&MenuAccount>>();
+    create_account_menu(
+        ContextMenuAction::FillUsername,
+        "Fill username",
+        EDITABLE_CONTEXTS,
+        &accounts,
+        show_store,
+    );
+    create_account_menu(
+        ContextMenuAction::FillPassword,
+        "Fill password",
+        EDITABLE_CONTEXTS,
+        &accounts,
+        show_store,
+    );
+    create_menu_item(json!({
+        "id": action_id(ContextMenuAction::GeneratePassword),
+        "title": "Generate password",
+        "contexts": EDITABLE_CONTEXTS,
+    }));
+    create_menu_item(json!({
+        "id": format!("{}|new", action_id(ContextMenuAction::GeneratePassword)),
+        "parentId": action_id(ContextMenuAction::GeneratePassword),
+        "title": "New login",
+        "contexts": EDITABLE_CONTEXTS,
+    }));
+    create_account_items(
+        ContextMenuAction::GeneratePassword,
+        EDITABLE_CONTEXTS,
+        &accounts,
+        show_store,
+    );
+    create_account_menu(
+        ContextMenuAction::CopyTotp,
+        "Copy TOTP",
+        PAGE_CONTEXTS,
+        &accounts
+            .iter()
+            .filter(|account| account.has_totp)
+            .cloned()
+            .collect::<Vec<&MenuAccount>>(),
+        show_store,
+    );
+    create_menu_item(json!({
+        "id": action_id(ContextMenuAction::SaveLogin),
+        "title": "Save this login",
+        "contexts": PAGE_CONTEXTS,
+    }));
+}
@@ -2,35 +2,65 @@ use browser_rpass::{
     js_binding::extension_api::*,
     request::{SessionEvent, SessionEventType},
     response::{MessageEnum, RequestEnum},
+    store::load_equivalent_domains,
+    types::Account,
+    url_match::account_matches_url,
 };
 use gloo_utils::format::JsValueSerdeExt;
+use lazy_static::lazy_static;
+use serde_json::json;
 use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
 use wasm_bindgen::JsValue;
 
-use crate::store::{EXTENSION_PORT, LISTENER_PORT};
+use crate::store::{CONTENT_FRAMES, EXTENSION_PORT, LISTENER_PORT};
+
+lazy_static! {
+    //kept here so events can be scoped to a frame without waiting on the storage
+    static ref EQUIVALENT_DOMAINS: Mutex<Vec<Vec<String>>> = Mutex::new(Vec::new());
+}
+pub async fn refresh_equivalent_domains() {
+    let equivalent_domains = load_equivalent_domains().await;
+    *EQUIVALENT_DOMAINS.lock().unwrap() = equivalent_domains;
+}
+
+//pages of the extension get every event as it is. any other port is a content script, which
+//runs in every frame, cross-origin iframes included, so it only gets the accounts its own
+//frame's url matches
+fn post_session_event(port: &Port, session_event: &SessionEvent) {
+    let sender_url = port.sender().and_then(|sender| sender.url());
+    let extension_url = chrome.runtime().get_url("");
+    let session_event = if sender_url
+        .as_ref()
+        .is_some_and(|url| url.starts_with(&extension_url))
+    {
+        session_event.clone()
+    } else {
+        let equivalent_domains = EQUIVALENT_DOMAINS.lock().unwrap().clone();
+        match session_event.for_frame(sender_url.as_deref(), &equivalent_domains) {
+            Some(session_event) => session_event,
+            None => return,
+        }
+    };
+    let request = MessageEnum::Message(RequestEnum::create_session_event_request(
+        None,
+        session_event,
+        None,
+        None,
+    ));
+    port.post_message(<JsValue as JsValueSerdeExt>::from_serde(&request).unwrap());
+}
 
 pub fn broadcast_session_event(
     session_event: SessionEvent,
     ports_to_disconnect: Option<HashMap<String, HashSet<String>>>,
 ) {
-    let event_type = session_event.event_type.clone();
-    match event_type {
-        SessionEventType::Create
-        | SessionEventType::Update
-        | SessionEventType::Delete
-        | SessionEventType::Refreshed => {}
-        _ => {}
-    }
+    //a store getting unlocked concerns every page, not only those listening to it already
+    let to_every_port = matches!(session_event.event_type, SessionEventType::Login(_));
     let mut locked = LISTENER_PORT.lock().unwrap();
-    if session_event.store_id_index.is_none() {
+    if session_event.store_id_index.is_none() || to_every_port {
         for port in EXTENSION_PORT.lock().unwrap().values() {
-            let request = MessageEnum::Message(RequestEnum::create_session_event_request(
-                None,
-                session_event.clone(),
-                None,
-                None,
-            ));
-            port.post_message(<JsValue as JsValueSerdeExt>::from_serde(&request).unwrap());
+            post_session_event(port, &session_event);
         }
         return;
     } else {
@@ -38,13 +68,7 @@ pub fn broadcast_session_event(
         if let Some(listeners) = listeners {
             for port in EXTENSION_PORT.lock().unwrap().values() {
                 if listeners.contains(&port.name()) {
-                    let request = MessageEnum::Message(RequestEnum::create_session_event_request(
-                        None,
-                        session_event.clone(),
-                        None,
-                        None,
-                    ));
-                    port.post_message(<JsValue as JsValueSerdeExt>::from_serde(&request).unwrap());
+                    post_session_event(port, &session_event);
                 }
             }
         }
@@ -61,11 +85,52 @@ pub fn broadcast_session_event(
     }
 }
 pub fn whisper_session_event(session_event: SessionEvent, port: &Port) {
-    let msg = MessageEnum::Message(RequestEnum::create_session_event_request(
-        None,
-        session_event,
-        None,
-        None,
-    ));
-    port.post_message(<JsValue as JsValueSerdeExt>::from_serde(&msg).unwrap());
+    post_session_event(port, &session_event);
+}
+pub async fn active_tab_id() -> Option<i32> {
+    let tabs = wasm_bindgen_futures::JsFuture::from(
+        chrome.tabs().query(
+            <JsValue as JsValueSerdeExt>::from_serde(&json!({"active":true,"currentWindow":true}))
+                .unwrap(),
+        ),
+    )
+    .await
+    .ok()?;
+    let tabs: serde_json::Value = <JsValue as JsValueSerdeExt>::into_serde(&tabs).ok()?;
+    tabs.get(0)?.get("id")?.as_i64().map(|tab_id| tab_id as i32)
+}
+//send the request to the content script of every frame in the tab that holds a login form. with
+//an account, only frames whose own url the account matches get it. returns the number of frames
+pub fn message_login_form_frames(
+    tab_id: i32,
+    account: Option<&Account>,
+    equivalent_domains: &[Vec<String>],
+    request: RequestEnum,
+) -> usize {
+    let port_names = CONTENT_FRAMES
+        .lock()
+        .unwrap()
+        .get(&tab_id)
+        .map(|frames| {
+            frames
+                .values()
+                .filter(|frame| {
+                    frame.has_login_form
+                        && account.map_or(true, |account| {
+                            frame.url.as_ref().is_some_and(|url| {
+                                account_matches_url(account, url, equivalent_domains)
+                            })
+                        })
+                })
+                .map(|frame| frame.port_name.clone())
+                .collect::<Vec<String>>()
+        })
+        .unwrap_or_default();
+    let message = <JsValue as JsValueSerdeExt>::from_serde(&MessageEnum::Message(request)).unwrap();
+    let ports = EXTENSION_PORT.lock().unwrap();
+    port_names
+        .iter()
+        .filter_map(|port_name| ports.get(port_name))
+        .map(|port| port.post_message(message.clone()))
+        .count()
 }
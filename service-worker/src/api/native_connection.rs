@@ -0,0 +1,272 @@
+use crate::api::extension_api::broadcast_session_event;
+use crate::event_handlers::native_message_handler::{process_native_message, reply_to_requester};
+use crate::store::{SessionAction, SessionActionWrapper, SessionStore, NATIVE_PORT, REQUEST_MAP};
+use browser_rpass::js_binding::extension_api::*;
+use browser_rpass::payload::FailurePayload;
+use browser_rpass::request::{NativeConnectionState, RequestEnum, SessionEvent, SessionEventType};
+use browser_rpass::response::{ErrorCode, ErrorResponse, ResponseEnum};
+use gloo::timers::callback::Timeout;
+use gloo_utils::format::JsValueSerdeExt;
+use lazy_static::lazy_static;
+use log::*;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use wasm_bindgen::prelude::Closure;
+use wasm_bindgen::JsValue;
+use yewdux::dispatch::Dispatch;
+
+const INITIAL_RECONNECT_DELAY_MS: u32 = 500;
+const MAX_RECONNECT_DELAY_MS: u32 = 60_000;
+//after this many failed reconnects in a row the native app is taken to be unreachable, and the
+//requests kept for replay are failed too
+const RECONNECT_ATTEMPTS_BEFORE_GIVING_UP: u32 = 5;
+
+lazy_static! {
+    static ref RECONNECT_ATTEMPTS: Mutex<u32> = Mutex::new(0);
+}
+lazy_static! {
+    pub static ref NATIVE_CONNECTION_STATE: Mutex<NativeConnectionState> =
+        Mutex::new(NativeConnectionState::default());
+}
+
+//opens a port to the native app and sends it an init request, followed by the requests that were
+//still waiting for an answer when the previous port went down
+pub fn connect_native_port() -> (Port, RequestEnum) {
+    let port = chrome.runtime().connect_native("rpass");
+    port.on_disconnect()
+        .add_listener(Closure::<dyn Fn(Port)>::new(native_port_disconnect_handler).into_js_value());
+    port.on_message()
+        .add_listener(Closure::<dyn Fn(String)>::new(native_port_message_handler).into_js_value());
+    #[allow(unused_mut)]
+    let mut init_config = HashMap::new();
+    let init_request = RequestEnum::create_init_request(init_config, None, None);
+    port.post_message(<JsValue as JsValueSerdeExt>::from_serde(&init_request).unwrap());
+    for request in REQUEST_MAP.lock().unwrap().values() {
+        if let Some(message) = replayable_message(request) {
+            port.post_message(message);
+        }
+    }
+    (port, init_request)
+}
+
+//how long the native app may take to answer a request. requests that may wait on the user at
+//pinentry get longer
+fn request_timeout_ms(request: &RequestEnum) -> u32 {
+    match request {
+        RequestEnum::Login(_)
+        | RequestEnum::CreateStore(_)
+        | RequestEnum::DeleteStore(_)
+        | RequestEnum::Audit(_) => 120_000,
+        RequestEnum::Fetch(_)
+        | RequestEnum::Create(_)
+        | RequestEnum::Edit(_)
+        | RequestEnum::Delete(_) => 60_000,
+        _ => 30_000,
+    }
+}
+
+//keeps a request forwarded to the native app until it is answered, and gives up on it once its
+//deadline has passed
+pub fn track_native_request(acknowledgement: String, request: RequestEnum) {
+    let timeout_ms = request_timeout_ms(&request);
+    REQUEST_MAP
+        .lock()
+        .unwrap()
+        .insert(acknowledgement.clone(), request);
+    Timeout::new(timeout_ms, move || {
+        let request = REQUEST_MAP.lock().unwrap().remove(&acknowledgement);
+        if let Some(request) = request {
+            warn!(
+                "native app did not answer {} request in {}ms",
+                request, timeout_ms
+            );
+            Dispatch::<SessionStore>::new().apply(SessionActionWrapper {
+                meta: Some(json!({ "acknowledgement": acknowledgement })),
+                action: SessionAction::RequestTimedOut(request, timeout_ms),
+            });
+        }
+    })
+    .forget();
+}
+
+//the message to send the native app again for a request that is safe to repeat, as it does not
+//change the store
+fn replayable_message(request: &RequestEnum) -> Option<JsValue> {
+    match request {
+        RequestEnum::Get(get_request) => <JsValue as JsValueSerdeExt>::from_serde(get_request).ok(),
+        RequestEnum::Fetch(fetch_request) => {
+            <JsValue as JsValueSerdeExt>::from_serde(fetch_request).ok()
+        }
+        RequestEnum::Search(search_request) => {
+            <JsValue as JsValueSerdeExt>::from_serde(search_request).ok()
+        }
+        RequestEnum::Logout(logout_request) => {
+            <JsValue as JsValueSerdeExt>::from_serde(logout_request).ok()
+        }
+        RequestEnum::GeneratePassword(generate_password_request) => {
+            <JsValue as JsValueSerdeExt>::from_serde(generate_password_request).ok()
+        }
+        RequestEnum::Audit(audit_request) => {
+            <JsValue as JsValueSerdeExt>::from_serde(audit_request).ok()
+        }
+        _ => None,
+    }
+}
+
+fn reconnect_delay_ms(attempt: u32) -> u32 {
+    INITIAL_RECONNECT_DELAY_MS
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(MAX_RECONNECT_DELAY_MS)
+}
+
+fn set_connection_state(state: NativeConnectionState) {
+    {
+        let mut current = NATIVE_CONNECTION_STATE.lock().unwrap();
+        if *current == state {
+            return;
+        }
+        *current = state.clone();
+    }
+    broadcast_session_event(
+        SessionEvent {
+            store_id_index: None,
+            event_type: SessionEventType::NativeAppConnection(state),
+            data: None,
+            header: None,
+            resource: None,
+            is_global: true,
+            acknowledgement: None,
+        },
+        None,
+    );
+}
+
+//answers a request the native app will not answer anymore, the same way a failure reported by
+//the native app would be answered
+fn fail_request(acknowledgement: String, request: RequestEnum) {
+    let message = "connection to the native app was lost".to_owned();
+    let error_response = ResponseEnum::ErrorResponse(ErrorResponse {
+        acknowledgement: Some(acknowledgement.clone()),
+        message: Some(message.clone()),
+        code: Some(ErrorCode::NativeAppConnectionError),
+    });
+    let failure = FailurePayload {
+        message: Some(message),
+        code: Some(ErrorCode::NativeAppConnectionError),
+        ..Default::default()
+    };
+    let action = match &request {
+        RequestEnum::Create(create_request) => Some(SessionAction::DataCreationFailed(
+            create_request.resource.clone(),
+            failure,
+            Some(request.clone()),
+        )),
+        RequestEnum::Edit(edit_request) => Some(SessionAction::DataEditFailed(
+            edit_request.resource.clone(),
+            failure,
+            Some(request.clone()),
+        )),
+        RequestEnum::CreateStore(_) => Some(SessionAction::StoreCreationFailed(
+            request.clone(),
+            error_response.clone(),
+        )),
+        RequestEnum::DeleteStore(_) => Some(SessionAction::StoreDeletionFailed(
+            request.clone(),
+            error_response.clone(),
+        )),
+        RequestEnum::Login(login_request) => Some(SessionAction::LoginError(login_request.clone())),
+        _ => None,
+    };
+    let dispatch = Dispatch::<SessionStore>::new();
+    //a store left loading would never be fetched again
+    if let RequestEnum::Fetch(fetch_request) = &request {
+        if let Some(store_id) = fetch_request.store_id.clone() {
+            dispatch.apply(SessionActionWrapper {
+                meta: None,
+                action: SessionAction::DataLoadingFailed(store_id),
+            });
+        }
+    }
+    match action {
+        Some(action) => dispatch.apply(SessionActionWrapper {
+            meta: Some(json!({ "acknowledgement": acknowledgement })),
+            action,
+        }),
+        None => reply_to_requester(&Some(acknowledgement), &error_response),
+    }
+}
+
+fn native_port_disconnect_handler(_port: Port) {
+    if let Ok(mut borrowed) = NATIVE_PORT.lock().try_borrow_mut() {
+        *borrowed = None;
+    }
+    let attempt = {
+        let mut attempts = RECONNECT_ATTEMPTS.lock().unwrap();
+        *attempts += 1;
+        *attempts
+    };
+    let giving_up = attempt > RECONNECT_ATTEMPTS_BEFORE_GIVING_UP;
+    if giving_up {
+        error!(
+            "native app disconnected {} times in a row. Likely native app not reachable.",
+            attempt
+        );
+    } else {
+        warn!("native port disconnected, reconnect attempt {}", attempt);
+    }
+    let failed_requests = {
+        let mut request_map = REQUEST_MAP.lock().unwrap();
+        let failed_acknowledgements = request_map
+            .iter()
+            .filter(|(_, request)| giving_up || replayable_message(request).is_none())
+            .map(|(acknowledgement, _)| acknowledgement.clone())
+            .collect::<Vec<String>>();
+        failed_acknowledgements
+            .into_iter()
+            .filter_map(|acknowledgement| {
+                request_map
+                    .remove(&acknowledgement)
+                    .map(|request| (acknowledgement, request))
+            })
+            .collect::<Vec<(String, RequestEnum)>>()
+    };
+    for (acknowledgement, request) in failed_requests {
+        fail_request(acknowledgement, request);
+    }
+    let retry_in_ms = reconnect_delay_ms(attempt);
+    set_connection_state(if giving_up {
+        NativeConnectionState::Disconnected
+    } else {
+        NativeConnectionState::Reconnecting {
+            attempt,
+            retry_in_ms,
+        }
+    });
+    Timeout::new(retry_in_ms, || {
+        let (port, _init_request) = connect_native_port();
+        if let Ok(mut borrowed) = NATIVE_PORT.lock().try_borrow_mut() {
+            *borrowed = Some(port);
+        }
+    })
+    .forget();
+}
+
+fn native_port_message_handler(msg: String) {
+    //the native app only answers once it is up, so the first message marks the port as connected
+    if *NATIVE_CONNECTION_STATE.lock().unwrap() != NativeConnectionState::Connected {
+        *RECONNECT_ATTEMPTS.lock().unwrap() = 0;
+        set_connection_state(NativeConnectionState::Connected);
+    }
+    match serde_json::from_slice::<Value>(&msg.as_bytes()) {
+        Ok(parsed_json) => {
+            let _ = process_native_message(parsed_json, NATIVE_PORT.lock().borrow().as_ref(), None);
+        }
+        Err(e) => {
+            error!(
+                "error happend while parsing:{:?}. Error message: {:?}",
+                msg, e
+            );
+        }
+    }
+}
@@ -1,2 +1,8 @@
+pub mod alarm_handler;
+pub mod command_handler;
+pub mod context_menu_handler;
 pub mod native_message_handler;
+pub mod omnibox_handler;
 pub mod popup_request_handler;
+pub mod storage_handler;
+pub mod tab_handler;
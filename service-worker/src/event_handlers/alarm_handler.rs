@@ -0,0 +1,21 @@
+use gloo_utils::format::JsValueSerdeExt;
+#[allow(unused_imports)]
+use log::*;
+use wasm_bindgen::prelude::Closure;
+use wasm_bindgen::JsValue;
+
+use crate::api::clipboard::{clear_pending_clipboard, CLIPBOARD_CLEAR_ALARM};
+
+//alarms start the service worker again when it was suspended, unlike timers
+pub fn create_alarm_listener() -> Closure<dyn Fn(JsValue)> {
+    Closure::<dyn Fn(JsValue)>::new(move |alarm: JsValue| {
+        let alarm: serde_json::Value =
+            <JsValue as JsValueSerdeExt>::into_serde(&alarm).unwrap_or_default();
+        match alarm.get("name").and_then(|name| name.as_str()) {
+            Some(CLIPBOARD_CLEAR_ALARM) => {
+                wasm_bindgen_futures::spawn_local(clear_pending_clipboard());
+            }
+            name => debug!("unknown alarm: {:?}", name),
+        }
+    })
+}
@@ -0,0 +1,42 @@
+use browser_rpass::js_binding::extension_api::*;
+use browser_rpass::request::BrowserCommand;
+use browser_rpass::response::RequestEnum;
+#[allow(unused_imports)]
+use log::*;
+use wasm_bindgen::prelude::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+
+use crate::api::extension_api::{active_tab_id, message_login_form_frames};
+
+//the content script knows the accounts of the site and what is in the form, so the shortcut is
+//only routed to the frames with a login form and handled there
+pub fn create_command_listener() -> Closure<dyn Fn(String, JsValue)> {
+    Closure::<dyn Fn(String, JsValue)>::new(move |command: String, tab: JsValue| {
+        let command = match serde_json::from_value::<BrowserCommand>(serde_json::json!(command)) {
+            Ok(command) => command,
+            Err(_) => {
+                warn!("unknown command: {}", command);
+                return;
+            }
+        };
+        let tab_id =
+            (!tab.is_undefined() && !tab.is_null()).then(|| tab.unchecked_into::<Tab>().id());
+        wasm_bindgen_futures::spawn_local(async move {
+            let tab_id = match tab_id {
+                Some(tab_id) => Some(tab_id),
+                None => active_tab_id().await,
+            };
+            if let Some(tab_id) = tab_id {
+                let frames = message_login_form_frames(
+                    tab_id,
+                    None,
+                    &[],
+                    RequestEnum::create_command_request(command, None),
+                );
+                if frames == 0 {
+                    debug!("no login form in tab {} for {:?}", tab_id, command);
+                }
+            }
+        });
+    })
+}
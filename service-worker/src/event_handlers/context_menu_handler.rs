@@ -0,0 +1,101 @@
+use std::ops::Deref;
+
+use browser_rpass::js_binding::extension_api::*;
+use browser_rpass::request::ContextMenuAction;
+use browser_rpass::response::{MessageEnum, RequestEnum};
+use browser_rpass::store::load_equivalent_domains;
+use browser_rpass::types::Account;
+use browser_rpass::url_match::account_matches_url;
+use gloo_utils::format::JsValueSerdeExt;
+#[allow(unused_imports)]
+use log::*;
+use wasm_bindgen::prelude::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use yewdux::dispatch::Dispatch;
+
+use crate::api::context_menu::parse_menu_id;
+use crate::store::{SessionStore, CONTENT_FRAMES, EXTENSION_PORT};
+
+pub fn find_account(store_id: &str, account_id: &str) -> Option<Account> {
+    let dispatch = Dispatch::<SessionStore>::new();
+    let stores = dispatch.get().stores.clone();
+    let stores = stores.borrow();
+    let store = stores.get(store_id).filter(|store| store.verified)?;
+    let account = store
+        .accounts
+        .borrow()
+        .iter()
+        .find(|account| account.id == account_id)
+        .map(|account| account.deref().clone());
+    account
+}
+
+//the entry is handled by the content script of the frame it was clicked in. entries of an
+//account are only sent to a frame whose own origin matches the account
+pub fn create_context_menu_listener() -> Closure<dyn Fn(JsValue, JsValue)> {
+    Closure::<dyn Fn(JsValue, JsValue)>::new(move |info: JsValue, tab: JsValue| {
+        let info: serde_json::Value =
+            <JsValue as JsValueSerdeExt>::into_serde(&info).unwrap_or_default();
+        let Some((action, account_ids)) = info
+            .get("menuItemId")
+            .and_then(|menu_id| menu_id.as_str())
+            .and_then(parse_menu_id)
+        else {
+            return;
+        };
+        let frame_id = info
+            .get("frameId")
+            .and_then(|frame_id| frame_id.as_i64())
+            .unwrap_or(0) as i32;
+        if tab.is_undefined() || tab.is_null() {
+            return;
+        }
+        let tab_id = tab.unchecked_into::<Tab>().id();
+        let Some(frame) = CONTENT_FRAMES
+            .lock()
+            .unwrap()
+            .get(&tab_id)
+            .and_then(|frames| frames.get(&frame_id).cloned())
+        else {
+            warn!("no content script in frame {} of tab {}", frame_id, tab_id);
+            return;
+        };
+        let (store_id, account) = match account_ids {
+            Some((store_id, account_id)) => {
+                let Some(account) = find_account(&store_id, &account_id) else {
+                    warn!("account for {:?} is not available", action);
+                    return;
+                };
+                (Some(store_id), Some(account))
+            }
+            None if action == ContextMenuAction::GeneratePassword
+                || action == ContextMenuAction::SaveLogin =>
+            {
+                (None, None)
+            }
+            None => return,
+        };
+        wasm_bindgen_futures::spawn_local(async move {
+            if let Some(account) = account.as_ref() {
+                let equivalent_domains = load_equivalent_domains().await;
+                let matches = frame
+                    .url
+                    .as_ref()
+                    .is_some_and(|url| account_matches_url(account, url, &equivalent_domains));
+                if !matches {
+                    warn!(
+                        "refused {:?} of an account of {:?} in a frame of {:?}",
+                        action, account.domain, frame.url
+                    );
+                    return;
+                }
+            }
+            let request = MessageEnum::Message(RequestEnum::create_context_menu_request(
+                store_id, action, account, None,
+            ));
+            if let Some(port) = EXTENSION_PORT.lock().unwrap().get(&frame.port_name) {
+                port.post_message(<JsValue as JsValueSerdeExt>::from_serde(&request).unwrap());
+            }
+        });
+    })
+}
@@ -1,6 +1,8 @@
 use crate::store::SessionAction;
 use crate::store::SessionActionWrapper;
 use crate::store::SessionStore;
+use crate::store::EXTENSION_PORT;
+use crate::store::PORT_ID_MAP;
 use crate::store::REQUEST_MAP;
 use crate::Resource;
 use browser_rpass::js_binding::extension_api::Port;
@@ -11,9 +13,11 @@ use yewdux::dispatch::Dispatch;
 
 use browser_rpass::request::*;
 use browser_rpass::response::*;
+use gloo_utils::format::JsValueSerdeExt;
 use serde_json;
 use serde_json::json;
 use serde_json::Value;
+use wasm_bindgen::JsValue;
 
 pub fn process_native_message(
     json_msg: Value,
@@ -24,21 +28,17 @@ pub fn process_native_message(
     let response_wrapper = serde_json::from_value::<ResponseEnum>(json_msg.clone()).unwrap();
     let acknowledgement = response_wrapper.get_acknowledgement();
     let request = if let Some(ref acknowledgement) = acknowledgement {
-        REQUEST_MAP
-            .lock()
-            .unwrap()
-            .get(acknowledgement)
-            .map(|req| req.clone())
+        REQUEST_MAP.lock().unwrap().remove(acknowledgement)
     } else {
         None
     };
     match response_wrapper {
         ResponseEnum::LoginResponse(login_response) => {
-            if let RequestEnum::Login(login_request) = request.clone().unwrap() {
+            //the request is gone once it timed out
+            if let Some(RequestEnum::Login(login_request)) = request.clone() {
                 let login_response2 = login_response.clone();
                 let mut ctx = ctx.unwrap_or(json!({}));
                 ctx["store_id"] = json!(login_request.store_id);
-                ctx["prev_store_id"] = json!(login_request.prev_store_id);
                 ctx["is_default"] = json!(login_request.is_default);
                 ctx["acknowledgement"] = json!(login_request.acknowledgement);
                 wasm_bindgen_futures::spawn_local(async move {
@@ -91,6 +91,8 @@ pub fn process_native_message(
         }
         ResponseEnum::GetResponse(get_response) => {
             let response = ResponseEnum::GetResponse(get_response);
+            //one-time codes are not part of the session state either
+            reply_to_requester(&acknowledgement, &response);
             return Ok(response);
         }
         ResponseEnum::DeleteResponse(delete_response) => {
@@ -99,8 +101,11 @@ pub fn process_native_message(
                     session_store_dispatch.apply(SessionActionWrapper {
                         action: SessionAction::DataDeleted(
                             Resource::Account,
-                            delete_response.deleted_resource_id.clone(),
-                            delete_response.data.clone(),
+                            delete_response
+                                .deleted
+                                .as_ref()
+                                .map(|deleted| deleted.id.clone())
+                                .unwrap_or_else(|| delete_response.deleted_resource_id.clone()),
                         ),
                         meta: ctx,
                     });
@@ -125,7 +130,11 @@ pub fn process_native_message(
                         meta: ctx,
                         action: SessionAction::DataCreationFailed(
                             create_response.resource.clone(),
-                            create_response.data.clone(),
+                            create_response
+                                .error
+                                .clone()
+                                .map(|error| error.payload)
+                                .unwrap_or_default(),
                             request,
                         ),
                     });
@@ -144,13 +153,12 @@ pub fn process_native_message(
                     });
                 }
                 _ => {
-                    session_store_dispatch.apply(SessionActionWrapper {
-                        meta: ctx,
-                        action: SessionAction::StoreCreationFailed(
-                            request.clone().unwrap(),
-                            response.clone(),
-                        ),
-                    });
+                    if let Some(request) = request {
+                        session_store_dispatch.apply(SessionActionWrapper {
+                            meta: ctx,
+                            action: SessionAction::StoreCreationFailed(request, response.clone()),
+                        });
+                    }
                 }
             }
             return Ok(response);
@@ -166,13 +174,12 @@ pub fn process_native_message(
                     });
                 }
                 _ => {
-                    session_store_dispatch.apply(SessionActionWrapper {
-                        meta: ctx,
-                        action: SessionAction::StoreDeletionFailed(
-                            request.clone().unwrap(),
-                            response.clone(),
-                        ),
-                    });
+                    if let Some(request) = request {
+                        session_store_dispatch.apply(SessionActionWrapper {
+                            meta: ctx,
+                            action: SessionAction::StoreDeletionFailed(request, response.clone()),
+                        });
+                    }
                 }
             }
             return Ok(response);
@@ -192,7 +199,11 @@ pub fn process_native_message(
                         meta: ctx,
                         action: SessionAction::DataEditFailed(
                             edit_response.resource.clone(),
-                            edit_response.data.clone(),
+                            edit_response
+                                .error
+                                .clone()
+                                .map(|error| error.payload)
+                                .unwrap_or_default(),
                             request,
                         ),
                     });
@@ -200,6 +211,20 @@ pub fn process_native_message(
             }
             return Ok(response);
         }
+        ResponseEnum::GeneratePasswordResponse(generate_password_response) => {
+            let response = ResponseEnum::GeneratePasswordResponse(generate_password_response);
+            //generated passwords are not part of the session state, so hand the response straight
+            //back to the port that asked for it
+            reply_to_requester(&acknowledgement, &response);
+            return Ok(response);
+        }
+        ResponseEnum::AuditResponse(audit_response) => {
+            let response = ResponseEnum::AuditResponse(audit_response);
+            //the report holds decrypted details of the store, so it is only ever sent to the
+            //port that asked for it
+            reply_to_requester(&acknowledgement, &response);
+            return Ok(response);
+        }
         ResponseEnum::SearchResponse(search_response) => {
             let response = ResponseEnum::SearchResponse(search_response);
             return Ok(response);
@@ -246,3 +271,17 @@ pub fn process_native_message(
         }
     };
 }
+
+pub fn reply_to_requester(acknowledgement: &Option<String>, response: &ResponseEnum) {
+    let extension_port_name = acknowledgement
+        .as_ref()
+        .and_then(|acknowledgement| PORT_ID_MAP.lock().unwrap().remove(acknowledgement));
+    if let Some(extension_port_name) = extension_port_name {
+        if let Some(extension_port) = EXTENSION_PORT.lock().unwrap().get(&extension_port_name) {
+            extension_port.post_message(
+                <JsValue as JsValueSerdeExt>::from_serde(&MessageEnum::Response(response.clone()))
+                    .unwrap(),
+            );
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::Mutex;
+
+use browser_rpass::js_binding::extension_api::*;
+use browser_rpass::types::Account;
+use browser_rpass::url_match::{parse_saved_url, MatchMode};
+use gloo_utils::format::JsValueSerdeExt;
+use js_sys::Function;
+use lazy_static::lazy_static;
+#[allow(unused_imports)]
+use log::*;
+use secrecy::ExposeSecret;
+use serde_json::json;
+use wasm_bindgen::prelude::Closure;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+use yewdux::dispatch::Dispatch;
+
+use crate::api::clipboard::copy_from_service_worker;
+use crate::api::extension_api::active_tab_id;
+use crate::event_handlers::context_menu_handler::find_account;
+use crate::event_handlers::popup_request_handler::LOGIN_STEP_TTL_MS;
+use crate::store::{LoginStep, SessionAction, SessionActionWrapper, SessionStore};
+
+const MAX_SUGGESTIONS: usize = 6;
+
+lazy_static! {
+    //the suggestions last shown, by the text the address bar holds once one of them is picked
+    static ref SUGGESTIONS: Mutex<HashMap<String, (String, String)>> = Mutex::new(HashMap::new());
+}
+
+//accounts of the logged in stores whose urls, username or path contain the query
+fn search_accounts(query: &str) -> Vec<(String, Account)> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let contains = |text: &str| text.to_lowercase().contains(&query);
+    let dispatch = Dispatch::<SessionStore>::new();
+    let stores = dispatch.get().stores.clone();
+    let stores = stores.borrow();
+    let mut found = stores
+        .values()
+        .filter(|store| store.verified)
+        .flat_map(|store| {
+            store
+                .accounts
+                .borrow()
+                .iter()
+                .filter(|account| {
+                    contains(&account.username)
+                        || account.path.as_deref().is_some_and(contains)
+                        || account
+                            .all_urls()
+                            .iter()
+                            .any(|account_url| contains(&account_url.url))
+                })
+                .map(|account| (store.store_id.clone(), account.deref().clone()))
+                .collect::<Vec<(String, Account)>>()
+        })
+        .collect::<Vec<(String, Account)>>();
+    found.sort_by(|(_, a), (_, b)| (&a.domain, &a.username).cmp(&(&b.domain, &b.username)));
+    found
+}
+
+fn account_site(account: &Account) -> String {
+    account
+        .all_urls()
+        .first()
+        .map(|account_url| account_url.url.clone())
+        .unwrap_or_default()
+}
+
+//chrome reads suggestion descriptions as xml
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+pub fn create_omnibox_input_changed_listener() -> Closure<dyn Fn(String, Function)> {
+    Closure::<dyn Fn(String, Function)>::new(move |text: String, suggest: Function| {
+        let mut suggestions = SUGGESTIONS.lock().unwrap();
+        suggestions.clear();
+        let results = search_accounts(&text)
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(store_id, account)| {
+                let site = account_site(&account);
+                let mut content = format!("{} on {}", account.username, site);
+                if suggestions.contains_key(&content) {
+                    content = format!("{} ({})", content, store_id);
+                }
+                let description =
+                    escape_xml(&format!("{} - {} ({})", site, account.username, store_id));
+                suggestions.insert(content.clone(), (store_id, account.id.clone()));
+                json!({"content": content, "description": description})
+            })
+            .collect::<Vec<serde_json::Value>>();
+        let _ = suggest.call1(
+            &JsValue::NULL,
+            &<JsValue as JsValueSerdeExt>::from_serde(&results).unwrap(),
+        );
+    })
+}
+
+//enter opens the account's site in the current tab and fills it, alt+enter copies its password.
+//text entered without picking a suggestion goes to the first account it matches
+pub fn create_omnibox_input_entered_listener() -> Closure<dyn Fn(String, String)> {
+    Closure::<dyn Fn(String, String)>::new(move |text: String, disposition: String| {
+        let picked = SUGGESTIONS.lock().unwrap().get(&text).cloned();
+        let account = match picked {
+            Some((store_id, account_id)) => {
+                find_account(&store_id, &account_id).map(|account| (store_id, account))
+            }
+            None => search_accounts(&text).into_iter().next(),
+        };
+        let Some((store_id, account)) = account else {
+            warn!("no account matches {:?}", text);
+            return;
+        };
+        wasm_bindgen_futures::spawn_local(async move {
+            match disposition.as_str() {
+                "newForegroundTab" => copy_password(account).await,
+                "newBackgroundTab" => open_account(store_id, account, true).await,
+                _ => open_account(store_id, account, false).await,
+            }
+        });
+    })
+}
+
+fn remember_account(tab_id: i32, store_id: String, host: String, account_id: String) {
+    Dispatch::<SessionStore>::new().apply(SessionActionWrapper {
+        meta: None,
+        action: SessionAction::LoginStepRemembered(
+            tab_id,
+            LoginStep {
+                store_id: Some(store_id),
+                domain: host,
+                account_id,
+                expires_at: js_sys::Date::now() + LOGIN_STEP_TTL_MS,
+            },
+        ),
+    });
+}
+
+//the account is left as the login step of the tab, so the content script fills it in once the
+//login form of the site shows up
+async fn open_account(store_id: String, account: Account, in_background: bool) {
+    let url = account
+        .all_urls()
+        .into_iter()
+        .filter(|account_url| !matches!(account_url.match_mode, MatchMode::Regex(_)))
+        .find_map(|account_url| parse_saved_url(&account_url.url));
+    let Some((url, host)) = url.and_then(|url| {
+        url.host_str()
+            .map(|host| (url.to_string(), host.to_owned()))
+    }) else {
+        warn!("no url to open for account {}", account.id);
+        return;
+    };
+    if in_background {
+        let tab = JsFuture::from(
+            chrome.tabs().create(
+                <JsValue as JsValueSerdeExt>::from_serde(&json!({"url": url, "active": false}))
+                    .unwrap(),
+            ),
+        )
+        .await
+        .ok()
+        .and_then(|tab| <JsValue as JsValueSerdeExt>::into_serde::<serde_json::Value>(&tab).ok());
+        if let Some(tab_id) = tab.and_then(|tab| tab.get("id")?.as_i64()) {
+            remember_account(tab_id as i32, store_id, host, account.id);
+        }
+    } else if let Some(tab_id) = active_tab_id().await {
+        remember_account(tab_id, store_id, host, account.id);
+        let _ = JsFuture::from(chrome.tabs().update(
+            tab_id,
+            <JsValue as JsValueSerdeExt>::from_serde(&json!({ "url": url })).unwrap(),
+        ))
+        .await;
+    }
+}
+
+//copied by the service worker itself, as the page open in the current tab may belong to anyone
+async fn copy_password(account: Account) {
+    let Some(password) = account.get_password() else {
+        warn!("account {} has no password to copy", account.id);
+        return;
+    };
+    copy_from_service_worker(password.expose_secret().clone()).await;
+}
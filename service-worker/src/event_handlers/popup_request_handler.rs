@@ -1,13 +1,25 @@
+use crate::api::clipboard::schedule_clipboard_clear;
+use crate::api::extension_api::active_tab_id;
+use crate::api::extension_api::message_login_form_frames;
 use crate::api::extension_api::whisper_session_event;
+use crate::api::native_connection::track_native_request;
+use crate::store::ContentFrame;
+use crate::store::LoginStep;
+use crate::store::PendingCredential;
 use crate::store::SessionAction;
 use crate::store::SessionActionWrapper;
 use crate::store::SessionStore;
 use crate::store::StoreData;
+use crate::store::CONTENT_FRAMES;
 use crate::store::EXTENSION_PORT;
 use crate::store::LISTENER_PORT;
+use crate::store::PENDING_CREDENTIALS;
 use crate::store::PORT_ID_MAP;
-use crate::store::REQUEST_MAP;
 use browser_rpass::js_binding::extension_api::*;
+use browser_rpass::payload::{
+    FailurePayload, FetchPayload, InitPayload, LoginStepPayload, PendingCredentialPayload,
+};
+use browser_rpass::store::load_equivalent_domains;
 use browser_rpass::types::Account;
 use browser_rpass::types::StateStoreStatus;
 use browser_rpass::types::StorageStatus;
@@ -23,6 +35,7 @@ use browser_rpass::response::*;
 use gloo_utils::format::JsValueSerdeExt;
 use serde_json;
 use serde_json::json;
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::ops::Deref;
@@ -52,6 +65,26 @@ pub fn handle_request_from_popup(
                 .insert(acknowledgement, extension_port.name());
         }
         async move {
+            if let RequestEnum::LoginStep(login_step_request) = &request {
+                handle_login_step_request(login_step_request, &extension_port);
+                return;
+            }
+            if let RequestEnum::PendingCredential(pending_credential_request) = &request {
+                handle_pending_credential_request(pending_credential_request, &extension_port);
+                return;
+            }
+            if let RequestEnum::ClipboardClear(clipboard_clear_request) = &request {
+                schedule_clipboard_clear(clipboard_clear_request.copied.clone());
+                return;
+            }
+            if let RequestEnum::FrameStatus(frame_status_request) = &request {
+                handle_frame_status_request(frame_status_request, &extension_port);
+                return;
+            }
+            if let RequestEnum::Fill(fill_request) = &request {
+                handle_fill_request(fill_request, &extension_port).await;
+                return;
+            }
             if let Some(native_port) = NATIVE_PORT.lock().borrow().as_ref() {
                 let native_request_acknowledgement: String = {
                     if let Some(ref acknowledgement) = request.get_acknowledgement() {
@@ -70,7 +103,17 @@ pub fn handle_request_from_popup(
                         .unwrap_or(false)
                     {
                         let stores = dispatch.get().stores.clone();
-                        let mut data = HashMap::new();
+                        let unlocked_store_ids = stores
+                            .borrow()
+                            .values()
+                            .filter(|store| store.verified)
+                            .map(|store| store.store_id.clone())
+                            .collect::<Vec<String>>();
+                        let mut payload = InitPayload {
+                            store_ids: unlocked_store_ids.clone(),
+                            unlocked_store_ids,
+                            ..Default::default()
+                        };
                         if let Some(default_store_id) =
                             dispatch.get().default_store.clone().borrow().deref()
                         {
@@ -86,36 +129,15 @@ pub fn handle_request_from_popup(
                                     set.insert(extension_port.name());
                                     set
                                 });
-                            if stores.borrow().get(default_store_id).is_none() {
-                                data.insert(
-                                    DataFieldType::DefaultStoreAvailable,
-                                    serde_json::to_value(false).unwrap(),
-                                );
-                            } else {
-                                data.insert(
-                                    DataFieldType::DefaultStoreID,
-                                    serde_json::to_value(default_store_id).unwrap(),
-                                );
-                                data.insert(
-                                    DataFieldType::DefaultStoreAvailable,
-                                    serde_json::to_value(true).unwrap(),
-                                );
+                            if stores.borrow().get(default_store_id).is_some() {
+                                payload.default_store_id = Some(default_store_id.clone());
+                                payload.default_store_available = true;
                             }
-                        } else {
-                            data.insert(
-                                DataFieldType::DefaultStoreAvailable,
-                                serde_json::to_value(false).unwrap(),
-                            );
                         }
-                        let mut payload = HashMap::new();
-                        payload.insert(
-                            DataFieldType::Data,
-                            serde_json::to_value(data.clone()).unwrap(),
-                        );
                         let session_event = SessionEvent {
                             store_id_index: init_request.get_store_id(),
-                            event_type: SessionEventType::Init(payload),
-                            data: Some(data),
+                            event_type: SessionEventType::Init(payload.into()),
+                            data: None,
                             header: None,
                             resource: None,
                             is_global: false,
@@ -152,20 +174,24 @@ pub fn handle_request_from_popup(
                                     .keys()
                                     .cloned()
                                     .collect::<Vec<String>>();
-                                let store_ids = serde_json::to_value(store_ids).unwrap();
                                 let keys = dispatch.get().keys.borrow().clone();
-                                let mut payload = HashMap::new();
-                                payload.insert(
-                                    DataFieldType::StoreIDList,
-                                    serde_json::to_value(store_ids.clone()).unwrap(),
-                                );
-                                payload.insert(
-                                    DataFieldType::Keys,
-                                    serde_json::to_value(keys).unwrap(),
-                                );
+                                let unlocked_store_ids = dispatch
+                                    .get()
+                                    .stores
+                                    .borrow()
+                                    .values()
+                                    .filter(|store| store.verified)
+                                    .map(|store| store.store_id.clone())
+                                    .collect::<Vec<String>>();
+                                let payload = InitPayload {
+                                    store_ids,
+                                    keys,
+                                    unlocked_store_ids,
+                                    ..Default::default()
+                                };
                                 let session_event = SessionEvent {
                                     store_id_index: init_request.get_store_id(),
-                                    event_type: SessionEventType::Init(payload),
+                                    event_type: SessionEventType::Init(payload.into()),
                                     data: None,
                                     header: None,
                                     resource: None,
@@ -187,6 +213,19 @@ pub fn handle_request_from_popup(
                     request.set_header(header);
                     match request.clone() {
                         RequestEnum::Get(get_request) => match get_request.resource.clone() {
+                            Resource::Totp => {
+                                track_native_request(
+                                    native_request_acknowledgement.clone(),
+                                    request.clone(),
+                                );
+                                PORT_ID_MAP.lock().unwrap().insert(
+                                    session_event_acknowledgement.clone(),
+                                    extension_port.name(),
+                                );
+                                native_port.post_message(
+                                    <JsValue as JsValueSerdeExt>::from_serde(&get_request).unwrap(),
+                                );
+                            }
                             _ => {
                                 todo!();
                             }
@@ -199,10 +238,10 @@ pub fn handle_request_from_popup(
                             }
                         }
                         RequestEnum::Logout(logout_request) => {
-                            REQUEST_MAP
-                                .lock()
-                                .unwrap()
-                                .insert(native_request_acknowledgement.clone(), request.clone());
+                            track_native_request(
+                                native_request_acknowledgement.clone(),
+                                request.clone(),
+                            );
                             PORT_ID_MAP.lock().unwrap().insert(
                                 session_event_acknowledgement.clone(),
                                 extension_port.name(),
@@ -212,10 +251,10 @@ pub fn handle_request_from_popup(
                             );
                         }
                         RequestEnum::Create(create_request) => {
-                            REQUEST_MAP
-                                .lock()
-                                .unwrap()
-                                .insert(native_request_acknowledgement.clone(), request.clone());
+                            track_native_request(
+                                native_request_acknowledgement.clone(),
+                                request.clone(),
+                            );
                             PORT_ID_MAP.lock().unwrap().insert(
                                 session_event_acknowledgement.clone(),
                                 extension_port.name(),
@@ -225,10 +264,10 @@ pub fn handle_request_from_popup(
                             );
                         }
                         RequestEnum::CreateStore(create_store_request) => {
-                            REQUEST_MAP
-                                .lock()
-                                .unwrap()
-                                .insert(native_request_acknowledgement.clone(), request.clone());
+                            track_native_request(
+                                native_request_acknowledgement.clone(),
+                                request.clone(),
+                            );
                             PORT_ID_MAP.lock().unwrap().insert(
                                 session_event_acknowledgement.clone(),
                                 extension_port.name(),
@@ -239,10 +278,10 @@ pub fn handle_request_from_popup(
                             );
                         }
                         RequestEnum::DeleteStore(delete_store_request) => {
-                            REQUEST_MAP
-                                .lock()
-                                .unwrap()
-                                .insert(native_request_acknowledgement.clone(), request.clone());
+                            track_native_request(
+                                native_request_acknowledgement.clone(),
+                                request.clone(),
+                            );
                             PORT_ID_MAP.lock().unwrap().insert(
                                 session_event_acknowledgement.clone(),
                                 extension_port.name(),
@@ -253,10 +292,10 @@ pub fn handle_request_from_popup(
                             );
                         }
                         RequestEnum::Edit(edit_request) => {
-                            REQUEST_MAP
-                                .lock()
-                                .unwrap()
-                                .insert(native_request_acknowledgement.clone(), request.clone());
+                            track_native_request(
+                                native_request_acknowledgement.clone(),
+                                request.clone(),
+                            );
                             PORT_ID_MAP.lock().unwrap().insert(
                                 session_event_acknowledgement.clone(),
                                 extension_port.name(),
@@ -266,10 +305,10 @@ pub fn handle_request_from_popup(
                             );
                         }
                         RequestEnum::Delete(delete_request) => {
-                            REQUEST_MAP
-                                .lock()
-                                .unwrap()
-                                .insert(native_request_acknowledgement.clone(), request.clone());
+                            track_native_request(
+                                native_request_acknowledgement.clone(),
+                                request.clone(),
+                            );
                             PORT_ID_MAP.lock().unwrap().insert(
                                 session_event_acknowledgement.clone(),
                                 extension_port.name(),
@@ -278,6 +317,35 @@ pub fn handle_request_from_popup(
                                 <JsValue as JsValueSerdeExt>::from_serde(&delete_request).unwrap(),
                             );
                         }
+                        RequestEnum::GeneratePassword(generate_password_request) => {
+                            track_native_request(
+                                native_request_acknowledgement.clone(),
+                                request.clone(),
+                            );
+                            PORT_ID_MAP.lock().unwrap().insert(
+                                session_event_acknowledgement.clone(),
+                                extension_port.name(),
+                            );
+                            native_port.post_message(
+                                <JsValue as JsValueSerdeExt>::from_serde(
+                                    &generate_password_request,
+                                )
+                                .unwrap(),
+                            );
+                        }
+                        RequestEnum::Audit(audit_request) => {
+                            track_native_request(
+                                native_request_acknowledgement.clone(),
+                                request.clone(),
+                            );
+                            PORT_ID_MAP.lock().unwrap().insert(
+                                session_event_acknowledgement.clone(),
+                                extension_port.name(),
+                            );
+                            native_port.post_message(
+                                <JsValue as JsValueSerdeExt>::from_serde(&audit_request).unwrap(),
+                            );
+                        }
                         RequestEnum::Fetch(fetch_request) => {
                             let meta = Some(json!({"requester_port_id":extension_port.name()}));
                             let data = dispatch.get().stores.clone();
@@ -291,6 +359,8 @@ pub fn handle_request_from_popup(
                                         store_id: fetch_request.store_id.clone().unwrap(),
                                         signing_key: None,
                                         verified: false,
+                                        password_policy: None,
+                                        password_policy_error: None,
                                     }
                                 })
                                 .storage_status
@@ -320,7 +390,7 @@ pub fn handle_request_from_popup(
                                         session_event_acknowledgement.clone(),
                                         extension_port.name(),
                                     );
-                                    REQUEST_MAP.lock().unwrap().insert(
+                                    track_native_request(
                                         native_request_acknowledgement.clone(),
                                         request.clone(),
                                     );
@@ -333,29 +403,36 @@ pub fn handle_request_from_popup(
                                 StorageStatus::Loaded => {
                                     let resource = fetch_request.resource.clone();
                                     let store = dispatch.get().stores.clone();
-                                    let accounts = store
+                                    let store_data = store
                                         .borrow()
                                         .get(&fetch_request.store_id.clone().unwrap())
                                         .unwrap()
-                                        .accounts
                                         .clone();
-                                    let mut data = HashMap::new();
-                                    data.insert(
-                                        DataFieldType::Data,
-                                        serde_json::to_value(
-                                            accounts
-                                                .borrow()
-                                                .iter()
-                                                .map(|v| (**v).clone())
-                                                .collect::<Vec<Account>>(),
-                                        )
-                                        .unwrap(),
-                                    );
+                                    let payload = FetchPayload {
+                                        accounts: store_data
+                                            .accounts
+                                            .borrow()
+                                            .iter()
+                                            .map(|v| (**v).clone())
+                                            .collect::<Vec<Account>>(),
+                                        sub_stores: Vec::new(),
+                                        password_policy: store_data.password_policy,
+                                        password_policy_error: store_data.password_policy_error,
+                                    };
+                                    let meta = meta.map(|mut meta| {
+                                        if let Some(custom_field_prefix) =
+                                            dispatch.get().custom_field_prefix.clone()
+                                        {
+                                            meta["custom_field_prefix"] =
+                                                custom_field_prefix.into();
+                                        }
+                                        meta
+                                    });
                                     let mock_session_event = {
                                         SessionEvent {
                                             store_id_index: fetch_request.store_id.clone(),
-                                            event_type: SessionEventType::Refreshed,
-                                            data: Some(data),
+                                            event_type: SessionEventType::Refreshed(payload.into()),
+                                            data: None,
                                             header: meta,
                                             resource: Some(vec![resource]),
                                             is_global: true,
@@ -364,17 +441,7 @@ pub fn handle_request_from_popup(
                                             ),
                                         }
                                     };
-                                    let message = MessageEnum::Message(
-                                        RequestEnum::create_session_event_request(
-                                            None,
-                                            mock_session_event.clone(),
-                                            fetch_request.store_id.clone(),
-                                            None,
-                                        ),
-                                    );
-                                    extension_port.post_message(
-                                        <JsValue as JsValueSerdeExt>::from_serde(&message).unwrap(),
-                                    );
+                                    whisper_session_event(mock_session_event, &extension_port);
                                 }
                                 StorageStatus::Error => {
                                     todo!();
@@ -395,10 +462,10 @@ pub fn handle_request_from_popup(
                                     set
                                 });
 
-                            REQUEST_MAP
-                                .lock()
-                                .unwrap()
-                                .insert(native_request_acknowledgement.clone(), request.clone());
+                            track_native_request(
+                                native_request_acknowledgement.clone(),
+                                request.clone(),
+                            );
                             PORT_ID_MAP.lock().unwrap().insert(
                                 session_event_acknowledgement.clone(),
                                 extension_port.name(),
@@ -442,6 +509,236 @@ pub fn handle_request_from_popup(
         }
     });
 }
+//multi-step logins only need to carry the picked account over to the next page of the same tab,
+//so the step is short lived and answered right here without asking the native app
+pub const LOGIN_STEP_TTL_MS: f64 = 2.0 * 60.0 * 1000.0;
+fn handle_login_step_request(request: &LoginStepRequest, extension_port: &Port) {
+    let dispatch = Dispatch::<SessionStore>::new();
+    let tab_id = extension_port
+        .sender()
+        .and_then(|sender| sender.tab())
+        .map(|tab| tab.id());
+    let mut login_step = None;
+    let mut error = None;
+    let status = match (tab_id, &request.account_id) {
+        (None, _) => {
+            warn!(
+                "login step requested from a port without a tab: {:?}",
+                request
+            );
+            error =
+                Some(FailurePayload::new("login step requested from a port without a tab").into());
+            Status::Failure
+        }
+        (Some(tab_id), Some(account_id)) => {
+            dispatch.apply(SessionActionWrapper {
+                meta: None,
+                action: SessionAction::LoginStepRemembered(
+                    tab_id,
+                    LoginStep {
+                        store_id: request.store_id.clone(),
+                        domain: request.domain.clone(),
+                        account_id: account_id.clone(),
+                        expires_at: js_sys::Date::now() + LOGIN_STEP_TTL_MS,
+                    },
+                ),
+            });
+            login_step = Some(
+                LoginStepPayload {
+                    account_id: Some(account_id.clone()),
+                }
+                .into(),
+            );
+            Status::Success
+        }
+        (Some(tab_id), None) => {
+            let now = js_sys::Date::now();
+            let remembered = dispatch
+                .get()
+                .login_steps
+                .borrow()
+                .get(&tab_id)
+                //content scripts recall without a store, as any unlocked store may hold the account
+                .filter(|step| {
+                    !step.is_expired(now)
+                        && step.domain == request.domain
+                        && (request.store_id.is_none() || step.store_id == request.store_id)
+                })
+                .map(|step| step.account_id.clone());
+            login_step = Some(
+                LoginStepPayload {
+                    account_id: remembered,
+                }
+                .into(),
+            );
+            Status::Success
+        }
+    };
+    let response = ResponseEnum::LoginStepResponse(LoginStepResponse {
+        store_id: request.store_id.clone(),
+        acknowledgement: request.acknowledgement.clone(),
+        login_step,
+        error,
+        status,
+        meta: None,
+    });
+    extension_port.post_message(
+        <JsValue as JsValueSerdeExt>::from_serde(&MessageEnum::Response(response)).unwrap(),
+    );
+}
+//the page that follows a form submission is usually a new document, so the captured
+//credentials are only handed out once and dropped if nobody asks for them in time
+const PENDING_CREDENTIAL_TTL_MS: f64 = 60.0 * 1000.0;
+fn handle_pending_credential_request(request: &PendingCredentialRequest, extension_port: &Port) {
+    let tab_id = extension_port
+        .sender()
+        .and_then(|sender| sender.tab())
+        .map(|tab| tab.id());
+    let now = js_sys::Date::now();
+    let mut credential = None;
+    let mut error = None;
+    let status = match (tab_id, &request.password) {
+        (None, _) => {
+            warn!("pending credential requested from a port without a tab");
+            error = Some(
+                FailurePayload::new("pending credential requested from a port without a tab")
+                    .into(),
+            );
+            Status::Failure
+        }
+        (Some(tab_id), Some(password)) => {
+            let mut pending_credentials = PENDING_CREDENTIALS.lock().unwrap();
+            pending_credentials.retain(|_, credential| credential.expires_at > now);
+            pending_credentials.insert(
+                tab_id,
+                PendingCredential {
+                    store_id: request.store_id.clone(),
+                    domain: request.domain.clone(),
+                    username: request.username.clone(),
+                    password: password.clone(),
+                    account_id: request.account_id.clone(),
+                    expires_at: now + PENDING_CREDENTIAL_TTL_MS,
+                },
+            );
+            Status::Success
+        }
+        (Some(tab_id), None) => {
+            let mut pending_credentials = PENDING_CREDENTIALS.lock().unwrap();
+            pending_credentials.retain(|_, credential| credential.expires_at > now);
+            //every frame of the next page asks, so only a frame of the same site takes it
+            let pending_credential = match pending_credentials.entry(tab_id) {
+                Entry::Occupied(entry) if entry.get().domain == request.domain => {
+                    Some(entry.remove())
+                }
+                _ => None,
+            };
+            drop(pending_credentials);
+            //the account changed may be in another unlocked store than the default one
+            credential = pending_credential.map(|pending_credential| {
+                PendingCredentialPayload {
+                    domain: pending_credential.domain,
+                    username: pending_credential.username,
+                    password: pending_credential.password,
+                    account_id: pending_credential.account_id,
+                    store_id: pending_credential.store_id,
+                }
+                .into()
+            });
+            Status::Success
+        }
+    };
+    let response = ResponseEnum::PendingCredentialResponse(PendingCredentialResponse {
+        store_id: request.store_id.clone(),
+        acknowledgement: request.acknowledgement.clone(),
+        credential,
+        error,
+        status,
+        meta: None,
+    });
+    extension_port.post_message(
+        <JsValue as JsValueSerdeExt>::from_serde(&MessageEnum::Response(response)).unwrap(),
+    );
+}
+fn handle_frame_status_request(request: &FrameStatusRequest, extension_port: &Port) {
+    let Some(sender) = extension_port.sender() else {
+        return;
+    };
+    let (Some(tab_id), Some(frame_id)) = (sender.tab().map(|tab| tab.id()), sender.frame_id())
+    else {
+        warn!("frame status sent from a port without a tab: {:?}", request);
+        return;
+    };
+    CONTENT_FRAMES
+        .lock()
+        .unwrap()
+        .entry(tab_id)
+        .or_default()
+        .insert(
+            frame_id,
+            ContentFrame {
+                port_name: extension_port.name(),
+                url: sender.url(),
+                has_login_form: request.has_login_form,
+            },
+        );
+}
+//fills are only forwarded to frames whose own url matches the account, the same way each frame is
+//only sent the accounts of its own site, so a cross-origin iframe never gets those of the page
+//embedding it
+async fn handle_fill_request(request: &FillRequest, extension_port: &Port) {
+    let tab_id = match extension_port
+        .sender()
+        .and_then(|sender| sender.tab())
+        .map(|tab| tab.id())
+    {
+        Some(tab_id) => Some(tab_id),
+        None => active_tab_id().await,
+    };
+    let account = {
+        let dispatch = Dispatch::<SessionStore>::new();
+        let stores = dispatch.get().stores.clone();
+        let stores = stores.borrow();
+        request
+            .store_id
+            .as_ref()
+            .and_then(|store_id| stores.get(store_id))
+            .and_then(|store| {
+                store
+                    .accounts
+                    .borrow()
+                    .iter()
+                    .find(|account| account.id == request.account_id)
+                    .map(|account| account.deref().clone())
+            })
+    };
+    let mut filled_frames = 0;
+    if let (Some(tab_id), Some(account)) = (tab_id, account) {
+        let equivalent_domains = load_equivalent_domains().await;
+        let mut fill_request = request.clone();
+        fill_request.account = Some(account.clone());
+        filled_frames = message_login_form_frames(
+            tab_id,
+            Some(&account),
+            &equivalent_domains,
+            RequestEnum::Fill(fill_request),
+        );
+    }
+    let response = ResponseEnum::FillResponse(FillResponse {
+        store_id: request.store_id.clone(),
+        acknowledgement: request.acknowledgement.clone(),
+        error: (filled_frames == 0)
+            .then(|| FailurePayload::new("no login form of the account's site to fill").into()),
+        status: if filled_frames > 0 {
+            Status::Success
+        } else {
+            Status::Failure
+        },
+        meta: None,
+    });
+    extension_port.post_message(
+        <JsValue as JsValueSerdeExt>::from_serde(&MessageEnum::Response(response)).unwrap(),
+    );
+}
 pub fn create_request_listener() -> Closure<dyn Fn(Port)> {
     let native_port = NATIVE_PORT.lock().borrow().clone();
     let on_connect_with_popup_cb = Closure::<dyn Fn(Port)>::new(move |port: Port| {
@@ -467,6 +764,10 @@ pub fn create_request_listener() -> Closure<dyn Fn(Port)> {
                 store_id_with_no_listeners.iter().for_each(|store_id| {
                     LISTENER_PORT.lock().unwrap().remove(store_id);
                 });
+                CONTENT_FRAMES.lock().unwrap().retain(|_, frames| {
+                    frames.retain(|_, frame| frame.port_name != port.name());
+                    !frames.is_empty()
+                });
                 EXTENSION_PORT.lock().unwrap().remove(&port.name());
             })
             .into_js_value(),
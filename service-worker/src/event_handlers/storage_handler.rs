@@ -0,0 +1,20 @@
+use browser_rpass::store::EQUIVALENT_DOMAINS_KEY;
+use wasm_bindgen::prelude::Closure;
+use wasm_bindgen::JsValue;
+
+use crate::api::extension_api::refresh_equivalent_domains;
+
+//the equivalent domains are changed from the popup, and the copy the service worker scopes
+//events with has to follow
+pub fn create_storage_changed_listener() -> Closure<dyn Fn(JsValue, String)> {
+    Closure::<dyn Fn(JsValue, String)>::new(move |changes: JsValue, area_name: String| {
+        if area_name != "local" {
+            return;
+        }
+        if js_sys::Reflect::has(&changes, &JsValue::from_str(EQUIVALENT_DOMAINS_KEY))
+            .unwrap_or(false)
+        {
+            wasm_bindgen_futures::spawn_local(refresh_equivalent_domains());
+        }
+    })
+}
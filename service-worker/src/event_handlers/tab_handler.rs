@@ -0,0 +1,49 @@
+use browser_rpass::js_binding::extension_api::*;
+use gloo_utils::format::JsValueSerdeExt;
+#[allow(unused_imports)]
+use log::*;
+use wasm_bindgen::prelude::Closure;
+use wasm_bindgen::JsValue;
+
+use crate::api::badge::{forget_tab, set_tab_url};
+
+pub fn create_tab_updated_listener() -> Closure<dyn Fn(i32, JsValue, JsValue)> {
+    Closure::<dyn Fn(i32, JsValue, JsValue)>::new(
+        move |tab_id: i32, change_info: JsValue, _tab: JsValue| {
+            let change_info: serde_json::Value =
+                <JsValue as JsValueSerdeExt>::into_serde(&change_info).unwrap_or_default();
+            if let Some(url) = change_info.get("url").and_then(|url| url.as_str()) {
+                set_tab_url(tab_id, url.to_owned());
+            }
+        },
+    )
+}
+
+//tabs opened before the service worker started are picked up once they are switched to
+pub fn create_tab_activated_listener() -> Closure<dyn Fn(JsValue)> {
+    Closure::<dyn Fn(JsValue)>::new(move |active_info: JsValue| {
+        let active_info: serde_json::Value =
+            <JsValue as JsValueSerdeExt>::into_serde(&active_info).unwrap_or_default();
+        let Some(tab_id) = active_info.get("tabId").and_then(|tab_id| tab_id.as_i64()) else {
+            return;
+        };
+        let tab_id = tab_id as i32;
+        wasm_bindgen_futures::spawn_local(async move {
+            let Ok(tab) = wasm_bindgen_futures::JsFuture::from(chrome.tabs().get(tab_id)).await
+            else {
+                return;
+            };
+            let tab: serde_json::Value =
+                <JsValue as JsValueSerdeExt>::into_serde(&tab).unwrap_or_default();
+            if let Some(url) = tab.get("url").and_then(|url| url.as_str()) {
+                set_tab_url(tab_id, url.to_owned());
+            }
+        });
+    })
+}
+
+pub fn create_tab_removed_listener() -> Closure<dyn Fn(i32)> {
+    Closure::<dyn Fn(i32)>::new(move |tab_id: i32| {
+        forget_tab(tab_id);
+    })
+}
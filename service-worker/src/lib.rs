@@ -3,13 +3,20 @@ pub use browser_rpass::js_binding::extension_api::chrome;
 pub use browser_rpass::request;
 pub use browser_rpass::request::RequestEnum;
 pub use browser_rpass::util::*;
+use event_handlers::alarm_handler::*;
+use event_handlers::command_handler::*;
+use event_handlers::context_menu_handler::*;
 use event_handlers::native_message_handler::*;
+use event_handlers::omnibox_handler::*;
 use event_handlers::popup_request_handler::*;
+use event_handlers::storage_handler::*;
+use event_handlers::tab_handler::*;
 pub use gloo_utils::format::JsValueSerdeExt;
 use std::panic;
 use store::SessionStore;
 pub use store::NATIVE_PORT;
 use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::JsValue;
 use yewdux::dispatch::Dispatch;
 mod store;
 pub use browser_rpass::types::*;
@@ -35,6 +42,17 @@ pub async fn main() {
     panic::set_hook(Box::new(console_error_panic_hook::hook));
     init_log();
     trace!("service worker starts");
+    //added before the first await, so the alarm that started the service worker is not missed
+    chrome
+        .alarms()
+        .on_alarm()
+        .add_listener(create_alarm_listener().into_js_value());
+    chrome
+        .storage()
+        .on_changed()
+        .add_listener(create_storage_changed_listener().into_js_value());
+    //menus outlive the service worker, so whatever an earlier instance created is replaced
+    api::context_menu::rebuild_context_menus(Vec::new()).await;
 
     //Currently load the session store from the extension session storage does not make any difference since
     //1. Service worker does not die out (extension message port will get created whenever the
@@ -43,7 +61,9 @@ pub async fn main() {
     //   starts, which means the loaded state of the store is empty in session storage anyway
     //
     //However, we might want to persist the session store state in the future, so we keep this.
-    //Because we are using session store, it should be safe to save data in it.
+    //Secrets of the accounts are not saved in plain text, see `SessionStore::save_session`.
+    //needed before the first content script connects, to scope the accounts it is sent
+    api::extension_api::refresh_equivalent_domains().await;
     let saved_state = SessionStore::load().await;
     if let Some(parsed_state) = saved_state {
         Dispatch::<SessionStore>::new().set(parsed_state);
@@ -53,4 +73,38 @@ pub async fn main() {
         .runtime()
         .on_connect()
         .add_listener(create_request_listener().into_js_value());
+    chrome
+        .commands()
+        .on_command()
+        .add_listener(create_command_listener().into_js_value());
+    chrome
+        .context_menus()
+        .on_clicked()
+        .add_listener(create_context_menu_listener().into_js_value());
+    chrome
+        .tabs()
+        .on_updated()
+        .add_listener(create_tab_updated_listener().into_js_value());
+    chrome
+        .tabs()
+        .on_activated()
+        .add_listener(create_tab_activated_listener().into_js_value());
+    chrome
+        .tabs()
+        .on_removed()
+        .add_listener(create_tab_removed_listener().into_js_value());
+    chrome.omnibox().set_default_suggestion(
+        <JsValue as JsValueSerdeExt>::from_serde(
+            &serde_json::json!({"description": "Search the accounts of the logged in stores"}),
+        )
+        .unwrap(),
+    );
+    chrome
+        .omnibox()
+        .on_input_changed()
+        .add_listener(create_omnibox_input_changed_listener().into_js_value());
+    chrome
+        .omnibox()
+        .on_input_entered()
+        .add_listener(create_omnibox_input_entered_listener().into_js_value());
 }
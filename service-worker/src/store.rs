@@ -1,13 +1,25 @@
-use crate::event_handlers::native_message_handler::process_native_message;
+use crate::api::badge::{badge_accounts, refresh_badges};
+use crate::api::context_menu::{menu_accounts, rebuild_context_menus, MenuAccount};
+use crate::api::native_connection::connect_native_port;
 pub use crate::Resource;
 use crate::{api, StorageStatus};
-use browser_rpass::request::{DataFieldType, LoginRequest, RequestEnumTrait, SessionEventType};
+use browser_rpass::custom_field::CUSTOM_FIELD_TYPES_FIELD;
+use browser_rpass::password_generator::PasswordPolicy;
+use browser_rpass::payload::{
+    AccountPayload, DeletePayload, FailurePayload, InitPayload, LoginPayload, LogoutPayload,
+    StorePayload,
+};
+use browser_rpass::request::{
+    DataFieldType, LoginRequest, RequestEnumTrait, RequestTimeout, SessionEventType,
+};
 use browser_rpass::response::{
     CreateResponse, CreateStoreResponse, DeleteStoreResponse, EditResponse, FetchResponse,
     InitResponse, LogoutResponse, ResponseEnum,
 };
+use browser_rpass::session_crypto;
 use browser_rpass::store;
 use browser_rpass::types::*;
+use browser_rpass::url_match::URL_MATCH_FIELD;
 use gloo_utils::format::JsValueSerdeExt;
 use log::*;
 use parking_lot::ReentrantMutex;
@@ -16,8 +28,8 @@ use serde_json;
 use serde_json::{json, Value};
 use std::cell::RefCell;
 use std::collections::HashSet;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::{any::type_name, collections::HashMap, ops::Deref, rc::Rc, sync::Mutex};
-use wasm_bindgen::prelude::Closure;
 use wasm_bindgen::JsValue;
 use yewdux::dispatch::Dispatch;
 use yewdux::mrc::Mrc;
@@ -44,16 +56,19 @@ pub enum SessionAction {
     LogoutError(LogoutResponse),
     DataFetched(FetchResponse),
     DataLoading(String, Option<String>),
+    DataLoadingFailed(String),
+    RequestTimedOut(RequestEnum, u32),
     DataCreated(CreateResponse),
     StoreCreated(CreateStoreResponse),
     StoreCreationFailed(RequestEnum, ResponseEnum),
     StoreDeleted(DeleteStoreResponse),
     StoreDeletionFailed(RequestEnum, ResponseEnum),
     DataEdited(EditResponse),
-    DataDeleted(Resource, String, HashMap<DataFieldType, Value>),
+    DataDeleted(Resource, String),
     DataDeletionFailed(Resource, String),
-    DataCreationFailed(Resource, HashMap<DataFieldType, Value>, Option<RequestEnum>),
-    DataEditFailed(Resource, HashMap<DataFieldType, Value>, Option<RequestEnum>),
+    DataCreationFailed(Resource, FailurePayload, Option<RequestEnum>),
+    DataEditFailed(Resource, FailurePayload, Option<RequestEnum>),
+    LoginStepRemembered(i32, LoginStep),
 }
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SessionActionWrapper {
@@ -61,67 +76,24 @@ pub struct SessionActionWrapper {
     pub action: SessionAction,
 }
 
-fn native_port_disconnect_handler(_port: Port) {
-    {
-        if let Ok(mut borrowed) = NATIVE_PORT.lock().try_borrow_mut() {
-            *borrowed = None;
-        }
-    }
-    // need to add async task to receive repeated disconnect events.
-    // mostly to detect if native app is not configured properly
-    wasm_bindgen_futures::spawn_local(async move {
-        {
-            if let Ok(borrowed) = NATIVE_PORT.lock().try_borrow() {
-                if borrowed.is_none() {
-                    error!(
-                    "native port disconnected, but no port found. Likely native app not reachable."
-                );
-                    return;
-                }
-            }
-        }
-        let new_port = chrome.runtime().connect_native("rpass");
-        new_port.on_disconnect().add_listener(
-            Closure::<dyn Fn(Port)>::new(native_port_disconnect_handler).into_js_value(),
-        );
-        new_port.on_message().add_listener(
-            Closure::<dyn Fn(String)>::new(native_port_message_handler).into_js_value(),
-        );
-        #[allow(unused_mut)]
-        let mut init_config = HashMap::new();
-        let init_request = RequestEnum::create_init_request(init_config, None, None);
-        new_port.post_message(<JsValue as JsValueSerdeExt>::from_serde(&init_request).unwrap());
-        if let Ok(mut borrowed) = NATIVE_PORT.lock().try_borrow_mut() {
-            *borrowed = Some(new_port);
-        }
-    });
-}
-fn native_port_message_handler(msg: String) {
-    match serde_json::from_slice::<Value>(&msg.as_bytes()) {
-        Ok(parsed_json) => {
-            let _ = process_native_message(parsed_json, NATIVE_PORT.lock().borrow().as_ref(), None);
-        }
-        Err(e) => {
-            error!(
-                "error happend while parsing:{:?}. Error message: {:?}",
-                msg, e
-            );
+//breach check results of the native app are passed on to the popup along with the event
+fn with_breach_count(meta: Option<Value>, response_meta: &Option<Value>) -> Option<Value> {
+    let breach_count = response_meta
+        .as_ref()
+        .and_then(|response_meta| response_meta.get("breach_count"));
+    match breach_count {
+        Some(breach_count) => {
+            let mut meta = meta.unwrap_or(json!({}));
+            meta["breach_count"] = breach_count.clone();
+            Some(meta)
         }
+        None => meta,
     }
 }
+
 lazy_static! {
     pub static ref NATIVE_PORT: ReentrantMutex<RefCell<Option<Port>>> = {
-        let port = chrome.runtime().connect_native("rpass");
-        port.on_disconnect().add_listener(
-            Closure::<dyn Fn(Port)>::new(native_port_disconnect_handler).into_js_value(),
-        );
-        port.on_message().add_listener(
-            Closure::<dyn Fn(String)>::new(native_port_message_handler).into_js_value(),
-        );
-        #[allow(unused_mut)]
-        let mut init_config = HashMap::new();
-        let init_request = RequestEnum::create_init_request(init_config, None, None);
-        port.post_message(<JsValue as JsValueSerdeExt>::from_serde(&init_request).unwrap());
+        let (port, init_request) = connect_native_port();
         let dispatch = Dispatch::<SessionStore>::new();
         dispatch.apply(SessionActionWrapper {
             action: SessionAction::InitStarted(init_request),
@@ -143,6 +115,17 @@ lazy_static! {
 lazy_static! {
     pub static ref PORT_ID_MAP: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
 }
+//captured credentials hold a plaintext password, so they stay in memory instead of the
+//session store, which is saved to the browser's session storage
+lazy_static! {
+    pub static ref PENDING_CREDENTIALS: Mutex<HashMap<i32, PendingCredential>> =
+        Mutex::new(HashMap::new());
+}
+//content script ports of every frame, keyed by tab id and then frame id
+lazy_static! {
+    pub static ref CONTENT_FRAMES: Mutex<HashMap<i32, HashMap<i32, ContentFrame>>> =
+        Mutex::new(HashMap::new());
+}
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
 pub struct StoreData {
@@ -151,6 +134,40 @@ pub struct StoreData {
     pub signing_key: Option<String>,
     pub store_id: String,
     pub verified: bool,
+    pub password_policy: Option<PasswordPolicy>,
+    pub password_policy_error: Option<String>,
+}
+
+//account picked on the previous step of a multi-step login in a tab
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LoginStep {
+    pub store_id: Option<String>,
+    pub domain: String,
+    pub account_id: String,
+    pub expires_at: f64,
+}
+impl LoginStep {
+    pub fn is_expired(&self, now: f64) -> bool {
+        self.expires_at <= now
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentFrame {
+    pub port_name: String,
+    //the frame's own url, not the tab's, so a cross-origin iframe keeps its origin
+    pub url: Option<String>,
+    pub has_login_form: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingCredential {
+    pub store_id: Option<String>,
+    pub domain: String,
+    pub username: Option<String>,
+    pub password: String,
+    pub account_id: Option<String>,
+    pub expires_at: f64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
@@ -159,75 +176,174 @@ pub struct SessionStore {
     pub keys: Mrc<Vec<Key>>,
     pub default_store: Mrc<Option<String>>,
     pub status: StateStoreStatus,
+    #[serde(default)]
+    pub login_steps: Mrc<HashMap<i32, LoginStep>>,
+    //prefix the native app saves custom fields under, as told with the accounts it sends
+    #[serde(default)]
+    pub custom_field_prefix: Option<String>,
 }
 
 impl Store for SessionStore {
     fn new() -> Self {
         init_listener(StorageListener);
+        init_listener(ContextMenuListener::default());
+        init_listener(BadgeListener::default());
         SessionStore::default()
     }
     fn should_notify(&self, old: &Self) -> bool {
         self != old
     }
 }
+//secret fields of the saved state, keyed by store id and then account id
+type Secrets = HashMap<String, HashMap<String, serde_json::Map<String, Value>>>;
+//key of the saved state under which its secrets are kept, encrypted with the session key
+const SECRETS_KEY: &str = "secrets";
+//fields of an account that are saved in plain text. every other field, like the password, the
+//note or custom fields the user marked hidden, is encrypted
+const PLAIN_ACCOUNT_FIELDS: &[&str] = &["id", "username", "domain", "urls", "path", "has_totp"];
+//custom fields saved in plain text, which the native app may save under a prefix
+const PLAIN_CUSTOM_FIELDS: &[&str] = &[URL_MATCH_FIELD, CUSTOM_FIELD_TYPES_FIELD];
+lazy_static! {
+    //only the latest state is written, as saves finish out of order once encryption is awaited
+    static ref SAVE_GENERATION: AtomicU32 = AtomicU32::new(0);
+}
+
+fn is_plain_account_field(field: &str, custom_field_prefix: &str) -> bool {
+    PLAIN_ACCOUNT_FIELDS.contains(&field)
+        || PLAIN_CUSTOM_FIELDS.contains(&field)
+        || field
+            .strip_prefix(custom_field_prefix)
+            .is_some_and(|field| PLAIN_CUSTOM_FIELDS.contains(&field))
+}
+
+//takes every field that isn't known to be safe out of the accounts of a serialized state
+fn take_secrets(state: &mut Value) -> Secrets {
+    let mut secrets = Secrets::new();
+    let custom_field_prefix = state
+        .get("custom_field_prefix")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_owned();
+    let Some(stores) = state.get_mut("stores").and_then(Value::as_object_mut) else {
+        return secrets;
+    };
+    for (store_id, store_data) in stores.iter_mut() {
+        let Some(accounts) = store_data.get_mut("accounts").and_then(Value::as_array_mut) else {
+            continue;
+        };
+        //secrets are keyed by account id, so accounts without one are not saved
+        accounts.retain(|account| account.get("id").and_then(Value::as_str).is_some());
+        let store_secrets = secrets.entry(store_id.clone()).or_default();
+        for account in accounts.iter_mut() {
+            let Some(account) = account.as_object_mut() else {
+                continue;
+            };
+            let Some(id) = account.get("id").and_then(Value::as_str).map(str::to_owned) else {
+                continue;
+            };
+            let secret_fields = account
+                .keys()
+                .filter(|field| !is_plain_account_field(field, &custom_field_prefix))
+                .cloned()
+                .collect::<Vec<String>>();
+            let account_secrets = secret_fields
+                .into_iter()
+                .filter_map(|field| account.remove_entry(&field))
+                .collect::<serde_json::Map<String, Value>>();
+            store_secrets.insert(id, account_secrets);
+        }
+    }
+    secrets
+}
+
+//puts the secrets back into a serialized state. stores whose secrets are gone, as they were saved
+//in an earlier browser session, are marked for fetching again
+fn restore_secrets(state: &mut Value, secrets: Option<Secrets>) {
+    let Some(stores) = state.get_mut("stores").and_then(Value::as_object_mut) else {
+        return;
+    };
+    for (store_id, store_data) in stores.iter_mut() {
+        match secrets.as_ref().and_then(|secrets| secrets.get(store_id)) {
+            Some(store_secrets) => {
+                if let Some(accounts) = store_data.get_mut("accounts").and_then(Value::as_array_mut)
+                {
+                    for account in accounts.iter_mut() {
+                        let account_secrets = account
+                            .get("id")
+                            .and_then(Value::as_str)
+                            .and_then(|id| store_secrets.get(id))
+                            .cloned();
+                        if let (Some(account), Some(account_secrets)) =
+                            (account.as_object_mut(), account_secrets)
+                        {
+                            account.extend(account_secrets);
+                        }
+                    }
+                }
+            }
+            None => {
+                store_data["accounts"] = json!([]);
+                store_data["storage_status"] = json!(StorageStatus::Uninitialized);
+            }
+        }
+    }
+}
+
 impl SessionStore {
     pub async fn load() -> Option<SessionStore> {
-        let loaded = chrome
+        let value = chrome
             .storage()
             .session()
             .get_item(&type_name::<SessionStore>(), store::StorageArea::Session)
-            .await;
-        if let Ok(value) = loaded {
-            let parsed = <JsValue as JsValueSerdeExt>::into_serde::<String>(&value);
-            if let Ok(json_string) = parsed {
-                let state = serde_json::from_str::<SessionStore>(&json_string);
-                if let Ok(state) = state {
-                    return Some(state);
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
-        } else {
-            None
-        }
+            .await
+            .ok()?;
+        let json_string = <JsValue as JsValueSerdeExt>::into_serde::<String>(&value).ok()?;
+        let mut state = serde_json::from_str::<Value>(&json_string).ok()?;
+        let secrets = match state
+            .get_mut(SECRETS_KEY)
+            .map(Value::take)
+            .and_then(|secrets| secrets.as_str().map(str::to_owned))
+        {
+            Some(secrets) => session_crypto::decrypt(&secrets)
+                .await
+                .map_err(|err| warn!("saved secrets could not be decrypted: {:?}", err))
+                .ok()
+                .and_then(|secrets| serde_json::from_str::<Secrets>(&secrets).ok()),
+            None => None,
+        };
+        restore_secrets(&mut state, secrets);
+        serde_json::from_value::<SessionStore>(state).ok()
     }
 
-    pub fn save<T: Serialize>(state: &T, area: store::StorageArea) -> Result<(), StorageError> {
-        let value = serde_json::to_string(state)
+    //saves the state to session storage without any secret of the accounts in plain text
+    pub fn save_session(&self) -> Result<(), StorageError> {
+        let mut state = serde_json::to_value(self)
             .map_err(|serde_error| StorageError::SerdeError(serde_error))?;
-
+        let secrets = take_secrets(&mut state);
+        let generation = SAVE_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
         wasm_bindgen_futures::spawn_local(async move {
-            match area {
-                store::StorageArea::Local => {
-                    let _ = chrome
-                        .storage()
-                        .local()
-                        .set_string_item(type_name::<T>().to_owned(), value, area)
-                        .await;
-                }
-                store::StorageArea::Sync => {
-                    let _ = chrome
-                        .storage()
-                        .sync()
-                        .set_string_item(type_name::<T>().to_owned(), value, area)
-                        .await;
-                }
-                store::StorageArea::Session => {
-                    let _ = chrome
-                        .storage()
-                        .session()
-                        .set_string_item(type_name::<T>().to_owned(), value, area)
-                        .await;
-                    let _current = chrome
-                        .storage()
-                        .session()
-                        .get_all(store::StorageArea::Session)
-                        .await
-                        .unwrap();
-                }
+            let secrets =
+                match session_crypto::encrypt(&serde_json::to_string(&secrets).unwrap()).await {
+                    Ok(secrets) => Value::String(secrets),
+                    //the accounts are fetched from the native app again after a restart instead
+                    Err(err) => {
+                        error!("secrets could not be encrypted: {:?}", err);
+                        Value::Null
+                    }
+                };
+            if SAVE_GENERATION.load(Ordering::SeqCst) != generation {
+                return;
             }
+            state[SECRETS_KEY] = secrets;
+            let _ = chrome
+                .storage()
+                .session()
+                .set_string_item(
+                    type_name::<SessionStore>().to_owned(),
+                    state.to_string(),
+                    store::StorageArea::Session,
+                )
+                .await;
         });
         Ok(())
     }
@@ -264,10 +380,10 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                         .as_bool()
                         .unwrap()
                 };
-                let mut data = HashMap::new();
-                data.insert(DataFieldType::Verified, json!(true));
-                data.insert(DataFieldType::StoreID, json!(store_id.clone()));
-                data.insert(DataFieldType::IsDefault, json!(is_default));
+                let payload = LoginPayload {
+                    store_id: store_id.clone(),
+                    is_default,
+                };
                 let mut stores_ptr = store.stores.borrow_mut();
                 if let Some(store) = stores_ptr.get_mut(&store_id.clone()) {
                     store.verified = true;
@@ -280,36 +396,23 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                             signing_key: None,
                             store_id: store_id.clone(),
                             verified: true,
+                            password_policy: None,
+                            password_policy_error: None,
                         },
                     );
                 }
                 if is_default {
                     store.default_store.borrow_mut().replace(store_id.clone());
                 }
-                if let Some(prev_store_id) = meta.as_ref().unwrap().get("prev_store_id") {
-                    if let Some(prev_store_id) = prev_store_id.as_str().to_owned() {
-                        if prev_store_id != store_id {
-                            let mut locked = LISTENER_PORT.lock().unwrap();
-                            if let Some(store) = locked.get_mut(prev_store_id) {
-                                ports_to_disconnect
-                                    .entry(prev_store_id.to_string())
-                                    .and_modify(|v| {
-                                        v.extend(store.clone());
-                                    })
-                                    .or_insert_with(|| store.clone());
-                            }
-                        }
-                    }
-                }
                 (
                     SessionStore {
                         ..store.deref().clone()
                     }
                     .into(),
                     Some(SessionEvent {
-                        event_type: SessionEventType::Login,
+                        event_type: SessionEventType::Login(payload.into()),
                         store_id_index: Some(store_id),
-                        data: Some(data),
+                        data: None,
                         header: meta,
                         resource: Some(vec![Resource::Auth]),
                         is_global: true,
@@ -354,16 +457,17 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                     if let Some(target_store) = store.stores.clone().borrow_mut().get_mut(&store_id)
                     {
                         if (*target_store).verified {
-                            let mut data = HashMap::new();
-                            data.insert(DataFieldType::StoreID, json!(store_id));
+                            let payload = LogoutPayload {
+                                store_id: Some(store_id.clone()),
+                            };
                             target_store.storage_status = StorageStatus::Uninitialized;
                             target_store.verified = false;
                             (
                                 store,
                                 Some(SessionEvent {
                                     store_id_index: Some(store_id),
-                                    event_type: SessionEventType::Logout,
-                                    data: Some(data),
+                                    event_type: SessionEventType::Logout(payload.into()),
+                                    data: None,
                                     header: meta,
                                     resource: Some(vec![Resource::Auth]),
                                     is_global: true,
@@ -398,7 +502,7 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                         store,
                         Some(SessionEvent {
                             store_id_index: None,
-                            event_type: SessionEventType::Logout,
+                            event_type: SessionEventType::Logout(LogoutPayload::default().into()),
                             data: None,
                             header: meta,
                             resource: Some(vec![Resource::Auth]),
@@ -408,7 +512,7 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                     )
                 }
             }
-            SessionAction::DataDeleted(resource, resource_id, data) => match resource.clone() {
+            SessionAction::DataDeleted(resource, resource_id) => match resource.clone() {
                 Resource::Account => {
                     let mut store_id = None;
                     let mut stores_ptr = store.stores.borrow_mut().clone();
@@ -431,8 +535,10 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                         .into(),
                         Some(SessionEvent {
                             store_id_index: store_id,
-                            event_type: SessionEventType::Delete,
-                            data: Some(data),
+                            event_type: SessionEventType::Delete(
+                                DeletePayload { id: resource_id }.into(),
+                            ),
+                            data: None,
                             header: meta,
                             resource: Some(vec![resource]),
                             is_global: true,
@@ -445,34 +551,22 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                         ..store.deref().clone()
                     }
                     .into(),
-                    Some(SessionEvent {
-                        store_id_index: None,
-                        event_type: SessionEventType::Delete,
-                        data: None,
-                        header: meta,
-                        resource: Some(vec![resource]),
-                        is_global: true,
-                        acknowledgement,
-                    }),
+                    None,
                 ),
             },
             SessionAction::DataCreated(mut create_response) => {
                 let resource = create_response.resource.clone();
                 match resource {
                     Resource::Account => {
-                        let data_payload =
-                            create_response.data.remove(&DataFieldType::Data).unwrap();
-                        let account: Rc<Account> =
-                            Rc::new(serde_json::from_value(data_payload).unwrap());
+                        let Some(created) = create_response.created.take() else {
+                            error!("create response carries no account");
+                            return store;
+                        };
+                        let account = created.payload.account;
                         let mut stores_ptr = store.stores.borrow_mut().clone();
-                        let mut data = HashMap::new();
                         if let Some(store_updated) = stores_ptr.get_mut(&create_response.store_id) {
                             let mut account_vec = store_updated.accounts.borrow_mut();
-                            account_vec.push(account.clone());
-                            data.insert(
-                                DataFieldType::Data,
-                                serde_json::to_value(account).unwrap(),
-                            );
+                            account_vec.push(Rc::new(account.clone()));
                         }
                         (
                             SessionStore {
@@ -481,9 +575,11 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                             .into(),
                             Some(SessionEvent {
                                 store_id_index: Some(create_response.store_id),
-                                event_type: SessionEventType::Create,
-                                data: Some(data),
-                                header: meta,
+                                event_type: SessionEventType::Create(
+                                    AccountPayload { account }.into(),
+                                ),
+                                data: None,
+                                header: with_breach_count(meta, &create_response.meta),
                                 resource: Some(vec![resource]),
                                 is_global: true,
                                 acknowledgement,
@@ -495,15 +591,7 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                             ..store.deref().clone()
                         }
                         .into(),
-                        Some(SessionEvent {
-                            store_id_index: None,
-                            event_type: SessionEventType::Create,
-                            data: None,
-                            header: meta,
-                            resource: Some(vec![resource]),
-                            is_global: true,
-                            acknowledgement,
-                        }),
+                        None,
                     ),
                 }
             }
@@ -511,11 +599,13 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                 let resource = edit_response.resource.clone();
                 match resource {
                     Resource::Account => {
-                        let data_payload = edit_response.data.clone();
-                        let updated_data = data_payload.get(&DataFieldType::UpdatedFields).unwrap();
-                        let updated_data = updated_data.as_object().unwrap();
+                        let updated_fields = edit_response
+                            .updated_fields
+                            .clone()
+                            .map(|updated_fields| updated_fields.payload)
+                            .unwrap_or_default();
                         let mut stores_ptr = store.stores.borrow_mut().clone();
-                        let mut data = HashMap::new();
+                        let mut edited_account = None;
                         let mut meta = meta.unwrap_or(json!({}));
                         for store_ptr in stores_ptr.values_mut() {
                             if let Some(account) = store_ptr
@@ -529,38 +619,8 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                                     serde_json::to_value(account.id.clone()).unwrap(),
                                 );
                                 let new_account: &mut Account = Rc::make_mut(account);
-                                for (key, value) in updated_data {
-                                    //TODO compare through each field's string value, rather than manually
-                                    //checking each field with string literal
-                                    if let Some(new_value) = value.get("new") {
-                                        match key.as_str() {
-                                            "username" => {
-                                                new_account.username =
-                                                    new_value.as_str().unwrap().to_owned();
-                                            }
-                                            "password" => new_account.set_password(Some(
-                                                new_value.as_str().unwrap().to_owned(),
-                                            )),
-                                            "domain" => {
-                                                new_account.domain =
-                                                    Some(new_value.as_str().unwrap().to_owned());
-                                            }
-                                            "note" => {
-                                                new_account.note =
-                                                    Some(new_value.as_str().unwrap().to_owned());
-                                            }
-                                            "path" => {
-                                                new_account.path =
-                                                    Some(new_value.as_str().unwrap().to_owned());
-                                            }
-                                            _ => {}
-                                        }
-                                    }
-                                }
-                                data.insert(
-                                    DataFieldType::Data,
-                                    serde_json::to_value(new_account).unwrap(),
-                                );
+                                updated_fields.apply_to(new_account);
+                                edited_account = Some(new_account.clone());
                                 break;
                             }
                         }
@@ -569,11 +629,13 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                                 ..store.deref().clone()
                             }
                             .into(),
-                            Some(SessionEvent {
+                            edited_account.map(|account| SessionEvent {
                                 store_id_index: Some(edit_response.store_id),
-                                event_type: SessionEventType::Update,
-                                data: Some(data),
-                                header: Some(meta),
+                                event_type: SessionEventType::Update(
+                                    AccountPayload { account }.into(),
+                                ),
+                                data: None,
+                                header: with_breach_count(Some(meta), &edit_response.meta),
                                 resource: Some(vec![resource]),
                                 is_global: true,
                                 acknowledgement,
@@ -585,15 +647,7 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                             ..store.deref().clone()
                         }
                         .into(),
-                        Some(SessionEvent {
-                            store_id_index: Some(edit_response.store_id),
-                            event_type: SessionEventType::Create,
-                            data: None,
-                            header: meta,
-                            resource: Some(vec![resource]),
-                            is_global: true,
-                            acknowledgement,
-                        }),
+                        None,
                     ),
                 }
             }
@@ -601,7 +655,7 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                 let mut stores_ptr = store.stores.borrow_mut();
                 let session_data = stores_ptr.get_mut(&fetch_response.store_id);
                 if let Some(session_data) = session_data {
-                    let data = fetch_response.data;
+                    let fetched = fetch_response.fetched.map(|fetched| fetched.payload);
                     let mut meta = meta.unwrap_or(json!({}));
                     let meta_obj = meta.as_object_mut().unwrap();
                     let response_meta = fetch_response.meta.clone().unwrap_or(json!({}));
@@ -610,25 +664,31 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                     if path.is_some() {
                         meta_obj.insert("path".to_owned(), path.unwrap().into());
                     }
+                    let custom_field_prefix = response_meta
+                        .get("custom_field_prefix")
+                        .and_then(|v| v.as_str())
+                        .map(|v| v.to_owned())
+                        .or(store.custom_field_prefix.clone());
+                    if let Some(custom_field_prefix) = custom_field_prefix.as_ref() {
+                        meta_obj.insert(
+                            "custom_field_prefix".to_owned(),
+                            custom_field_prefix.clone().into(),
+                        );
+                    }
                     let resource = fetch_response.resource.clone();
-                    if resource == Resource::Account {
-                        let data_payload: Vec<Rc<Account>> = data
-                            .get(&DataFieldType::Data)
-                            .unwrap_or(&json!([]))
-                            .as_array()
-                            .unwrap_or(&vec![])
-                            .into_iter()
-                            .cloned()
-                            .map(|val| Rc::new(serde_json::from_value(val).unwrap()))
-                            .collect();
+                    if let Some(fetched) = fetched.filter(|_| resource == Resource::Account) {
+                        let data_payload: Vec<Rc<Account>> =
+                            fetched.accounts.iter().cloned().map(Rc::new).collect();
                         session_data.storage_status = StorageStatus::Loaded;
                         session_data.verified = true;
+                        session_data.password_policy = fetched.password_policy.clone();
+                        session_data.password_policy_error = fetched.password_policy_error.clone();
                         let session_event = {
                             match session_data.storage_status {
                                 _ => Some(SessionEvent {
                                     store_id_index: Some(fetch_response.store_id),
-                                    event_type: SessionEventType::Refreshed,
-                                    data: Some(data),
+                                    event_type: SessionEventType::Refreshed(fetched.into()),
+                                    data: None,
                                     header: Some(meta),
                                     resource: Some(vec![resource]),
                                     is_global: true,
@@ -640,6 +700,7 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                         *account_section = data_payload;
                         (
                             SessionStore {
+                                custom_field_prefix,
                                 ..store.deref().clone()
                             }
                             .into(),
@@ -694,19 +755,20 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                                 store_id,
                                 signing_key: None,
                                 verified: false,
+                                password_policy: None,
+                                password_policy_error: None,
                             },
                         );
                     }
                 }
-                let mut data = HashMap::new();
-                data.insert(
-                    DataFieldType::StoreIDList,
-                    serde_json::to_value(store_ids).unwrap(),
-                );
-                data.insert(
-                    DataFieldType::Keys,
-                    serde_json::to_value(keys.clone()).unwrap(),
-                );
+                let payload = InitPayload {
+                    store_ids: store_ids
+                        .iter()
+                        .filter_map(|store_id| store_id.as_str().map(|v| v.to_owned()))
+                        .collect(),
+                    keys: keys.clone(),
+                    ..Default::default()
+                };
                 (
                     SessionStore {
                         stores: Mrc::new(stores_ptr),
@@ -717,7 +779,7 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                     .into(),
                     Some(SessionEvent {
                         store_id_index: None,
-                        event_type: SessionEventType::Init(data),
+                        event_type: SessionEventType::Init(payload.into()),
                         data: None,
                         header: meta,
                         resource: Some(vec![Resource::Store]),
@@ -750,7 +812,50 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                     None,
                 )
             }
-            SessionAction::DataCreationFailed(resource, _data, request) => {
+            SessionAction::DataLoadingFailed(store_id) => {
+                if let Some(session_data) = store.stores.borrow_mut().get_mut(&store_id) {
+                    session_data.storage_status = StorageStatus::Uninitialized;
+                }
+                (
+                    SessionStore {
+                        ..store.deref().clone()
+                    }
+                    .into(),
+                    None,
+                )
+            }
+            SessionAction::RequestTimedOut(request, timeout_ms) => {
+                if let Some(ref acknowledgement) = acknowledgement {
+                    PORT_ID_MAP.lock().unwrap().remove(acknowledgement);
+                }
+                if let RequestEnum::Fetch(ref fetch_request) = request {
+                    if let Some(store_id) = fetch_request.store_id.as_ref() {
+                        if let Some(session_data) = store.stores.borrow_mut().get_mut(store_id) {
+                            session_data.storage_status = StorageStatus::Uninitialized;
+                        }
+                    }
+                }
+                (
+                    SessionStore {
+                        ..store.deref().clone()
+                    }
+                    .into(),
+                    Some(SessionEvent {
+                        store_id_index: None,
+                        event_type: SessionEventType::RequestTimedOut(RequestTimeout {
+                            request_type: request.to_string(),
+                            store_id: request.get_store_id(),
+                            timeout_ms,
+                        }),
+                        data: None,
+                        header: meta,
+                        resource: None,
+                        is_global: true,
+                        acknowledgement,
+                    }),
+                )
+            }
+            SessionAction::DataCreationFailed(resource, failure, request) => {
                 let session_event = {
                     if let Some(request) = request {
                         let request_acknowledgement = request.get_acknowledgement();
@@ -761,8 +866,8 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                         let session_event = match resource {
                             Resource::Account => Some(SessionEvent {
                                 store_id_index: request.get_store_id(),
-                                event_type: SessionEventType::CreationFailed,
-                                data: Some(_data),
+                                event_type: SessionEventType::CreationFailed(failure.into()),
+                                data: None,
                                 header: meta,
                                 resource: Some(vec![resource]),
                                 is_global: false,
@@ -783,6 +888,38 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                     session_event,
                 )
             }
+            SessionAction::DataEditFailed(resource, failure, request) => {
+                let session_event = {
+                    if let Some(request) = request {
+                        let request_acknowledgement = request.get_acknowledgement();
+                        if let Some(request_acknowledgement) = request_acknowledgement {
+                            extension_port_name =
+                                PORT_ID_MAP.lock().unwrap().remove(&request_acknowledgement)
+                        }
+                        match resource {
+                            Resource::Account => Some(SessionEvent {
+                                store_id_index: request.get_store_id(),
+                                event_type: SessionEventType::UpdateFailed(failure.into()),
+                                data: None,
+                                header: meta,
+                                resource: Some(vec![resource]),
+                                is_global: false,
+                                acknowledgement,
+                            }),
+                            _ => None,
+                        }
+                    } else {
+                        None
+                    }
+                };
+                (
+                    SessionStore {
+                        ..store.deref().clone()
+                    }
+                    .into(),
+                    session_event,
+                )
+            }
             SessionAction::StoreCreated(response) => {
                 let store_id = response.store_id.clone();
                 let mut stores_ptr = store.stores.borrow_mut();
@@ -798,8 +935,10 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                         ..Default::default()
                     },
                 );
-                let mut data = HashMap::new();
-                data.insert(DataFieldType::StoreID, json!(store_id.clone()));
+                let payload = StorePayload {
+                    store_id: store_id.clone(),
+                    error: None,
+                };
                 (
                     SessionStore {
                         ..store.deref().clone()
@@ -808,7 +947,7 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                     Some(SessionEvent {
                         //TODO: adding store id here causes error (only those subscribed to store id will receive the event, but the store just got created!)
                         store_id_index: None,
-                        event_type: SessionEventType::StoreCreated(data, store_id),
+                        event_type: SessionEventType::StoreCreated(payload.into()),
                         data: None,
                         header: meta,
                         resource: Some(vec![Resource::Store]),
@@ -825,8 +964,10 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                     .unwrap()
                     .remove(&response.acknowledgement.clone().unwrap());
                 stores_ptr.remove(&store_id);
-                let mut data = HashMap::new();
-                data.insert(DataFieldType::StoreID, json!(store_id.clone()));
+                let payload = StorePayload {
+                    store_id: store_id.clone(),
+                    error: None,
+                };
                 (
                     SessionStore {
                         ..store.deref().clone()
@@ -834,7 +975,7 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                     .into(),
                     Some(SessionEvent {
                         store_id_index: None,
-                        event_type: SessionEventType::StoreDeleted(data, store_id),
+                        event_type: SessionEventType::StoreDeleted(payload.into()),
                         data: None,
                         header: meta,
                         resource: Some(vec![Resource::Store]),
@@ -849,12 +990,18 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                     extension_port_name =
                         PORT_ID_MAP.lock().unwrap().remove(&request_acknowledgement)
                 }
-                let mut data = HashMap::new();
                 let store_id = request.get_store_id().unwrap_or_default();
-                if let ResponseEnum::DeleteStoreResponse(response) = response {
-                    data.insert(DataFieldType::StoreID, json!(store_id));
-                    data.insert(DataFieldType::Error, json!(response.data));
-                }
+                let error = match response {
+                    ResponseEnum::DeleteStoreResponse(response) => Some(json!(response.data)),
+                    ResponseEnum::ErrorResponse(response) => {
+                        response.message.clone().map(Value::String)
+                    }
+                    _ => None,
+                };
+                let payload = StorePayload {
+                    store_id: store_id.clone(),
+                    error,
+                };
                 (
                     SessionStore {
                         ..store.deref().clone()
@@ -862,7 +1009,7 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                     .into(),
                     Some(SessionEvent {
                         store_id_index: Some(store_id.clone()),
-                        event_type: SessionEventType::StoreDeletionFailed(data, store_id),
+                        event_type: SessionEventType::StoreDeletionFailed(payload.into()),
                         data: None,
                         header: meta,
                         resource: Some(vec![Resource::Store]),
@@ -877,12 +1024,18 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                     extension_port_name =
                         PORT_ID_MAP.lock().unwrap().remove(&request_acknowledgement)
                 }
-                let mut data = HashMap::new();
                 let store_id = request.get_store_id().unwrap_or_default();
-                if let ResponseEnum::CreateResponse(response) = response {
-                    data.insert(DataFieldType::StoreID, json!(store_id));
-                    data.insert(DataFieldType::Error, json!(response.data));
-                }
+                let error = match response {
+                    ResponseEnum::CreateStoreResponse(response) => Some(json!(response.data)),
+                    ResponseEnum::ErrorResponse(response) => {
+                        response.message.clone().map(Value::String)
+                    }
+                    _ => None,
+                };
+                let payload = StorePayload {
+                    store_id: store_id.clone(),
+                    error,
+                };
                 (
                     SessionStore {
                         ..store.deref().clone()
@@ -890,7 +1043,7 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                     .into(),
                     Some(SessionEvent {
                         store_id_index: Some(store_id.clone()),
-                        event_type: SessionEventType::StoreCreationFailed(data, store_id),
+                        event_type: SessionEventType::StoreCreationFailed(payload.into()),
                         data: None,
                         header: meta,
                         resource: Some(vec![Resource::Store]),
@@ -899,6 +1052,19 @@ impl Reducer<SessionStore> for SessionActionWrapper {
                     }),
                 )
             }
+            SessionAction::LoginStepRemembered(tab_id, login_step) => {
+                let now = js_sys::Date::now();
+                let mut login_steps = store.login_steps.borrow_mut();
+                login_steps.retain(|_, step| !step.is_expired(now));
+                login_steps.insert(tab_id, login_step);
+                (
+                    SessionStore {
+                        ..store.deref().clone()
+                    }
+                    .into(),
+                    None,
+                )
+            }
             _ => (
                 SessionStore {
                     ..store.deref().clone()
@@ -926,12 +1092,42 @@ impl Reducer<SessionStore> for SessionActionWrapper {
         session_store
     }
 }
+#[derive(Default)]
+struct ContextMenuListener {
+    menu_accounts: Vec<MenuAccount>,
+}
+impl Listener for ContextMenuListener {
+    type Store = SessionStore;
+
+    fn on_change(&mut self, state: Rc<Self::Store>) {
+        let menu_accounts = menu_accounts(&state.stores.borrow());
+        if menu_accounts != self.menu_accounts {
+            self.menu_accounts = menu_accounts.clone();
+            wasm_bindgen_futures::spawn_local(rebuild_context_menus(menu_accounts));
+        }
+    }
+}
+#[derive(Default)]
+struct BadgeListener {
+    accounts: Option<Vec<Rc<Account>>>,
+}
+impl Listener for BadgeListener {
+    type Store = SessionStore;
+
+    fn on_change(&mut self, state: Rc<Self::Store>) {
+        let accounts = badge_accounts(&state.stores.borrow());
+        if accounts != self.accounts {
+            self.accounts = accounts;
+            refresh_badges();
+        }
+    }
+}
 struct StorageListener;
 impl Listener for StorageListener {
     type Store = SessionStore;
 
     fn on_change(&mut self, state: Rc<Self::Store>) {
-        if let Err(err) = Self::Store::save(state.as_ref(), store::StorageArea::Session) {
+        if let Err(err) = state.save_session() {
             println!("Error saving state to storage: {:?}", err);
         } else {
         }
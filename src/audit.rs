@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+
+use crate::{password_strength::estimate_strength, types::Account};
+
+pub const DEFAULT_MAX_PASSWORD_AGE_DAYS: i64 = 365;
+//passwords scoring below this are reported as weak
+pub const MIN_PASSWORD_SCORE: u8 = 3;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct AuditOptions {
+    pub max_password_age_days: i64,
+    pub min_password_score: u8,
+}
+impl Default for AuditOptions {
+    fn default() -> Self {
+        AuditOptions {
+            max_password_age_days: DEFAULT_MAX_PASSWORD_AGE_DAYS,
+            min_password_score: MIN_PASSWORD_SCORE,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "finding", rename_all = "snake_case")]
+pub enum AuditFinding {
+    ReusedPassword { shared_with: Vec<String> },
+    //found in a local copy of a breached password list, checked by the native app
+    BreachedPassword { occurrences: u64 },
+    WeakPassword { score: u8 },
+    MissingPassword,
+    OldPassword { age_days: i64 },
+    InsecureDomain,
+    MissingUsername,
+    MissingDomain,
+}
+impl AuditFinding {
+    pub fn category(&self) -> &'static str {
+        match self {
+            AuditFinding::ReusedPassword { .. } => "Reused passwords",
+            AuditFinding::BreachedPassword { .. } => "Breached passwords",
+            AuditFinding::WeakPassword { .. } => "Weak passwords",
+            AuditFinding::MissingPassword => "Missing passwords",
+            AuditFinding::OldPassword { .. } => "Old passwords",
+            AuditFinding::InsecureDomain => "Plain http domains",
+            AuditFinding::MissingUsername => "Missing usernames",
+            AuditFinding::MissingDomain => "Missing domains",
+        }
+    }
+}
+
+//whether the passwords were looked up in a breached password list, which only the native app can do
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum BreachCheck {
+    #[default]
+    NotConfigured,
+    Checked,
+    Unavailable {
+        reason: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    pub id: String,
+    pub username: String,
+    pub domain: Option<String>,
+    pub findings: Vec<AuditFinding>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct AuditReport {
+    pub total_entries: usize,
+    pub generated_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub breach_check: BreachCheck,
+    //only entries with at least one finding
+    pub entries: Vec<AuditEntry>,
+}
+impl AuditReport {
+    //number of entries affected by every category of findings
+    pub fn summary(&self) -> Vec<(&'static str, usize)> {
+        let mut summary: Vec<(&'static str, usize)> = vec![];
+        for finding in self.entries.iter().flat_map(|entry| entry.findings.iter()) {
+            match summary
+                .iter_mut()
+                .find(|(category, _)| *category == finding.category())
+            {
+                Some((_, count)) => *count += 1,
+                None => summary.push((finding.category(), 1)),
+            }
+        }
+        summary
+    }
+    pub fn add_finding(&mut self, account: &Account, finding: AuditFinding) {
+        match self.entries.iter_mut().find(|entry| entry.id == account.id) {
+            Some(entry) => entry.findings.push(finding),
+            None => self.entries.push(AuditEntry {
+                id: account.id.clone(),
+                username: account.username.clone(),
+                domain: account.domain.clone(),
+                findings: vec![finding],
+            }),
+        }
+    }
+}
+
+pub fn audit_accounts(
+    accounts: &[Account],
+    options: &AuditOptions,
+    now: DateTime<Utc>,
+) -> AuditReport {
+    let mut ids_by_password: HashMap<String, Vec<String>> = HashMap::new();
+    for account in accounts {
+        if let Some(password) = account.get_password() {
+            if !password.expose_secret().is_empty() {
+                ids_by_password
+                    .entry(password.expose_secret().clone())
+                    .or_default()
+                    .push(account.id.clone());
+            }
+        }
+    }
+    let entries = accounts
+        .iter()
+        .map(|account| {
+            let mut findings = vec![];
+            let password = account
+                .get_password()
+                .map(|password| password.expose_secret().clone())
+                .unwrap_or_default();
+            if let Some(ids) = ids_by_password.get(&password) {
+                if ids.len() > 1 {
+                    findings.push(AuditFinding::ReusedPassword {
+                        shared_with: ids
+                            .iter()
+                            .filter(|id| **id != account.id)
+                            .cloned()
+                            .collect(),
+                    });
+                }
+            }
+            if password.is_empty() {
+                findings.push(AuditFinding::MissingPassword);
+            } else {
+                let strength = estimate_strength(&password);
+                if strength.score < options.min_password_score {
+                    findings.push(AuditFinding::WeakPassword {
+                        score: strength.score,
+                    });
+                }
+            }
+            if let Some(updated_at) = account.updated_at.or(account.created_at) {
+                let age_days = (now - updated_at).num_days();
+                if age_days > options.max_password_age_days {
+                    findings.push(AuditFinding::OldPassword { age_days });
+                }
+            }
+            //the urls an account has besides its domain count the same as the domain
+            let addresses = account
+                .domain
+                .iter()
+                .chain(account.urls.iter().map(|url| &url.url))
+                .map(|address| address.trim())
+                .filter(|address| !address.is_empty())
+                .collect::<Vec<&str>>();
+            if addresses.is_empty() {
+                findings.push(AuditFinding::MissingDomain);
+            } else if addresses
+                .iter()
+                .any(|address| address.to_lowercase().starts_with("http://"))
+            {
+                findings.push(AuditFinding::InsecureDomain);
+            }
+            if account.username.trim().is_empty() {
+                findings.push(AuditFinding::MissingUsername);
+            }
+            AuditEntry {
+                id: account.id.clone(),
+                username: account.username.clone(),
+                domain: account.domain.clone(),
+                findings,
+            }
+        })
+        .filter(|entry| !entry.findings.is_empty())
+        .collect();
+    AuditReport {
+        total_entries: accounts.len(),
+        generated_at: Some(now),
+        breach_check: BreachCheck::NotConfigured,
+        entries,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::url_match::AccountUrl;
+    use chrono::Duration;
+
+    const STRONG_PASSWORD: &str = "kq8#Vz2!mWp9$Lx4&Tn7";
+
+    fn account(id: &str, password: &str) -> Account {
+        let mut account = Account::default();
+        account.id = id.to_owned();
+        account.username = format!("{}@example.com", id);
+        account.domain = Some("https://example.com".to_owned());
+        account.set_password(Some(password.to_owned()));
+        account
+    }
+
+    fn findings(accounts: &[Account], id: &str) -> Vec<AuditFinding> {
+        audit_accounts(accounts, &AuditOptions::default(), Utc::now())
+            .entries
+            .into_iter()
+            .find(|entry| entry.id == id)
+            .map(|entry| entry.findings)
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn clean_account_is_left_out() {
+        let report = audit_accounts(
+            &[account("a", STRONG_PASSWORD)],
+            &AuditOptions::default(),
+            Utc::now(),
+        );
+        assert_eq!(report.total_entries, 1);
+        assert!(report.entries.is_empty());
+    }
+
+    #[test]
+    fn reused_password() {
+        let accounts = [
+            account("a", STRONG_PASSWORD),
+            account("b", STRONG_PASSWORD),
+            account("c", "another-Strong-passw0rd-9"),
+        ];
+        assert_eq!(
+            findings(&accounts, "a"),
+            vec![AuditFinding::ReusedPassword {
+                shared_with: vec!["b".to_owned()]
+            }]
+        );
+        assert!(findings(&accounts, "c").is_empty());
+    }
+
+    #[test]
+    fn weak_password() {
+        let found = findings(&[account("a", "password")], "a");
+        assert!(matches!(
+            found.as_slice(),
+            [AuditFinding::WeakPassword { score }] if *score < MIN_PASSWORD_SCORE
+        ));
+    }
+
+    #[test]
+    fn missing_password_is_not_weak_or_reused() {
+        let mut without_password = account("a", "");
+        without_password.set_password(None);
+        let accounts = [without_password, account("b", ""), account("c", "")];
+        assert_eq!(
+            findings(&accounts, "a"),
+            vec![AuditFinding::MissingPassword]
+        );
+        assert_eq!(
+            findings(&accounts, "b"),
+            vec![AuditFinding::MissingPassword]
+        );
+    }
+
+    #[test]
+    fn old_password() {
+        let now = Utc::now();
+        let mut old = account("a", STRONG_PASSWORD);
+        old.created_at = Some(now - Duration::days(400));
+        let mut updated = account("b", "another-Strong-passw0rd-9");
+        updated.created_at = Some(now - Duration::days(400));
+        updated.updated_at = Some(now - Duration::days(10));
+        let report = audit_accounts(&[old, updated], &AuditOptions::default(), now);
+        assert_eq!(report.entries.len(), 1);
+        assert_eq!(
+            report.entries[0].findings,
+            vec![AuditFinding::OldPassword { age_days: 400 }]
+        );
+    }
+
+    #[test]
+    fn insecure_domain() {
+        let mut insecure = account("a", STRONG_PASSWORD);
+        insecure.domain = Some(" HTTP://example.com".to_owned());
+        assert_eq!(
+            findings(&[insecure], "a"),
+            vec![AuditFinding::InsecureDomain]
+        );
+    }
+
+    #[test]
+    fn insecure_url() {
+        let mut insecure = account("a", STRONG_PASSWORD);
+        insecure.urls = vec![AccountUrl {
+            url: "http://login.example.com".to_owned(),
+            ..Default::default()
+        }];
+        assert_eq!(
+            findings(&[insecure], "a"),
+            vec![AuditFinding::InsecureDomain]
+        );
+    }
+
+    #[test]
+    fn missing_domain() {
+        let mut missing = account("a", STRONG_PASSWORD);
+        missing.domain = Some("  ".to_owned());
+        assert_eq!(findings(&[missing], "a"), vec![AuditFinding::MissingDomain]);
+    }
+
+    #[test]
+    fn url_stands_in_for_missing_domain() {
+        let mut with_url = account("a", STRONG_PASSWORD);
+        with_url.domain = None;
+        with_url.urls = vec![AccountUrl {
+            url: "https://example.com/login".to_owned(),
+            ..Default::default()
+        }];
+        assert!(findings(&[with_url], "a").is_empty());
+    }
+
+    #[test]
+    fn missing_username() {
+        let mut missing = account("a", STRONG_PASSWORD);
+        missing.username = " ".to_owned();
+        assert_eq!(
+            findings(&[missing], "a"),
+            vec![AuditFinding::MissingUsername]
+        );
+    }
+
+    #[test]
+    fn summary_counts_entries_per_category() {
+        let mut report = audit_accounts(
+            &[account("a", "password"), account("b", "password")],
+            &AuditOptions::default(),
+            Utc::now(),
+        );
+        report.add_finding(
+            &account("c", STRONG_PASSWORD),
+            AuditFinding::BreachedPassword { occurrences: 3 },
+        );
+        assert_eq!(
+            report.summary(),
+            vec![
+                ("Reused passwords", 2),
+                ("Weak passwords", 2),
+                ("Breached passwords", 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use gloo_utils::format::JsValueSerdeExt;
+use js_sys::Uint8Array;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::js_binding::clipboard_copy;
+use crate::js_binding::extension_api::{chrome, Port};
+use crate::request::RequestEnum;
+use crate::session_crypto::{crypto, to_hex};
+use crate::store::StorageArea;
+
+//seconds a copied value stays on the clipboard, 0 leaves it there. kept in sync storage with the
+//other options
+pub const CLIPBOARD_CLEAR_SECONDS_KEY: &str = "clipboard_clear_seconds";
+pub const DEFAULT_CLIPBOARD_CLEAR_SECONDS: u32 = 30;
+
+pub async fn load_clipboard_clear_seconds() -> u32 {
+    chrome
+        .storage()
+        .sync()
+        .get_item(CLIPBOARD_CLEAR_SECONDS_KEY, StorageArea::Sync)
+        .await
+        .ok()
+        .and_then(|value| <JsValue as JsValueSerdeExt>::into_serde::<String>(&value).ok())
+        .and_then(|seconds| seconds.parse::<u32>().ok())
+        .unwrap_or(DEFAULT_CLIPBOARD_CLEAR_SECONDS)
+}
+pub async fn set_clipboard_clear_seconds(seconds: u32) {
+    chrome
+        .storage()
+        .sync()
+        .set_string_item(
+            CLIPBOARD_CLEAR_SECONDS_KEY.to_owned(),
+            seconds.to_string(),
+            StorageArea::Sync,
+        )
+        .await;
+}
+
+//stands in for a copied value, which is never sent to or kept by the service worker. a salted
+//sha-256 is enough to tell whether the clipboard still holds the value
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CopiedDigest {
+    pub salt: String,
+    pub digest: String,
+}
+impl CopiedDigest {
+    pub async fn of(text: &str) -> Result<Self, JsValue> {
+        let mut salt = [0u8; 16];
+        getrandom::getrandom(&mut salt).map_err(|error| JsValue::from_str(&error.to_string()))?;
+        let salt = to_hex(&salt);
+        let digest = salted_digest(&salt, text).await?;
+        Ok(CopiedDigest { salt, digest })
+    }
+    pub async fn matches(&self, text: &str) -> bool {
+        salted_digest(&self.salt, text)
+            .await
+            .is_ok_and(|digest| digest == self.digest)
+    }
+}
+//the offscreen document of chrome computes the same digest in offscreen.js
+async fn salted_digest(salt: &str, text: &str) -> Result<String, JsValue> {
+    let data = Uint8Array::from(format!("{}{}", salt, text).as_bytes());
+    let digest = JsFuture::from(
+        crypto()?
+            .subtle()
+            .digest_with_str_and_buffer_source("SHA-256", &data)?,
+    )
+    .await?;
+    Ok(to_hex(&Uint8Array::new(&digest).to_vec()))
+}
+
+//copy the text and have the service worker clear it later. the timer lives in the service worker
+//so that closing the popup does not cancel it
+pub async fn copy_to_clipboard(port: &Port, text: &str) -> Result<JsValue, JsValue> {
+    let result = clipboard_copy(text).await?;
+    let clipboard_clear_request =
+        RequestEnum::create_clipboard_clear_request(CopiedDigest::of(text).await?, None);
+    port.post_message(<JsValue as JsValueSerdeExt>::from_serde(&clipboard_clear_request).unwrap());
+    Ok(result)
+}
+
+//empties the clipboard if it still holds the copied value, so whatever the user copied since is
+//left alone. needs a window, which the service worker of firefox has as a background page
+pub async fn clear_clipboard_if_unchanged(copied: &CopiedDigest) -> Result<bool, JsValue> {
+    let window = web_sys::window().ok_or(JsValue::from_str("Missing Window"))?;
+    let navigator = window.navigator();
+    let clipboard = navigator.clipboard().expect("Missing Clipboard");
+    let current = JsFuture::from(clipboard.read_text()).await?;
+    if !copied
+        .matches(&current.as_string().unwrap_or_default())
+        .await
+    {
+        return Ok(false);
+    }
+    JsFuture::from(clipboard.write_text("")).await?;
+    Ok(true)
+}
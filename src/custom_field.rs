@@ -0,0 +1,188 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::types::{Account, TOTP_FIELD};
+use crate::url_match::{URLS_FIELD, URL_MATCH_FIELD};
+
+//TODO don't use magic string for field names
+//field of an entry holding the type of each custom field by name. the values themselves stay
+//plain strings, so the entry reads the same in other pass clients
+pub const CUSTOM_FIELD_TYPES_FIELD: &str = "custom_field_types";
+//kept by the native app on password changes, and never sent to the extension
+pub const PASSWORD_HISTORY_FIELD: &str = "password_history";
+//fields the extension manages itself, which are not offered as custom fields
+const RESERVED_FIELDS: &[&str] = &[
+    TOTP_FIELD,
+    URL_MATCH_FIELD,
+    URLS_FIELD,
+    CUSTOM_FIELD_TYPES_FIELD,
+    PASSWORD_HISTORY_FIELD,
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CustomFieldType {
+    Hidden,
+    Url,
+    Email,
+    //an otpauth:// uri or bare base32 secret. like the one-time code field, the native app
+    //keeps the secret to itself
+    Totp,
+    Multiline,
+    //also taken for types saved by a newer version of the extension
+    #[default]
+    #[serde(other)]
+    Text,
+}
+
+impl CustomFieldType {
+    pub const ALL: [CustomFieldType; 6] = [
+        CustomFieldType::Text,
+        CustomFieldType::Hidden,
+        CustomFieldType::Url,
+        CustomFieldType::Email,
+        CustomFieldType::Totp,
+        CustomFieldType::Multiline,
+    ];
+    pub fn name(&self) -> &'static str {
+        match self {
+            CustomFieldType::Text => "text",
+            CustomFieldType::Hidden => "hidden",
+            CustomFieldType::Url => "url",
+            CustomFieldType::Email => "email",
+            CustomFieldType::Totp => "totp",
+            CustomFieldType::Multiline => "multiline",
+        }
+    }
+    pub fn label(&self) -> &'static str {
+        match self {
+            CustomFieldType::Text => "Text",
+            CustomFieldType::Hidden => "Hidden",
+            CustomFieldType::Url => "URL",
+            CustomFieldType::Email => "Email",
+            CustomFieldType::Totp => "One-time code",
+            CustomFieldType::Multiline => "Multiline",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        CustomFieldType::ALL
+            .into_iter()
+            .find(|field_type| field_type.name() == name)
+    }
+    //masked when shown
+    pub fn is_secret(&self) -> bool {
+        matches!(self, CustomFieldType::Hidden | CustomFieldType::Totp)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct CustomField {
+    pub name: String,
+    pub value: String,
+    #[serde(default, rename = "type")]
+    pub field_type: CustomFieldType,
+}
+
+pub fn is_reserved_field_name(name: &str) -> bool {
+    RESERVED_FIELDS.contains(&name.trim())
+}
+
+//the type of each custom field by name. the native app saves custom fields under a prefix, which
+//entries written by other clients may lack
+pub fn custom_field_types<'a>(
+    prefix: &str,
+    field: impl Fn(&str) -> Option<&'a Value>,
+) -> BTreeMap<String, CustomFieldType> {
+    field(&format!("{}{}", prefix, CUSTOM_FIELD_TYPES_FIELD))
+        .or(field(CUSTOM_FIELD_TYPES_FIELD))
+        .and_then(|types| serde_json::from_value(types.clone()).ok())
+        .unwrap_or_default()
+}
+
+//custom fields of a create or edit request. fields named in `removed` are cleared, and one-time
+//code fields left empty keep the secret already saved, as the extension never receives it
+pub fn custom_fields_request_value(
+    fields: &[CustomField],
+    removed: &[String],
+) -> HashMap<String, Value> {
+    let fields = fields
+        .iter()
+        .filter(|field| !field.name.trim().is_empty() && !is_reserved_field_name(&field.name))
+        .collect::<Vec<_>>();
+    let mut custom_fields = HashMap::new();
+    for field in fields.iter() {
+        let value = match field.field_type {
+            CustomFieldType::Totp => field.value.trim(),
+            _ => field.value.as_str(),
+        };
+        if field.field_type == CustomFieldType::Totp && value.is_empty() {
+            continue;
+        }
+        custom_fields.insert(
+            field.name.trim().to_owned(),
+            Value::String(value.to_owned()),
+        );
+    }
+    for name in removed {
+        if !fields.iter().any(|field| field.name.trim() == name) {
+            custom_fields.insert(name.clone(), Value::Null);
+        }
+    }
+    if !fields.is_empty() || !removed.is_empty() {
+        let types = fields
+            .iter()
+            .map(|field| (field.name.trim().to_owned(), field.field_type))
+            .collect::<BTreeMap<_, _>>();
+        custom_fields.insert(
+            CUSTOM_FIELD_TYPES_FIELD.to_owned(),
+            serde_json::to_value(types).unwrap_or_default(),
+        );
+    }
+    custom_fields
+}
+
+impl Account {
+    //the custom fields the user added to the account, by name. without a prefix every field that
+    //isn't managed by the extension is taken
+    pub fn typed_custom_fields(&self, prefix: Option<&str>) -> Vec<CustomField> {
+        let Some(fields) = self.custom_fields.as_ref() else {
+            return vec![];
+        };
+        let prefix = prefix.unwrap_or_default();
+        let types = custom_field_types(prefix, |name| fields.get(name));
+        let mut custom_fields = BTreeMap::new();
+        for (key, value) in fields {
+            let Some(name) = key.strip_prefix(prefix) else {
+                continue;
+            };
+            //cleared fields are null until the entry is fetched again
+            let Some(value) = value.as_str() else {
+                continue;
+            };
+            if name.is_empty() || is_reserved_field_name(name) {
+                continue;
+            }
+            custom_fields.insert(
+                name.to_owned(),
+                CustomField {
+                    name: name.to_owned(),
+                    value: value.to_owned(),
+                    field_type: types.get(name).copied().unwrap_or_default(),
+                },
+            );
+        }
+        //the secrets of one-time code fields stay with the native app, only their type is sent
+        for (name, field_type) in types {
+            custom_fields
+                .entry(name.clone())
+                .or_insert_with(|| CustomField {
+                    name,
+                    value: String::new(),
+                    field_type,
+                });
+        }
+        custom_fields.into_values().collect()
+    }
+}
@@ -28,6 +28,18 @@ extern "C" {
     pub type Storage;
     #[derive(Debug)]
     pub type StorageArea;
+    #[derive(Debug)]
+    pub type Commands;
+    #[derive(Debug)]
+    pub type ContextMenus;
+    #[derive(Debug)]
+    pub type Action;
+    #[derive(Debug)]
+    pub type Omnibox;
+    #[derive(Debug)]
+    pub type Offscreen;
+    #[derive(Debug)]
+    pub type Alarms;
 
     #[wasm_bindgen(js_name = "chrome")]
     pub static chrome: Chrome;
@@ -38,8 +50,60 @@ extern "C" {
     pub fn tabs(this: &Chrome) -> Tabs;
     #[wasm_bindgen(method, getter=storage,structural,js_name=storage)]
     pub fn storage(this: &Chrome) -> Storage;
+    #[wasm_bindgen(method, getter=commands,structural,js_name=commands)]
+    pub fn commands(this: &Chrome) -> Commands;
+    #[wasm_bindgen(method,getter=onCommand)]
+    pub fn on_command(this: &Commands) -> EventTarget;
+    #[wasm_bindgen(method, getter=contextMenus,structural,js_name=contextMenus)]
+    pub fn context_menus(this: &Chrome) -> ContextMenus;
+    #[wasm_bindgen(method,js_name=create)]
+    pub fn create(this: &ContextMenus, create_properties: JsValue) -> JsValue;
+    #[wasm_bindgen(method,js_name=removeAll)]
+    pub fn remove_all(this: &ContextMenus) -> Promise;
+    #[wasm_bindgen(method,getter=onClicked)]
+    pub fn on_clicked(this: &ContextMenus) -> EventTarget;
+    #[wasm_bindgen(method, getter=action,structural,js_name=action)]
+    pub fn action(this: &Chrome) -> Action;
+    #[wasm_bindgen(method,js_name=setBadgeText)]
+    pub fn set_badge_text(this: &Action, details: JsValue) -> Promise;
+    #[wasm_bindgen(method,js_name=setBadgeBackgroundColor)]
+    pub fn set_badge_background_color(this: &Action, details: JsValue) -> Promise;
     #[wasm_bindgen(method,js_name=query)]
     pub fn query(this: &Tabs, query_info: JsValue) -> Promise;
+    #[wasm_bindgen(method,js_name=get)]
+    pub fn get(this: &Tabs, tab_id: i32) -> Promise;
+    #[wasm_bindgen(method,js_name=update)]
+    pub fn update(this: &Tabs, tab_id: i32, update_properties: JsValue) -> Promise;
+    #[wasm_bindgen(method,js_name=create)]
+    pub fn create(this: &Tabs, create_properties: JsValue) -> Promise;
+    #[wasm_bindgen(method,getter=onUpdated)]
+    pub fn on_updated(this: &Tabs) -> EventTarget;
+    #[wasm_bindgen(method,getter=onActivated)]
+    pub fn on_activated(this: &Tabs) -> EventTarget;
+    #[wasm_bindgen(method,getter=onRemoved)]
+    pub fn on_removed(this: &Tabs) -> EventTarget;
+    #[wasm_bindgen(method, getter=omnibox,structural,js_name=omnibox)]
+    pub fn omnibox(this: &Chrome) -> Omnibox;
+    #[wasm_bindgen(method,js_name=setDefaultSuggestion)]
+    pub fn set_default_suggestion(this: &Omnibox, suggestion: JsValue);
+    //only chrome has offscreen documents
+    #[wasm_bindgen(method, getter=offscreen,structural,js_name=offscreen)]
+    pub fn offscreen(this: &Chrome) -> Option<Offscreen>;
+    #[wasm_bindgen(method,js_name=createDocument)]
+    pub fn create_document(this: &Offscreen, parameters: JsValue) -> Promise;
+    #[wasm_bindgen(method,js_name=closeDocument)]
+    pub fn close_document(this: &Offscreen) -> Promise;
+    #[wasm_bindgen(method, getter=alarms,structural,js_name=alarms)]
+    pub fn alarms(this: &Chrome) -> Alarms;
+    //an alarm of the same name replaces the earlier one
+    #[wasm_bindgen(method,js_name=create)]
+    pub fn create(this: &Alarms, name: &str, alarm_info: JsValue) -> Promise;
+    #[wasm_bindgen(method,getter=onAlarm)]
+    pub fn on_alarm(this: &Alarms) -> EventTarget;
+    #[wasm_bindgen(method,getter=onInputChanged)]
+    pub fn on_input_changed(this: &Omnibox) -> EventTarget;
+    #[wasm_bindgen(method,getter=onInputEntered)]
+    pub fn on_input_entered(this: &Omnibox) -> EventTarget;
     #[wasm_bindgen(method,getter=active)]
     pub fn active(this: &Tab) -> bool;
     #[wasm_bindgen(method,getter=discarded)]
@@ -62,6 +126,8 @@ extern "C" {
     pub fn local(this: &Storage) -> StorageArea;
     #[wasm_bindgen(method,getter=sync)]
     pub fn sync(this: &Storage) -> StorageArea;
+    #[wasm_bindgen(method,getter=onChanged)]
+    pub fn on_changed(this: &Storage) -> EventTarget;
     #[wasm_bindgen(method,getter=onDisconnect)]
     pub fn on_disconnect(this: &Runtime) -> EventTarget;
     #[wasm_bindgen(method,getter=onDisconnect,structural)]
@@ -76,6 +142,12 @@ extern "C" {
     pub fn disconnect(this: &Port);
     #[wasm_bindgen(method, getter=sender,structural)]
     pub fn sender(this: &Port) -> Option<MessageSender>;
+    #[wasm_bindgen(method, getter=tab,structural)]
+    pub fn tab(this: &MessageSender) -> Option<Tab>;
+    #[wasm_bindgen(method, getter=frameId,structural)]
+    pub fn frame_id(this: &MessageSender) -> Option<i32>;
+    #[wasm_bindgen(method, getter=url,structural)]
+    pub fn url(this: &MessageSender) -> Option<String>;
 
     #[wasm_bindgen(method, getter=onMessage,structural)]
     pub fn on_message(this: &Runtime) -> EventTarget;
@@ -89,6 +161,12 @@ extern "C" {
     pub fn connect_exteranl(this: &Runtime, s: &str) -> Port;
     #[wasm_bindgen(method,structural,js_name=connect)]
     pub fn connect(this: &Runtime) -> Port;
+    #[wasm_bindgen(method,structural,js_name=sendMessage)]
+    pub fn send_message(this: &Runtime, message: JsValue) -> Promise;
+    #[wasm_bindgen(method,structural,js_name=getURL)]
+    pub fn get_url(this: &Runtime, path: &str) -> String;
+    #[wasm_bindgen(method,structural,js_name=openOptionsPage)]
+    pub fn open_options_page(this: &Runtime) -> Promise;
     #[wasm_bindgen(method, js_class = "Port", js_name = "postMessage")]
     pub fn post_message(this: &Port, message: JsValue);
     #[wasm_bindgen(method,structural,js_name=sendNativeMessage)]
@@ -148,20 +226,19 @@ impl StorageArea {
             storage.get(js_key.clone()).await
         };
         if entry.is_undefined() {
-            return Err(StorageError::KeyNotFound(key.to_owned()));
+            Err(StorageError::KeyNotFound(key.to_owned()))
         } else {
-            return Ok(entry);
+            Ok(entry)
         }
         // entry.map_err(|_| StorageError::KeyNotFound(key.to_owned()))
     }
     pub fn get_all_sync(&self, storage: store::StorageArea) -> JsValue {
         let key: JsValue = JsValue::NULL;
-        let fetched = match storage {
+        match storage {
             store::StorageArea::Local => chrome.storage().local().get_sync(key.clone()),
             store::StorageArea::Sync => chrome.storage().sync().get_sync(key.clone()),
             store::StorageArea::Session => chrome.storage().session().get_sync(key.clone()),
-        };
-        fetched
+        }
     }
     pub async fn get_item(
         &self,
@@ -180,9 +257,9 @@ impl StorageArea {
         };
         let value = js_sys::Reflect::get(&entry, &JsValue::from_str(key));
         if let Ok(value) = value {
-            return Ok(value);
+            Ok(value)
         } else {
-            return Err(StorageError::KeyNotFound(key.to_owned()));
+            Err(StorageError::KeyNotFound(key.to_owned()))
         }
     }
     pub fn get_string_value_sync(
@@ -198,7 +275,7 @@ impl StorageArea {
                 store::StorageArea::Session => chrome.storage().session().get_sync(js_key.clone()),
             }
         };
-        let _fut = js_sys::Reflect::get(&entry, &JsValue::from_str(&"PromiseResult")).unwrap();
+        let _fut = js_sys::Reflect::get(&entry, &JsValue::from_str("PromiseResult")).unwrap();
         Ok(None)
     }
     pub fn set_string_item_sync(&self, key: String, value: String, stroage: store::StorageArea) {
@@ -1,12 +1,21 @@
+pub mod audit;
+pub mod clipboard;
+pub mod custom_field;
 pub mod js_binding;
+pub mod password_generator;
+pub mod password_strength;
+pub mod payload;
 pub mod request;
 pub mod response;
+pub mod session_crypto;
 pub mod store;
+pub mod store_routing;
 pub mod types;
+pub mod url_match;
 pub mod util;
 pub enum StringOrCallback {
     String(String),
-    Callback(Box<dyn FnOnce() -> ()>),
+    Callback(Box<dyn FnOnce()>),
 }
 
 pub use console_error_panic_hook;
@@ -0,0 +1,595 @@
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+pub const LOWERCASE_CHARACTERS: &str = "abcdefghijklmnopqrstuvwxyz";
+pub const UPPERCASE_CHARACTERS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+pub const DIGIT_CHARACTERS: &str = "0123456789";
+pub const SYMBOL_CHARACTERS: &str = "!@#$%^&*()-_=+[]{};:,.<>?/~";
+//characters that are easily confused with each other when read or typed by hand
+pub const LOOKALIKE_CHARACTERS: &str = "Il1|O0o`'\"";
+
+const WORDLIST: &str = include_str!("wordlist.txt");
+
+pub fn wordlist() -> Vec<&'static str> {
+    WORDLIST
+        .lines()
+        .map(|word| word.trim())
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum GeneratorMode {
+    #[default]
+    Characters,
+    Passphrase,
+}
+
+//rules a store can impose on every password generated (and, later, saved) for it.
+//the policy lives in the store itself so that every client using the store agrees on it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct PasswordPolicy {
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_digits: bool,
+    pub require_symbols: bool,
+    pub exclude_lookalikes: bool,
+    //number of previous passwords of an entry that may not be reused
+    pub history_size: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum PolicyViolation {
+    TooShort { min_length: usize },
+    TooLong { max_length: usize },
+    MissingLowercase,
+    MissingUppercase,
+    MissingDigit,
+    MissingSymbol,
+    ContainsLookalike,
+    ReusedPassword { history_size: usize },
+}
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PolicyViolation::TooShort { min_length } => {
+                write!(f, "must be at least {} characters long", min_length)
+            }
+            PolicyViolation::TooLong { max_length } => {
+                write!(f, "must be at most {} characters long", max_length)
+            }
+            PolicyViolation::MissingLowercase => write!(f, "must contain a lowercase letter"),
+            PolicyViolation::MissingUppercase => write!(f, "must contain an uppercase letter"),
+            PolicyViolation::MissingDigit => write!(f, "must contain a digit"),
+            PolicyViolation::MissingSymbol => write!(f, "must contain a symbol"),
+            PolicyViolation::ContainsLookalike => {
+                write!(
+                    f,
+                    "must not contain look-alike characters ({})",
+                    LOOKALIKE_CHARACTERS
+                )
+            }
+            PolicyViolation::ReusedPassword { history_size } => {
+                write!(
+                    f,
+                    "must not reuse any of the last {} passwords",
+                    history_size
+                )
+            }
+        }
+    }
+}
+
+impl PasswordPolicy {
+    //every rule of the policy, phrased as the violation it would produce
+    pub fn rules(&self) -> Vec<PolicyViolation> {
+        let mut rules = vec![];
+        if let Some(min_length) = self.min_length {
+            rules.push(PolicyViolation::TooShort { min_length });
+        }
+        if let Some(max_length) = self.max_length {
+            rules.push(PolicyViolation::TooLong { max_length });
+        }
+        if self.require_lowercase {
+            rules.push(PolicyViolation::MissingLowercase);
+        }
+        if self.require_uppercase {
+            rules.push(PolicyViolation::MissingUppercase);
+        }
+        if self.require_digits {
+            rules.push(PolicyViolation::MissingDigit);
+        }
+        if self.require_symbols {
+            rules.push(PolicyViolation::MissingSymbol);
+        }
+        if self.exclude_lookalikes {
+            rules.push(PolicyViolation::ContainsLookalike);
+        }
+        if let Some(history_size) = self.history_size {
+            rules.push(PolicyViolation::ReusedPassword { history_size });
+        }
+        rules
+    }
+    //previous_passwords is expected to be ordered from the most recent one
+    pub fn validate(&self, password: &str, previous_passwords: &[String]) -> Vec<PolicyViolation> {
+        let length = password.chars().count();
+        self.rules()
+            .into_iter()
+            .filter(|rule| match rule {
+                PolicyViolation::TooShort { min_length } => length < *min_length,
+                PolicyViolation::TooLong { max_length } => length > *max_length,
+                PolicyViolation::MissingLowercase => !password.chars().any(|c| c.is_lowercase()),
+                PolicyViolation::MissingUppercase => !password.chars().any(|c| c.is_uppercase()),
+                PolicyViolation::MissingDigit => !password.chars().any(|c| c.is_ascii_digit()),
+                PolicyViolation::MissingSymbol => !password
+                    .chars()
+                    .any(|c| !c.is_alphanumeric() && !c.is_whitespace()),
+                PolicyViolation::ContainsLookalike => {
+                    password.chars().any(|c| LOOKALIKE_CHARACTERS.contains(c))
+                }
+                PolicyViolation::ReusedPassword { history_size } => previous_passwords
+                    .iter()
+                    .take(*history_size)
+                    .any(|previous| previous == password),
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct PasswordGeneratorOptions {
+    pub mode: GeneratorMode,
+    pub length: usize,
+    pub lowercase: bool,
+    pub uppercase: bool,
+    pub digits: bool,
+    pub symbols: bool,
+    pub exclude_lookalikes: bool,
+    pub word_count: usize,
+    pub separator: String,
+    pub capitalize: bool,
+    //only set by a policy. `length` is the least length of a passphrase, this one its most
+    pub max_length: Option<usize>,
+}
+impl Default for PasswordGeneratorOptions {
+    fn default() -> Self {
+        PasswordGeneratorOptions {
+            mode: GeneratorMode::Characters,
+            length: 20,
+            lowercase: true,
+            uppercase: true,
+            digits: true,
+            symbols: true,
+            exclude_lookalikes: true,
+            word_count: 5,
+            separator: "-".to_owned(),
+            capitalize: false,
+            max_length: None,
+        }
+    }
+}
+
+impl PasswordGeneratorOptions {
+    //tighten the options so that generated passwords always satisfy the given policy
+    pub fn apply_policy(mut self, policy: &PasswordPolicy) -> Self {
+        if let Some(min_length) = policy.min_length {
+            self.length = self.length.max(min_length);
+        }
+        if let Some(max_length) = policy.max_length {
+            self.length = self.length.min(max_length);
+            self.max_length = Some(self.max_length.map_or(max_length, |m| m.min(max_length)));
+        }
+        self.lowercase |= policy.require_lowercase;
+        self.uppercase |= policy.require_uppercase;
+        self.digits |= policy.require_digits;
+        self.symbols |= policy.require_symbols;
+        self.exclude_lookalikes |= policy.exclude_lookalikes;
+        if policy.require_uppercase {
+            self.capitalize = true;
+        }
+        self
+    }
+    fn character_classes(&self) -> Vec<Vec<char>> {
+        let mut classes = vec![];
+        for (enabled, characters) in [
+            (self.lowercase, LOWERCASE_CHARACTERS),
+            (self.uppercase, UPPERCASE_CHARACTERS),
+            (self.digits, DIGIT_CHARACTERS),
+            (self.symbols, SYMBOL_CHARACTERS),
+        ] {
+            if !enabled {
+                continue;
+            }
+            let class = characters
+                .chars()
+                .filter(|c| !self.exclude_lookalikes || !LOOKALIKE_CHARACTERS.contains(*c))
+                .collect::<Vec<char>>();
+            if !class.is_empty() {
+                classes.push(class);
+            }
+        }
+        classes
+    }
+}
+
+pub fn generate_password(options: &PasswordGeneratorOptions) -> Result<String, String> {
+    match options.mode {
+        GeneratorMode::Characters => generate_character_password(options),
+        GeneratorMode::Passphrase => generate_passphrase(options),
+    }
+}
+
+fn generate_character_password(options: &PasswordGeneratorOptions) -> Result<String, String> {
+    let classes = options.character_classes();
+    if classes.is_empty() {
+        return Err("at least one character class must be enabled".to_owned());
+    }
+    if options.length < classes.len() {
+        return Err(format!(
+            "length {} is too short to include {} character classes",
+            options.length,
+            classes.len()
+        ));
+    }
+    let mut rng = thread_rng();
+    //pick one character from every enabled class first so that each class is guaranteed to
+    //appear, then fill the rest from the union of all classes and shuffle
+    let mut password = classes
+        .iter()
+        .map(|class| *class.choose(&mut rng).unwrap())
+        .collect::<Vec<char>>();
+    let pool = classes.concat();
+    while password.len() < options.length {
+        password.push(*pool.choose(&mut rng).unwrap());
+    }
+    password.shuffle(&mut rng);
+    Ok(password.into_iter().collect())
+}
+
+fn generate_passphrase(options: &PasswordGeneratorOptions) -> Result<String, String> {
+    if options.word_count == 0 {
+        return Err("word count must be greater than zero".to_owned());
+    }
+    let is_lookalike = |c: char| options.exclude_lookalikes && LOOKALIKE_CHARACTERS.contains(c);
+    let capitalize = |word: &str| {
+        let mut chars = word.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+            None => word.to_owned(),
+        }
+    };
+    //a word is left out if it has a look-alike either as it is or capitalized
+    let words = wordlist()
+        .into_iter()
+        .filter(|word| {
+            !word
+                .chars()
+                .chain(capitalize(word).chars())
+                .any(is_lookalike)
+        })
+        .collect::<Vec<&str>>();
+    let digits = DIGIT_CHARACTERS
+        .chars()
+        .filter(|c| !is_lookalike(*c))
+        .collect::<Vec<char>>();
+    let mut rng = thread_rng();
+    let separator = if (options.symbols && !options.separator.chars().any(|c| !c.is_alphanumeric()))
+        || options.separator.chars().any(is_lookalike)
+    {
+        "-".to_owned()
+    } else {
+        options.separator.clone()
+    };
+    let separator_length = separator.chars().count();
+    //the digits appended at the end
+    let suffix_length = if options.digits {
+        separator_length + 2
+    } else {
+        0
+    };
+    let max_length = options.max_length.unwrap_or(usize::MAX);
+    let mut chosen: Vec<String> = vec![];
+    let mut current_length = suffix_length;
+    loop {
+        let word_length_left =
+            max_length
+                .saturating_sub(current_length)
+                .saturating_sub(if chosen.is_empty() {
+                    0
+                } else {
+                    separator_length
+                });
+        let fitting = words
+            .iter()
+            .filter(|word| word.chars().count() <= word_length_left)
+            .collect::<Vec<_>>();
+        let Some(word) = fitting.choose(&mut rng) else {
+            if chosen.len() < options.word_count {
+                return Err(format!(
+                    "{} words don't fit in {} characters",
+                    options.word_count, max_length
+                ));
+            }
+            break;
+        };
+        let word = if options.capitalize || (options.uppercase && chosen.is_empty()) {
+            capitalize(word)
+        } else {
+            word.to_string()
+        };
+        if !chosen.is_empty() {
+            current_length += separator_length;
+        }
+        current_length += word.chars().count();
+        chosen.push(word);
+        if chosen.len() >= options.word_count && current_length >= options.length.min(64) {
+            break;
+        }
+    }
+    let mut passphrase = chosen.join(&separator);
+    if options.digits {
+        passphrase.push_str(&separator);
+        for _ in 0..2 {
+            passphrase.push(*digits.choose(&mut rng).unwrap());
+        }
+    }
+    Ok(passphrase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strict_policy() -> PasswordPolicy {
+        PasswordPolicy {
+            min_length: Some(24),
+            max_length: Some(32),
+            require_lowercase: true,
+            require_uppercase: true,
+            require_digits: true,
+            require_symbols: true,
+            exclude_lookalikes: true,
+            history_size: Some(3),
+        }
+    }
+
+    fn passphrase_options() -> PasswordGeneratorOptions {
+        PasswordGeneratorOptions {
+            mode: GeneratorMode::Passphrase,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn apply_policy_raises_length_to_minimum() {
+        let options = PasswordGeneratorOptions {
+            length: 8,
+            ..Default::default()
+        }
+        .apply_policy(&strict_policy());
+        assert_eq!(options.length, 24);
+        assert_eq!(options.max_length, Some(32));
+    }
+
+    #[test]
+    fn apply_policy_lowers_length_to_maximum() {
+        let options = PasswordGeneratorOptions {
+            length: 64,
+            max_length: Some(40),
+            ..Default::default()
+        }
+        .apply_policy(&strict_policy());
+        assert_eq!(options.length, 32);
+        assert_eq!(options.max_length, Some(32));
+    }
+
+    #[test]
+    fn apply_policy_keeps_tighter_max_length() {
+        let options = PasswordGeneratorOptions {
+            max_length: Some(28),
+            ..Default::default()
+        }
+        .apply_policy(&strict_policy());
+        assert_eq!(options.max_length, Some(28));
+    }
+
+    #[test]
+    fn apply_policy_enables_required_classes() {
+        let options = PasswordGeneratorOptions {
+            lowercase: false,
+            uppercase: false,
+            digits: false,
+            symbols: false,
+            exclude_lookalikes: false,
+            ..Default::default()
+        }
+        .apply_policy(&strict_policy());
+        assert!(options.lowercase);
+        assert!(options.uppercase);
+        assert!(options.digits);
+        assert!(options.symbols);
+        assert!(options.exclude_lookalikes);
+        assert!(options.capitalize);
+    }
+
+    #[test]
+    fn apply_policy_never_disables_classes() {
+        let options = PasswordGeneratorOptions::default().apply_policy(&PasswordPolicy::default());
+        assert_eq!(options, PasswordGeneratorOptions::default());
+    }
+
+    #[test]
+    fn generated_password_satisfies_policy() {
+        let policy = strict_policy();
+        let options = PasswordGeneratorOptions {
+            length: 8,
+            lowercase: false,
+            symbols: false,
+            ..Default::default()
+        }
+        .apply_policy(&policy);
+        for _ in 0..50 {
+            let password = generate_password(&options).unwrap();
+            assert_eq!(password.chars().count(), 24);
+            assert_eq!(policy.validate(&password, &[]), vec![]);
+        }
+    }
+
+    #[test]
+    fn generated_password_contains_every_class() {
+        let options = PasswordGeneratorOptions {
+            length: 4,
+            ..Default::default()
+        };
+        for _ in 0..50 {
+            let password = generate_password(&options).unwrap();
+            assert!(password.chars().any(|c| c.is_ascii_lowercase()));
+            assert!(password.chars().any(|c| c.is_ascii_uppercase()));
+            assert!(password.chars().any(|c| c.is_ascii_digit()));
+            assert!(password.chars().any(|c| SYMBOL_CHARACTERS.contains(c)));
+            assert!(!password.chars().any(|c| LOOKALIKE_CHARACTERS.contains(c)));
+        }
+    }
+
+    #[test]
+    fn password_shorter_than_classes_is_rejected() {
+        let options = PasswordGeneratorOptions {
+            length: 3,
+            ..Default::default()
+        };
+        assert!(generate_password(&options).is_err());
+    }
+
+    #[test]
+    fn password_without_classes_is_rejected() {
+        let options = PasswordGeneratorOptions {
+            lowercase: false,
+            uppercase: false,
+            digits: false,
+            symbols: false,
+            ..Default::default()
+        };
+        assert!(generate_password(&options).is_err());
+    }
+
+    #[test]
+    fn validate_reports_each_violation() {
+        let policy = strict_policy();
+        let previous = vec![
+            "first".to_owned(),
+            "second".to_owned(),
+            "third".to_owned(),
+            "Ancient-Secret-Phrase-22-xyz".to_owned(),
+        ];
+        assert_eq!(
+            policy.validate("quick", &previous),
+            vec![
+                PolicyViolation::TooShort { min_length: 24 },
+                PolicyViolation::MissingUppercase,
+                PolicyViolation::MissingDigit,
+                PolicyViolation::MissingSymbol,
+            ]
+        );
+        assert_eq!(
+            policy.validate(&"Ab3$".repeat(9), &previous),
+            vec![PolicyViolation::TooLong { max_length: 32 }]
+        );
+        assert_eq!(
+            policy.validate("ABCDEFGHJKMNPQRSTUVWXYZ2$", &previous),
+            vec![PolicyViolation::MissingLowercase]
+        );
+        assert_eq!(
+            policy.validate("Il1-abcdefghijkmnpqrstuvwx", &previous),
+            vec![PolicyViolation::ContainsLookalike]
+        );
+        //only the last three passwords count
+        assert_eq!(
+            policy.validate("Ancient-Secret-Phrase-22-xyz", &previous),
+            vec![]
+        );
+        let previous = vec!["Recent-Secret-Phrase-22-xyz".to_owned()];
+        assert_eq!(
+            policy.validate("Recent-Secret-Phrase-22-xyz", &previous),
+            vec![PolicyViolation::ReusedPassword { history_size: 3 }]
+        );
+    }
+
+    #[test]
+    fn passphrase_stays_within_max_length() {
+        let options = PasswordGeneratorOptions {
+            length: 8,
+            word_count: 3,
+            max_length: Some(24),
+            ..passphrase_options()
+        };
+        for _ in 0..50 {
+            let passphrase = generate_password(&options).unwrap();
+            assert!(passphrase.chars().count() <= 24, "{}", passphrase);
+            assert!(passphrase.chars().count() >= 8, "{}", passphrase);
+        }
+    }
+
+    #[test]
+    fn passphrase_under_policy_satisfies_it() {
+        let policy = PasswordPolicy {
+            min_length: Some(20),
+            max_length: Some(30),
+            require_uppercase: true,
+            require_digits: true,
+            exclude_lookalikes: true,
+            ..Default::default()
+        };
+        let options = PasswordGeneratorOptions {
+            word_count: 3,
+            ..passphrase_options()
+        }
+        .apply_policy(&policy);
+        for _ in 0..50 {
+            let passphrase = generate_password(&options).unwrap();
+            assert_eq!(policy.validate(&passphrase, &[]), vec![], "{}", passphrase);
+        }
+    }
+
+    #[test]
+    fn passphrase_leaves_out_lookalikes() {
+        let options = PasswordGeneratorOptions {
+            capitalize: true,
+            ..passphrase_options()
+        };
+        for _ in 0..50 {
+            let passphrase = generate_password(&options).unwrap();
+            assert!(
+                !passphrase.chars().any(|c| LOOKALIKE_CHARACTERS.contains(c)),
+                "{}",
+                passphrase
+            );
+        }
+    }
+
+    #[test]
+    fn passphrase_that_cannot_fit_is_rejected() {
+        let options = PasswordGeneratorOptions {
+            word_count: 5,
+            max_length: Some(10),
+            ..passphrase_options()
+        };
+        assert!(generate_password(&options).is_err());
+    }
+
+    #[test]
+    fn passphrase_needs_a_word() {
+        let options = PasswordGeneratorOptions {
+            word_count: 0,
+            ..passphrase_options()
+        };
+        assert!(generate_password(&options).is_err());
+    }
+}
@@ -0,0 +1,293 @@
+use serde::{Deserialize, Serialize};
+
+use crate::password_generator::wordlist;
+
+//most common passwords from public breach corpora, most common first
+const COMMON_PASSWORDS: &[&str] = &[
+    "123456",
+    "password",
+    "123456789",
+    "12345678",
+    "12345",
+    "qwerty",
+    "1234567",
+    "111111",
+    "1234567890",
+    "123123",
+    "abc123",
+    "1234",
+    "password1",
+    "iloveyou",
+    "1q2w3e4r",
+    "000000",
+    "qwerty123",
+    "zaq12wsx",
+    "dragon",
+    "sunshine",
+    "princess",
+    "letmein",
+    "654321",
+    "monkey",
+    "27653",
+    "1qaz2wsx",
+    "123321",
+    "qwertyuiop",
+    "superman",
+    "asdfghjkl",
+    "trustno1",
+    "football",
+    "baseball",
+    "welcome",
+    "admin",
+    "login",
+    "master",
+    "hello",
+    "freedom",
+    "whatever",
+    "qazwsx",
+    "shadow",
+    "michael",
+    "jennifer",
+    "charlie",
+    "passw0rd",
+    "starwars",
+    "mustang",
+    "access",
+    "secret",
+    "changeme",
+    "default",
+    "root",
+    "test",
+    "guest",
+    "pass",
+    "p@ssw0rd",
+    "p@ssword",
+];
+const KEYBOARD_ROWS: &[&str] = &[
+    "`1234567890-=",
+    "qwertyuiop[]\\",
+    "asdfghjkl;'",
+    "zxcvbnm,./",
+    "1qaz2wsx3edc4rfv5tgb6yhn7ujm8ik,9ol.0p;/",
+];
+const MIN_PATTERN_LENGTH: usize = 3;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StrengthPattern {
+    CommonPassword,
+    DictionaryWord,
+    Sequence,
+    Repeat,
+    KeyboardPattern,
+    Year,
+    Bruteforce,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PasswordStrength {
+    //0 (too guessable) to 4 (very unguessable), on the same scale as zxcvbn
+    pub score: u8,
+    pub guesses_log10: f64,
+    //patterns that made the password easier to guess
+    pub patterns: Vec<StrengthPattern>,
+}
+
+//a rough, zxcvbn-style estimate: the password is split greedily into the most guessable
+//patterns found at each position and the guesses needed for every part are multiplied
+pub fn estimate_strength(password: &str) -> PasswordStrength {
+    let chars = password.chars().collect::<Vec<char>>();
+    if chars.is_empty() {
+        return PasswordStrength {
+            score: 0,
+            guesses_log10: 0.0,
+            patterns: vec![],
+        };
+    }
+    let lowercase = password.to_lowercase();
+    if let Some(rank) = COMMON_PASSWORDS
+        .iter()
+        .position(|common| *common == lowercase || *common == unleet(&lowercase))
+    {
+        return PasswordStrength {
+            score: 0,
+            guesses_log10: ((rank + 1) as f64).log10(),
+            patterns: vec![StrengthPattern::CommonPassword],
+        };
+    }
+    //lowercased char by char so that positions keep matching the original password
+    let lowercase = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect::<Vec<char>>();
+    let cardinality = bruteforce_cardinality(&chars) as f64;
+    let words = wordlist();
+    let mut guesses_log10 = 0.0;
+    let mut patterns = vec![];
+    let mut position = 0;
+    while position < chars.len() {
+        let rest = &lowercase[position..];
+        let matched = [
+            match_dictionary(rest, &words),
+            match_repeat(rest),
+            match_sequence(rest),
+            match_keyboard(rest),
+            match_year(rest),
+        ]
+        .into_iter()
+        .flatten()
+        //prefer the longest pattern, then the cheapest one
+        .max_by(|(length, guesses, _), (other_length, other_guesses, _)| {
+            length
+                .cmp(other_length)
+                .then(other_guesses.total_cmp(guesses))
+        });
+        match matched {
+            Some((length, guesses, pattern)) => {
+                guesses_log10 += guesses.log10();
+                if !patterns.contains(&pattern) {
+                    patterns.push(pattern);
+                }
+                position += length;
+            }
+            None => {
+                guesses_log10 += cardinality.log10();
+                position += 1;
+            }
+        }
+    }
+    if patterns.is_empty() {
+        patterns.push(StrengthPattern::Bruteforce);
+    }
+    PasswordStrength {
+        score: score_from_guesses(guesses_log10),
+        guesses_log10,
+        patterns,
+    }
+}
+
+fn score_from_guesses(guesses_log10: f64) -> u8 {
+    match guesses_log10 {
+        g if g < 3.0 => 0,
+        g if g < 6.0 => 1,
+        g if g < 8.0 => 2,
+        g if g < 10.0 => 3,
+        _ => 4,
+    }
+}
+
+fn unleet(password: &str) -> String {
+    password
+        .chars()
+        .map(|c| match c {
+            '4' | '@' => 'a',
+            '3' => 'e',
+            '1' | '!' => 'i',
+            '0' => 'o',
+            '$' | '5' => 's',
+            '7' => 't',
+            c => c,
+        })
+        .collect()
+}
+
+fn bruteforce_cardinality(chars: &[char]) -> usize {
+    let mut cardinality = 0;
+    if chars.iter().any(|c| c.is_ascii_lowercase()) {
+        cardinality += 26;
+    }
+    if chars.iter().any(|c| c.is_ascii_uppercase()) {
+        cardinality += 26;
+    }
+    if chars.iter().any(|c| c.is_ascii_digit()) {
+        cardinality += 10;
+    }
+    if chars.iter().any(|c| c.is_ascii_punctuation() || *c == ' ') {
+        cardinality += 33;
+    }
+    if chars.iter().any(|c| !c.is_ascii()) {
+        cardinality += 100;
+    }
+    cardinality.max(10)
+}
+
+//each matcher returns (matched length, guesses for the match, pattern)
+fn match_dictionary(rest: &[char], words: &[&str]) -> Option<(usize, f64, StrengthPattern)> {
+    let unleeted = unleet(&rest.iter().collect::<String>())
+        .chars()
+        .collect::<Vec<char>>();
+    //the wordlist is not sorted by frequency, so every word costs as much as the whole list
+    words
+        .iter()
+        .filter(|word| word.len() > MIN_PATTERN_LENGTH)
+        .filter(|word| {
+            let word = word.chars().collect::<Vec<char>>();
+            unleeted.starts_with(&word)
+        })
+        .map(|word| {
+            (
+                word.chars().count(),
+                words.len() as f64,
+                StrengthPattern::DictionaryWord,
+            )
+        })
+        .max_by_key(|(length, _, _)| *length)
+}
+
+fn match_repeat(rest: &[char]) -> Option<(usize, f64, StrengthPattern)> {
+    let length = rest.iter().take_while(|c| **c == rest[0]).count();
+    (length >= MIN_PATTERN_LENGTH).then_some((
+        length,
+        10.0 * length as f64,
+        StrengthPattern::Repeat,
+    ))
+}
+
+fn match_sequence(rest: &[char]) -> Option<(usize, f64, StrengthPattern)> {
+    if rest.len() < 2 {
+        return None;
+    }
+    let step = rest[1] as i64 - rest[0] as i64;
+    if step.abs() != 1 {
+        return None;
+    }
+    let length = 1 + rest
+        .windows(2)
+        .take_while(|pair| pair[1] as i64 - pair[0] as i64 == step)
+        .count();
+    (length >= MIN_PATTERN_LENGTH).then_some((
+        length,
+        20.0 * length as f64,
+        StrengthPattern::Sequence,
+    ))
+}
+
+fn match_keyboard(rest: &[char]) -> Option<(usize, f64, StrengthPattern)> {
+    KEYBOARD_ROWS
+        .iter()
+        .filter_map(|row| {
+            let row = row.chars().collect::<Vec<char>>();
+            let start = row.iter().position(|c| *c == rest[0])?;
+            let length = rest
+                .iter()
+                .zip(row[start..].iter())
+                .take_while(|(a, b)| a == b)
+                .count();
+            (length > MIN_PATTERN_LENGTH).then_some((
+                length,
+                40.0 * length as f64,
+                StrengthPattern::KeyboardPattern,
+            ))
+        })
+        .max_by_key(|(length, _, _)| *length)
+}
+
+fn match_year(rest: &[char]) -> Option<(usize, f64, StrengthPattern)> {
+    let year = rest.iter().take(4).collect::<String>();
+    let year = year.parse::<u32>().ok()?;
+    (year.to_string().len() == 4 && (1900..2100).contains(&year)).then_some((
+        4,
+        200.0,
+        StrengthPattern::Year,
+    ))
+}
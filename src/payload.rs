@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+use std::ops::Deref;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::audit::{AuditOptions, AuditReport};
+use crate::password_generator::{PasswordGeneratorOptions, PasswordPolicy, PolicyViolation};
+use crate::response::ErrorCode;
+use crate::types::{Account, Key};
+use crate::url_match::{AccountUrl, MatchMode, URL_MATCH_FIELD};
+
+//version of the payload representations below. a payload of another version is rejected when it
+//is deserialized, so a native app or extension part built from another version of this crate
+//fails loudly instead of misreading fields
+pub const PAYLOAD_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(try_from = "UncheckedVersioned<T>")]
+#[serde(bound(deserialize = "T: Deserialize<'de>"))]
+pub struct Versioned<T> {
+    pub version: u32,
+    pub payload: T,
+}
+
+#[derive(Deserialize)]
+struct UncheckedVersioned<T> {
+    version: u32,
+    payload: T,
+}
+
+impl<T> TryFrom<UncheckedVersioned<T>> for Versioned<T> {
+    type Error = String;
+
+    fn try_from(unchecked: UncheckedVersioned<T>) -> Result<Self, Self::Error> {
+        if unchecked.version != PAYLOAD_VERSION {
+            return Err(format!(
+                "unsupported payload version {}, expected {}",
+                unchecked.version, PAYLOAD_VERSION
+            ));
+        }
+        Ok(Versioned {
+            version: unchecked.version,
+            payload: unchecked.payload,
+        })
+    }
+}
+
+impl<T> From<T> for Versioned<T> {
+    fn from(payload: T) -> Self {
+        Versioned {
+            version: PAYLOAD_VERSION,
+            payload,
+        }
+    }
+}
+
+impl<T> Deref for Versioned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.payload
+    }
+}
+
+//stores and keys known to the service worker. the content script is also told about the default
+//store
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct InitPayload {
+    pub store_ids: Vec<String>,
+    pub keys: Vec<Key>,
+    //several stores can be unlocked at once, each one is locked on its own
+    #[serde(default)]
+    pub unlocked_store_ids: Vec<String>,
+    #[serde(default)]
+    pub default_store_id: Option<String>,
+    #[serde(default)]
+    pub default_store_available: bool,
+}
+
+//a store that was created or deleted, or failed to be
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct StorePayload {
+    pub store_id: String,
+    #[serde(default)]
+    pub error: Option<Value>,
+}
+
+//a store that was unlocked
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct LoginPayload {
+    pub store_id: String,
+    #[serde(default)]
+    pub is_default: bool,
+}
+
+//the store that was locked, none when every store was
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct LogoutPayload {
+    #[serde(default)]
+    pub store_id: Option<String>,
+}
+
+//an account as it is after being created or edited
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct AccountPayload {
+    pub account: Account,
+}
+
+//an account that was deleted
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct DeletePayload {
+    pub id: String,
+}
+
+//why a request failed. a password refused by the store's policy lists the rules it broke
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct FailurePayload {
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub code: Option<ErrorCode>,
+    #[serde(default)]
+    pub policy_violations: Vec<PolicyViolation>,
+}
+impl FailurePayload {
+    pub fn new(message: impl Into<String>) -> Self {
+        FailurePayload {
+            message: Some(message.into()),
+            ..Default::default()
+        }
+    }
+}
+
+//a generated password along with the options it was generated with, after the store's policy
+//was applied to them
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct GeneratedPasswordPayload {
+    pub password: String,
+    pub options: PasswordGeneratorOptions,
+    #[serde(default)]
+    pub password_policy: Option<PasswordPolicy>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct AuditPayload {
+    pub report: AuditReport,
+    pub options: AuditOptions,
+}
+
+//the account picked on the previous step of a multi-step login, if it is still remembered
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct LoginStepPayload {
+    #[serde(default)]
+    pub account_id: Option<String>,
+}
+
+//credentials submitted on the previous page of a tab. the account id is set for a password
+//change, along with the store the account is in
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct PendingCredentialPayload {
+    pub domain: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    pub password: String,
+    #[serde(default)]
+    pub account_id: Option<String>,
+    #[serde(default)]
+    pub store_id: Option<String>,
+}
+
+//accounts of a store along with the settings the store holds for them
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct FetchPayload {
+    pub accounts: Vec<Account>,
+    #[serde(default)]
+    pub sub_stores: Vec<String>,
+    #[serde(default)]
+    pub password_policy: Option<PasswordPolicy>,
+    //why the store's policy is ignored, when it has one that isn't signed or doesn't parse
+    #[serde(default)]
+    pub password_policy_error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FieldChange<T> {
+    #[serde(default)]
+    pub old: Option<T>,
+    #[serde(default)]
+    pub new: Option<T>,
+}
+
+//fields of an account changed by an edit, as reported by the native app
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct UpdatedFields {
+    #[serde(default)]
+    pub username: Option<FieldChange<String>>,
+    #[serde(default)]
+    pub password: Option<FieldChange<String>>,
+    #[serde(default)]
+    pub domain: Option<FieldChange<String>>,
+    #[serde(default)]
+    pub note: Option<FieldChange<String>>,
+    #[serde(default)]
+    pub path: Option<FieldChange<String>>,
+    #[serde(default)]
+    pub urls: Option<FieldChange<Vec<AccountUrl>>>,
+    #[serde(default)]
+    pub url_match: Option<FieldChange<MatchMode>>,
+    //custom fields, along with the types the extension keeps for them
+    #[serde(flatten)]
+    pub other: HashMap<String, Value>,
+}
+
+impl UpdatedFields {
+    pub fn apply_to(&self, account: &mut Account) {
+        fn new_value<T: Clone>(change: &Option<FieldChange<T>>) -> Option<T> {
+            change.as_ref().and_then(|change| change.new.clone())
+        }
+        if let Some(username) = new_value(&self.username) {
+            account.username = username;
+        }
+        if let Some(password) = new_value(&self.password) {
+            account.set_password(Some(password));
+        }
+        if let Some(domain) = new_value(&self.domain) {
+            account.domain = Some(domain);
+        }
+        if let Some(note) = new_value(&self.note) {
+            account.note = Some(note);
+        }
+        if let Some(path) = new_value(&self.path) {
+            account.path = Some(path);
+        }
+        if let Some(urls) = new_value(&self.urls) {
+            account.urls = urls;
+        }
+        if let Some(url_match) = new_value(&self.url_match) {
+            account
+                .custom_fields
+                .get_or_insert_with(HashMap::new)
+                .insert(URL_MATCH_FIELD.to_owned(), json!(url_match));
+        }
+        for (field, change) in self.other.iter() {
+            let Ok(change) = serde_json::from_value::<FieldChange<Value>>(change.clone()) else {
+                continue;
+            };
+            let custom_fields = account.custom_fields.get_or_insert_with(HashMap::new);
+            match (change.old, change.new) {
+                (_, Some(new)) => {
+                    custom_fields.insert(field.clone(), new);
+                }
+                (Some(_), None) => {
+                    custom_fields.remove(field);
+                }
+                (None, None) => {}
+            }
+        }
+    }
+}
@@ -14,24 +14,49 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum SessionEventType {
-    Create,
+    Create(Versioned<AccountPayload>),
     NativeAppConnectionError,
-    Update,
-    Refreshed,
-    Delete,
-    CreationFailed,
-    Login,
-    Logout,
+    Update(Versioned<AccountPayload>),
+    Refreshed(Versioned<FetchPayload>),
+    Delete(Versioned<DeletePayload>),
+    CreationFailed(Versioned<FailurePayload>),
+    UpdateFailed(Versioned<FailurePayload>),
+    Login(Versioned<LoginPayload>),
+    Logout(Versioned<LogoutPayload>),
     LogoutError,
     LoginError,
     Search,
-    Init(HashMap<DataFieldType, Value>),
+    Init(Versioned<InitPayload>),
     Error,
     CreateStore,
-    StoreCreated(HashMap<DataFieldType, Value>, String),
-    StoreCreationFailed(HashMap<DataFieldType, Value>, String),
-    StoreDeleted(HashMap<DataFieldType, Value>, String),
-    StoreDeletionFailed(HashMap<DataFieldType, Value>, String),
+    StoreCreated(Versioned<StorePayload>),
+    StoreCreationFailed(Versioned<StorePayload>),
+    StoreDeleted(Versioned<StorePayload>),
+    StoreDeletionFailed(Versioned<StorePayload>),
+    NativeAppConnection(NativeConnectionState),
+    RequestTimedOut(RequestTimeout),
+}
+
+//a request the native app did not answer before its deadline
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RequestTimeout {
+    pub request_type: String,
+    pub store_id: Option<String>,
+    pub timeout_ms: u32,
+}
+
+//state of the service worker's connection to the native app
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NativeConnectionState {
+    #[default]
+    Connected,
+    Reconnecting {
+        attempt: u32,
+        retry_in_ms: u32,
+    },
+    //reconnecting failed too many times in a row. attempts go on, at the longest delay
+    Disconnected,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
@@ -95,6 +120,9 @@ pub enum DataFieldType {
     CreateStore,
     StoreIDList,
     ParentStoreId,
+    TotpPeriod,
+    TotpRemaining,
+    KeepPasswordHistory,
 }
 impl fmt::Display for DataFieldType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -112,8 +140,54 @@ pub struct SessionEvent {
     pub acknowledgement: Option<String>,
     pub store_id_index: Option<String>,
 }
+impl SessionEvent {
+    //the event as the content script of a frame at `url` may see it. content scripts run in every
+    //frame, cross-origin iframes included, so a frame only gets the accounts its own url matches.
+    //an update of an account that does not match becomes its deletion, in case it matched before
+    pub fn for_frame(
+        &self,
+        url: Option<&str>,
+        equivalent_domains: &[Vec<String>],
+    ) -> Option<SessionEvent> {
+        let matches = |account: &Account| {
+            url.is_some_and(|url| account_matches_url(account, url, equivalent_domains))
+        };
+        let event_type = match &self.event_type {
+            SessionEventType::Refreshed(fetched) => {
+                let mut fetched = fetched.payload.clone();
+                fetched.accounts.retain(|account| matches(account));
+                SessionEventType::Refreshed(fetched.into())
+            }
+            SessionEventType::Create(created) if !matches(&created.account) => return None,
+            SessionEventType::Update(updated) if !matches(&updated.account) => {
+                SessionEventType::Delete(
+                    DeletePayload {
+                        id: updated.account.id.clone(),
+                    }
+                    .into(),
+                )
+            }
+            event_type => event_type.clone(),
+        };
+        Some(SessionEvent {
+            event_type,
+            ..self.clone()
+        })
+    }
+}
 
-use crate::{types::Resource, util::create_request_acknowledgement};
+use crate::{
+    audit::AuditOptions,
+    clipboard::CopiedDigest,
+    password_generator::PasswordGeneratorOptions,
+    payload::{
+        AccountPayload, DeletePayload, FailurePayload, FetchPayload, InitPayload, LoginPayload,
+        LogoutPayload, StorePayload, Versioned,
+    },
+    types::{Account, Resource},
+    url_match::account_matches_url,
+    util::create_request_acknowledgement,
+};
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(tag = "type", rename = "get")]
 pub struct GetRequest {
@@ -172,7 +246,6 @@ pub struct FetchRequest {
 #[serde(tag = "type", rename = "login")]
 pub struct LoginRequest {
     pub store_id: Option<String>,
-    pub prev_store_id: Option<String>,
     pub is_default: bool,
     pub acknowledgement: Option<String>,
     #[serde(flatten)]
@@ -235,6 +308,127 @@ pub struct DeleteStoreRequset {
     #[serde(flatten)]
     pub header: Option<HashMap<String, String>>,
 }
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename = "generate_password")]
+pub struct GeneratePasswordRequest {
+    pub store_id: Option<String>,
+    pub options: Option<PasswordGeneratorOptions>,
+    pub acknowledgement: Option<String>,
+    #[serde(flatten)]
+    pub header: Option<HashMap<String, String>>,
+}
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename = "audit")]
+pub struct AuditRequest {
+    pub store_id: Option<String>,
+    pub options: Option<AuditOptions>,
+    pub acknowledgement: Option<String>,
+    #[serde(flatten)]
+    pub header: Option<HashMap<String, String>>,
+}
+//the account picked on one step of a multi-step login, such as a username page followed by a
+//password page. without account_id it asks for the account picked on the previous step instead
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename = "login_step")]
+pub struct LoginStepRequest {
+    pub store_id: Option<String>,
+    pub domain: String,
+    pub account_id: Option<String>,
+    pub acknowledgement: Option<String>,
+    #[serde(flatten)]
+    pub header: Option<HashMap<String, String>>,
+}
+//credentials captured from a submitted form, kept by the service worker until the page that
+//follows the submission asks for them. without a password it takes the captured credentials
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename = "pending_credential")]
+pub struct PendingCredentialRequest {
+    pub store_id: Option<String>,
+    pub domain: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    //set when the credentials come from a change-password form of an existing account
+    pub account_id: Option<String>,
+    pub acknowledgement: Option<String>,
+    #[serde(flatten)]
+    pub header: Option<HashMap<String, String>>,
+}
+//sent by each content frame whenever it finds or loses a login form, so fill requests can be
+//routed to the frame that actually holds the form
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename = "frame_status")]
+pub struct FrameStatusRequest {
+    pub store_id: Option<String>,
+    pub has_login_form: bool,
+    pub acknowledgement: Option<String>,
+    #[serde(flatten)]
+    pub header: Option<HashMap<String, String>>,
+}
+//a value just copied to the clipboard, sent as a digest only. the service worker clears the
+//clipboard once the delay set by the user has passed, unless something else was copied since
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename = "clipboard_clear")]
+pub struct ClipboardClearRequest {
+    pub store_id: Option<String>,
+    pub copied: CopiedDigest,
+    pub acknowledgement: Option<String>,
+    #[serde(flatten)]
+    pub header: Option<HashMap<String, String>>,
+}
+//fills the given account into the login form of the active tab. the service worker attaches the
+//account before forwarding the request to the frames whose origin matches its domain
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename = "fill")]
+pub struct FillRequest {
+    pub store_id: Option<String>,
+    pub account_id: String,
+    pub account: Option<Account>,
+    pub acknowledgement: Option<String>,
+    #[serde(flatten)]
+    pub header: Option<HashMap<String, String>>,
+}
+//keyboard shortcuts declared under `commands` in the manifest, named as they are there
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum BrowserCommand {
+    //fill the best matching account, or open the account picker when there is no clear match
+    FillLogin,
+    //fill the account that comes after the one currently in the form
+    CycleLogin,
+}
+//a keyboard shortcut pressed in a tab, forwarded by the service worker to the frames of the tab
+//that hold a login form
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename = "command")]
+pub struct CommandRequest {
+    pub store_id: Option<String>,
+    pub command: BrowserCommand,
+    pub acknowledgement: Option<String>,
+    #[serde(flatten)]
+    pub header: Option<HashMap<String, String>>,
+}
+//entries of the right-click menu. account entries are named `<action>|<store id>|<account id>`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ContextMenuAction {
+    FillUsername,
+    FillPassword,
+    GeneratePassword,
+    CopyTotp,
+    SaveLogin,
+}
+//a context menu entry clicked in a frame, sent by the service worker to the content script of
+//that frame together with the account the entry is for
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename = "context_menu")]
+pub struct ContextMenuRequest {
+    pub store_id: Option<String>,
+    pub action: ContextMenuAction,
+    pub account: Option<Account>,
+    pub acknowledgement: Option<String>,
+    #[serde(flatten)]
+    pub header: Option<HashMap<String, String>>,
+}
 macro_rules! request_enum_trait_impl {
     ($($t:ty)*) => ($(
         impl RequestEnumTrait for $t {
@@ -333,12 +527,21 @@ request_enum_trait_impl!(InitRequest);
 request_enum_trait_impl!(CreateRequest);
 request_enum_trait_impl!(DeleteRequest);
 request_enum_trait_impl!(EditRequest);
+request_enum_trait_impl!(GeneratePasswordRequest);
+request_enum_trait_impl!(AuditRequest);
+request_enum_trait_impl!(LoginStepRequest);
+request_enum_trait_impl!(PendingCredentialRequest);
+request_enum_trait_impl!(FrameStatusRequest);
+request_enum_trait_impl!(ClipboardClearRequest);
+request_enum_trait_impl!(FillRequest);
+request_enum_trait_impl!(CommandRequest);
+request_enum_trait_impl!(ContextMenuRequest);
 macro_rules! into_js_value_impl {
     ($($t:ty)*) => ($(
-        impl Into<JsValue> for $t {
+        impl From<$t> for JsValue {
             #[inline]
-            fn into(self)->JsValue {
-                <JsValue as JsValueSerdeExt>::from_serde(&self).unwrap()
+            fn from(value: $t) -> Self {
+                <JsValue as JsValueSerdeExt>::from_serde(&value).unwrap()
             }
         }
 
@@ -355,6 +558,15 @@ into_js_value_impl!(CreateRequest);
 into_js_value_impl!(CreateStoreRequest);
 into_js_value_impl!(DeleteRequest);
 into_js_value_impl!(EditRequest);
+into_js_value_impl!(GeneratePasswordRequest);
+into_js_value_impl!(AuditRequest);
+into_js_value_impl!(LoginStepRequest);
+into_js_value_impl!(PendingCredentialRequest);
+into_js_value_impl!(FrameStatusRequest);
+into_js_value_impl!(ClipboardClearRequest);
+into_js_value_impl!(FillRequest);
+into_js_value_impl!(CommandRequest);
+into_js_value_impl!(ContextMenuRequest);
 into_js_value_impl!(SessionEvent);
 
 #[derive(Serialize, Deserialize, Debug, Clone, Display, PartialEq)]
@@ -384,6 +596,24 @@ pub enum RequestEnum {
     CreateStore(CreateStoreRequest),
     #[serde(rename = "delete_store")]
     DeleteStore(DeleteStoreRequset),
+    #[serde(rename = "generate_password")]
+    GeneratePassword(GeneratePasswordRequest),
+    #[serde(rename = "audit")]
+    Audit(AuditRequest),
+    #[serde(rename = "login_step")]
+    LoginStep(LoginStepRequest),
+    #[serde(rename = "pending_credential")]
+    PendingCredential(PendingCredentialRequest),
+    #[serde(rename = "frame_status")]
+    FrameStatus(FrameStatusRequest),
+    #[serde(rename = "clipboard_clear")]
+    ClipboardClear(ClipboardClearRequest),
+    #[serde(rename = "fill")]
+    Fill(FillRequest),
+    #[serde(rename = "command")]
+    Command(CommandRequest),
+    #[serde(rename = "context_menu")]
+    ContextMenu(ContextMenuRequest),
     #[serde(rename = "session_event")]
     SessionEventRequest(SessionEvent),
 }
@@ -430,6 +660,7 @@ impl RequestEnum {
             store_id,
         })
     }
+    #[allow(clippy::too_many_arguments)]
     pub fn create_create_request(
         store_id: Option<String>,
         username: Option<String>,
@@ -504,13 +735,11 @@ impl RequestEnum {
     pub fn create_login_request(
         acknowledgement: Option<String>,
         store_id: Option<String>,
-        prev_store_id: Option<String>,
         is_default: bool,
     ) -> RequestEnum {
         RequestEnum::Login(LoginRequest {
             is_default,
             store_id,
-            prev_store_id,
             acknowledgement: {
                 if acknowledgement.is_some() {
                     acknowledgement
@@ -566,10 +795,10 @@ impl RequestEnum {
         })
     }
     pub fn create_session_event_request(
-        acknowledgement: Option<String>,
+        _acknowledgement: Option<String>,
         session_event: SessionEvent,
-        store_id: Option<String>,
-        header: Option<HashMap<String, String>>,
+        _store_id: Option<String>,
+        _header: Option<HashMap<String, String>>,
     ) -> RequestEnum {
         RequestEnum::SessionEventRequest(session_event)
     }
@@ -591,6 +820,7 @@ impl RequestEnum {
             store_id: None,
         })
     }
+    #[allow(clippy::too_many_arguments)]
     pub fn create_create_store_request(
         parent_store: Option<String>,
         store_name: String,
@@ -637,6 +867,179 @@ impl RequestEnum {
             header,
         })
     }
+    pub fn create_generate_password_request(
+        store_id: Option<String>,
+        options: Option<PasswordGeneratorOptions>,
+        acknowledgement: Option<String>,
+        header: Option<HashMap<String, String>>,
+    ) -> RequestEnum {
+        RequestEnum::GeneratePassword(GeneratePasswordRequest {
+            store_id,
+            options,
+            acknowledgement: {
+                if acknowledgement.is_some() {
+                    acknowledgement
+                } else {
+                    Some(create_request_acknowledgement())
+                }
+            },
+            header,
+        })
+    }
+    pub fn create_audit_request(
+        store_id: Option<String>,
+        options: Option<AuditOptions>,
+        acknowledgement: Option<String>,
+        header: Option<HashMap<String, String>>,
+    ) -> RequestEnum {
+        RequestEnum::Audit(AuditRequest {
+            store_id,
+            options,
+            acknowledgement: {
+                if acknowledgement.is_some() {
+                    acknowledgement
+                } else {
+                    Some(create_request_acknowledgement())
+                }
+            },
+            header,
+        })
+    }
+    pub fn create_login_step_request(
+        store_id: Option<String>,
+        domain: String,
+        account_id: Option<String>,
+        acknowledgement: Option<String>,
+    ) -> RequestEnum {
+        RequestEnum::LoginStep(LoginStepRequest {
+            store_id,
+            domain,
+            account_id,
+            acknowledgement: {
+                if acknowledgement.is_some() {
+                    acknowledgement
+                } else {
+                    Some(create_request_acknowledgement())
+                }
+            },
+            header: None,
+        })
+    }
+    pub fn create_pending_credential_request(
+        store_id: Option<String>,
+        domain: String,
+        username: Option<String>,
+        password: Option<String>,
+        account_id: Option<String>,
+        acknowledgement: Option<String>,
+    ) -> RequestEnum {
+        RequestEnum::PendingCredential(PendingCredentialRequest {
+            store_id,
+            domain,
+            username,
+            password,
+            account_id,
+            acknowledgement: {
+                if acknowledgement.is_some() {
+                    acknowledgement
+                } else {
+                    Some(create_request_acknowledgement())
+                }
+            },
+            header: None,
+        })
+    }
+    pub fn create_frame_status_request(
+        store_id: Option<String>,
+        has_login_form: bool,
+        acknowledgement: Option<String>,
+    ) -> RequestEnum {
+        RequestEnum::FrameStatus(FrameStatusRequest {
+            store_id,
+            has_login_form,
+            acknowledgement: {
+                if acknowledgement.is_some() {
+                    acknowledgement
+                } else {
+                    Some(create_request_acknowledgement())
+                }
+            },
+            header: None,
+        })
+    }
+    pub fn create_clipboard_clear_request(
+        copied: CopiedDigest,
+        acknowledgement: Option<String>,
+    ) -> RequestEnum {
+        RequestEnum::ClipboardClear(ClipboardClearRequest {
+            store_id: None,
+            copied,
+            acknowledgement: {
+                if acknowledgement.is_some() {
+                    acknowledgement
+                } else {
+                    Some(create_request_acknowledgement())
+                }
+            },
+            header: None,
+        })
+    }
+    pub fn create_fill_request(
+        store_id: Option<String>,
+        account_id: String,
+        acknowledgement: Option<String>,
+    ) -> RequestEnum {
+        RequestEnum::Fill(FillRequest {
+            store_id,
+            account_id,
+            account: None,
+            acknowledgement: {
+                if acknowledgement.is_some() {
+                    acknowledgement
+                } else {
+                    Some(create_request_acknowledgement())
+                }
+            },
+            header: None,
+        })
+    }
+    pub fn create_command_request(
+        command: BrowserCommand,
+        acknowledgement: Option<String>,
+    ) -> RequestEnum {
+        RequestEnum::Command(CommandRequest {
+            store_id: None,
+            command,
+            acknowledgement: {
+                if acknowledgement.is_some() {
+                    acknowledgement
+                } else {
+                    Some(create_request_acknowledgement())
+                }
+            },
+            header: None,
+        })
+    }
+    pub fn create_context_menu_request(
+        store_id: Option<String>,
+        action: ContextMenuAction,
+        account: Option<Account>,
+        acknowledgement: Option<String>,
+    ) -> RequestEnum {
+        RequestEnum::ContextMenu(ContextMenuRequest {
+            store_id,
+            action,
+            account,
+            acknowledgement: {
+                if acknowledgement.is_some() {
+                    acknowledgement
+                } else {
+                    Some(create_request_acknowledgement())
+                }
+            },
+            header: None,
+        })
+    }
     pub fn get_type(&self) -> String {
         self.to_string()
     }
@@ -679,3 +1082,126 @@ pub enum Queryable {
     #[serde(rename = "username")]
     Username { path: String },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(id: &str, domain: &str) -> Account {
+        let mut account = Account::default();
+        account.id = id.to_owned();
+        account.domain = Some(domain.to_owned());
+        account.set_password(Some(format!("{}-password", id)));
+        account
+    }
+
+    fn session_event(event_type: SessionEventType) -> SessionEvent {
+        SessionEvent {
+            data: None,
+            event_type,
+            header: None,
+            resource: Some(vec![Resource::Account]),
+            is_global: true,
+            acknowledgement: None,
+            store_id_index: Some("store".to_owned()),
+        }
+    }
+
+    fn refreshed(accounts: Vec<Account>) -> SessionEvent {
+        session_event(SessionEventType::Refreshed(
+            FetchPayload {
+                accounts,
+                ..Default::default()
+            }
+            .into(),
+        ))
+    }
+
+    fn account_ids(session_event: &SessionEvent) -> Vec<String> {
+        match &session_event.event_type {
+            SessionEventType::Refreshed(fetched) => fetched
+                .accounts
+                .iter()
+                .map(|account| account.id.clone())
+                .collect(),
+            event_type => panic!("not a refresh: {:?}", event_type),
+        }
+    }
+
+    #[test]
+    fn cross_origin_frame_only_gets_its_own_accounts() {
+        let event = refreshed(vec![
+            account("bank", "bank.example"),
+            account("ads", "ads.example"),
+        ]);
+        let framed = event
+            .for_frame(Some("https://ads.example/frame.html"), &[])
+            .unwrap();
+        assert_eq!(account_ids(&framed), vec!["ads".to_owned()]);
+        let top = event
+            .for_frame(Some("https://login.bank.example/"), &[])
+            .unwrap();
+        assert_eq!(account_ids(&top), vec!["bank".to_owned()]);
+    }
+
+    #[test]
+    fn frame_gets_accounts_of_equivalent_domains() {
+        let event = refreshed(vec![account("shop", "shop.example")]);
+        let equivalent_domains = vec![vec!["shop.example".to_owned(), "shop.test".to_owned()]];
+        let framed = event
+            .for_frame(Some("https://shop.test/"), &equivalent_domains)
+            .unwrap();
+        assert_eq!(account_ids(&framed), vec!["shop".to_owned()]);
+    }
+
+    #[test]
+    fn frame_without_url_gets_no_accounts() {
+        let event = refreshed(vec![account("bank", "bank.example")]);
+        let framed = event.for_frame(None, &[]).unwrap();
+        assert!(account_ids(&framed).is_empty());
+    }
+
+    #[test]
+    fn creation_of_another_sites_account_is_not_sent() {
+        let event = session_event(SessionEventType::Create(
+            AccountPayload {
+                account: account("bank", "bank.example"),
+            }
+            .into(),
+        ));
+        assert!(event.for_frame(Some("https://ads.example/"), &[]).is_none());
+        assert_eq!(
+            event.for_frame(Some("https://bank.example/"), &[]),
+            Some(event.clone())
+        );
+    }
+
+    #[test]
+    fn update_of_another_sites_account_becomes_deletion() {
+        let event = session_event(SessionEventType::Update(
+            AccountPayload {
+                account: account("bank", "bank.example"),
+            }
+            .into(),
+        ));
+        let framed = event.for_frame(Some("https://ads.example/"), &[]).unwrap();
+        assert_eq!(
+            framed.event_type,
+            SessionEventType::Delete(
+                DeletePayload {
+                    id: "bank".to_owned()
+                }
+                .into()
+            )
+        );
+    }
+
+    #[test]
+    fn events_without_accounts_are_left_alone() {
+        let event = session_event(SessionEventType::NativeAppConnectionError);
+        assert_eq!(
+            event.for_frame(Some("https://ads.example/"), &[]),
+            Some(event.clone())
+        );
+    }
+}
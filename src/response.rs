@@ -8,6 +8,10 @@ use serde_variant::to_variant_name;
 use std::{collections::HashMap, fmt::Debug, path::PathBuf};
 use wasm_bindgen::JsValue;
 
+use crate::payload::{
+    AccountPayload, AuditPayload, DeletePayload, FailurePayload, FetchPayload,
+    GeneratedPasswordPayload, LoginStepPayload, PendingCredentialPayload, UpdatedFields, Versioned,
+};
 use crate::request::DataFieldType;
 pub use crate::{request::RequestEnum, types::Resource};
 
@@ -23,8 +27,12 @@ pub struct GetResponse {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CreateResponse {
     pub acknowledgement: Option<String>,
-    #[serde(flatten)]
-    pub data: HashMap<DataFieldType, Value>,
+    //set when the account was created
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<Versioned<AccountPayload>>,
+    //set when the request failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<Versioned<FailurePayload>>,
     pub store_id: String,
     pub status: Status,
     pub resource: Resource,
@@ -71,8 +79,12 @@ impl CreateStoreResponse {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct EditResponse {
     pub acknowledgement: Option<String>,
-    #[serde(flatten)]
-    pub data: HashMap<DataFieldType, Value>,
+    //set when the edit succeeded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_fields: Option<Versioned<UpdatedFields>>,
+    //set when the request failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<Versioned<FailurePayload>>,
     pub status: Status,
     pub resource: Resource,
     pub id: String,
@@ -95,8 +107,12 @@ pub struct FetchResponse {
     pub status: Status,
     pub store_id: String,
     pub resource: Resource,
-    #[serde(flatten)]
-    pub data: HashMap<DataFieldType, Value>,
+    //set when the accounts were fetched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fetched: Option<Versioned<FetchPayload>>,
+    //set when the request failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<Versioned<FailurePayload>>,
     pub meta: Option<Value>,
 }
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -127,11 +143,77 @@ pub struct DeleteResponse {
     // pub store_id: String,
     pub deleted_resource_id: String,
     pub acknowledgement: Option<String>,
-    #[serde(flatten)]
-    pub data: HashMap<DataFieldType, Value>,
+    //set when the account was deleted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted: Option<Versioned<DeletePayload>>,
+    //set when the request failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<Versioned<FailurePayload>>,
     pub status: Status,
 }
 #[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GeneratePasswordResponse {
+    pub store_id: Option<String>,
+    pub acknowledgement: Option<String>,
+    //set when a password was generated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generated: Option<Versioned<GeneratedPasswordPayload>>,
+    //set when the request failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<Versioned<FailurePayload>>,
+    pub status: Status,
+    pub meta: Option<Value>,
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuditResponse {
+    pub store_id: Option<String>,
+    pub acknowledgement: Option<String>,
+    //set when the store was audited
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audit: Option<Versioned<AuditPayload>>,
+    //set when the request failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<Versioned<FailurePayload>>,
+    pub status: Status,
+    pub meta: Option<Value>,
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LoginStepResponse {
+    pub store_id: Option<String>,
+    pub acknowledgement: Option<String>,
+    //set when a step was remembered or asked for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub login_step: Option<Versioned<LoginStepPayload>>,
+    //set when the request failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<Versioned<FailurePayload>>,
+    pub status: Status,
+    pub meta: Option<Value>,
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PendingCredentialResponse {
+    pub store_id: Option<String>,
+    pub acknowledgement: Option<String>,
+    //set when credentials were waiting for the tab
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential: Option<Versioned<PendingCredentialPayload>>,
+    //set when the request failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<Versioned<FailurePayload>>,
+    pub status: Status,
+    pub meta: Option<Value>,
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FillResponse {
+    pub store_id: Option<String>,
+    pub acknowledgement: Option<String>,
+    //set when the request failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<Versioned<FailurePayload>>,
+    pub status: Status,
+    pub meta: Option<Value>,
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GenericError {
     pub acknowledgement: Option<String>,
     #[serde(flatten)]
@@ -166,84 +248,93 @@ pub struct ErrorResponse {
     pub message: Option<String>,
     pub code: Option<ErrorCode>,
 }
-impl Into<JsValue> for ErrorResponse {
-    fn into(self) -> JsValue {
-        <JsValue as JsValueSerdeExt>::from_serde(&self).unwrap()
+impl From<ErrorResponse> for JsValue {
+    fn from(value: ErrorResponse) -> Self {
+        <JsValue as JsValueSerdeExt>::from_serde(&value).unwrap()
+    }
+}
+impl From<GenericError> for JsValue {
+    fn from(value: GenericError) -> Self {
+        <JsValue as JsValueSerdeExt>::from_serde(&value).unwrap()
     }
 }
-impl Into<JsValue> for GenericError {
-    fn into(self) -> JsValue {
-        <JsValue as JsValueSerdeExt>::from_serde(&self).unwrap()
+impl From<DeleteResponse> for JsValue {
+    fn from(value: DeleteResponse) -> Self {
+        <JsValue as JsValueSerdeExt>::from_serde(&value).unwrap()
     }
 }
-impl Into<JsValue> for DeleteResponse {
-    fn into(self) -> JsValue {
-        <JsValue as JsValueSerdeExt>::from_serde(&self).unwrap()
+impl From<InitResponse> for JsValue {
+    fn from(value: InitResponse) -> Self {
+        <JsValue as JsValueSerdeExt>::from_serde(&value).unwrap()
     }
 }
-impl Into<JsValue> for InitResponse {
-    fn into(self) -> JsValue {
-        <JsValue as JsValueSerdeExt>::from_serde(&self).unwrap()
+impl From<CreateResponse> for JsValue {
+    fn from(value: CreateResponse) -> Self {
+        <JsValue as JsValueSerdeExt>::from_serde(&value).unwrap()
     }
 }
-impl Into<JsValue> for CreateResponse {
-    fn into(self) -> JsValue {
-        <JsValue as JsValueSerdeExt>::from_serde(&self).unwrap()
+impl From<DeleteStoreResponse> for JsValue {
+    fn from(value: DeleteStoreResponse) -> Self {
+        <JsValue as JsValueSerdeExt>::from_serde(&value).unwrap()
     }
 }
-impl Into<JsValue> for DeleteStoreResponse {
-    fn into(self) -> JsValue {
-        <JsValue as JsValueSerdeExt>::from_serde(&self).unwrap()
+impl From<CreateStoreResponse> for JsValue {
+    fn from(value: CreateStoreResponse) -> Self {
+        <JsValue as JsValueSerdeExt>::from_serde(&value).unwrap()
     }
 }
-impl Into<JsValue> for CreateStoreResponse {
-    fn into(self) -> JsValue {
-        <JsValue as JsValueSerdeExt>::from_serde(&self).unwrap()
+impl From<LogoutResponse> for JsValue {
+    fn from(value: LogoutResponse) -> Self {
+        <JsValue as JsValueSerdeExt>::from_serde(&value).unwrap()
     }
 }
-impl Into<JsValue> for LogoutResponse {
-    fn into(self) -> JsValue {
-        <JsValue as JsValueSerdeExt>::from_serde(&self).unwrap()
+impl From<GetResponse> for JsValue {
+    fn from(value: GetResponse) -> Self {
+        <JsValue as JsValueSerdeExt>::from_serde(&value).unwrap()
     }
 }
-impl Into<JsValue> for GetResponse {
-    fn into(self) -> JsValue {
-        <JsValue as JsValueSerdeExt>::from_serde(&self).unwrap()
+impl From<SearchResponse> for JsValue {
+    fn from(value: SearchResponse) -> Self {
+        <JsValue as JsValueSerdeExt>::from_serde(&value).unwrap()
     }
 }
-impl Into<JsValue> for SearchResponse {
-    fn into(self) -> JsValue {
-        <JsValue as JsValueSerdeExt>::from_serde(&self).unwrap()
+impl From<FetchResponse> for JsValue {
+    fn from(value: FetchResponse) -> Self {
+        <JsValue as JsValueSerdeExt>::from_serde(&value).unwrap()
     }
 }
-impl Into<JsValue> for FetchResponse {
-    fn into(self) -> JsValue {
-        <JsValue as JsValueSerdeExt>::from_serde(&self).unwrap()
+impl From<GeneratePasswordResponse> for JsValue {
+    fn from(value: GeneratePasswordResponse) -> Self {
+        <JsValue as JsValueSerdeExt>::from_serde(&value).unwrap()
+    }
+}
+impl From<AuditResponse> for JsValue {
+    fn from(value: AuditResponse) -> Self {
+        <JsValue as JsValueSerdeExt>::from_serde(&value).unwrap()
+    }
+}
+impl From<LoginStepResponse> for JsValue {
+    fn from(value: LoginStepResponse) -> Self {
+        <JsValue as JsValueSerdeExt>::from_serde(&value).unwrap()
+    }
+}
+impl From<PendingCredentialResponse> for JsValue {
+    fn from(value: PendingCredentialResponse) -> Self {
+        <JsValue as JsValueSerdeExt>::from_serde(&value).unwrap()
+    }
+}
+impl From<FillResponse> for JsValue {
+    fn from(value: FillResponse) -> Self {
+        <JsValue as JsValueSerdeExt>::from_serde(&value).unwrap()
     }
 }
 impl ResponseEnumTrait for ErrorResponse {
     fn get_acknowledgement(&self) -> Option<String> {
-        return self.acknowledgement.clone();
+        self.acknowledgement.clone()
     }
     fn set_acknowledgement(&mut self, acknowledgement: Option<String>) {
         self.acknowledgement = acknowledgement;
     }
-    fn get_data(&self) -> HashMap<DataFieldType, Value> {
-        let mut data = HashMap::new();
-        if let Some(message) = &self.message {
-            data.insert(
-                DataFieldType::ErrorMessage,
-                serde_json::to_value(message).unwrap(),
-            );
-        }
-        if let Some(code) = &self.code {
-            data.insert(
-                DataFieldType::ErrorCode,
-                serde_json::to_value(code).unwrap(),
-            );
-        }
-        data.into()
-    }
     fn get_status(&self) -> Status {
         Status::Error
     }
@@ -254,9 +345,6 @@ macro_rules! response_enum_trait_impl {
     fn get_acknowledgement(&self) -> Option<String> {
         self.acknowledgement.clone()
     }
-    fn get_data(&self) -> HashMap<DataFieldType, Value>{
-        self.data.clone()
-    }
     fn set_acknowledgement(&mut self, acknowledgement: Option<String>){
         self.acknowledgement = acknowledgement;
     }
@@ -280,6 +368,11 @@ response_enum_trait_impl!(InitResponse);
 response_enum_trait_impl!(DeleteResponse);
 response_enum_trait_impl!(EditResponse);
 response_enum_trait_impl!(DeleteStoreResponse);
+response_enum_trait_impl!(GeneratePasswordResponse);
+response_enum_trait_impl!(AuditResponse);
+response_enum_trait_impl!(LoginStepResponse);
+response_enum_trait_impl!(PendingCredentialResponse);
+response_enum_trait_impl!(FillResponse);
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[enum_dispatch(ResponseEnumTrait,Into<JsValue>)]
@@ -308,6 +401,16 @@ pub enum ResponseEnum {
     DeleteResponse(DeleteResponse),
     #[serde(rename = "delete_store_response")]
     DeleteStoreResponse(DeleteStoreResponse),
+    #[serde(rename = "generate_password_response")]
+    GeneratePasswordResponse(GeneratePasswordResponse),
+    #[serde(rename = "audit_response")]
+    AuditResponse(AuditResponse),
+    #[serde(rename = "login_step_response")]
+    LoginStepResponse(LoginStepResponse),
+    #[serde(rename = "pending_credential_response")]
+    PendingCredentialResponse(PendingCredentialResponse),
+    #[serde(rename = "fill_response")]
+    FillResponse(FillResponse),
     #[serde(rename = "generic_error")]
     GenericError(GenericError),
 }
@@ -322,7 +425,6 @@ pub enum MessageEnum {
 #[enum_dispatch]
 pub trait ResponseEnumTrait: Debug {
     fn get_acknowledgement(&self) -> Option<String>;
-    fn get_data(&self) -> HashMap<DataFieldType, Value>;
     fn set_acknowledgement(&mut self, acknowledgement: Option<String>);
     fn get_status(&self) -> Status;
 }
@@ -350,6 +452,7 @@ pub enum ErrorCode {
     Generic = 5,
     LoginFailed = 6,
     NativeAppConnectionError = 7,
+    PolicyViolation = 8,
 }
 impl fmt::Display for ResponseEnum {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -0,0 +1,192 @@
+use std::cell::RefCell;
+
+use js_sys::{Array, Function, Object, Promise, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::{future_to_promise, JsFuture};
+use web_sys::{Crypto, CryptoKey, IdbDatabase, IdbFactory, IdbRequest, IdbTransactionMode};
+
+use crate::js_binding::extension_api::chrome;
+use crate::store::StorageArea;
+use crate::util::create_request_acknowledgement;
+
+//secrets kept in session storage are encrypted with an aes-gcm key that is generated once per
+//browser session. the key is not extractable, so only this extension can use it, and it is kept
+//in indexeddb because it has to outlive the service worker but cannot be serialized
+const KEY_DATABASE: &str = "rpass-session";
+const KEY_OBJECT_STORE: &str = "keys";
+//session storage is emptied when the browser closes, so a missing id means a new session
+const SESSION_KEY_ID: &str = "session_key_id";
+const IV_LENGTH: usize = 12;
+
+thread_local! {
+    //shared by concurrent callers, so a new session gets a single key
+    static SESSION_KEY: RefCell<Option<Promise>> = const { RefCell::new(None) };
+}
+
+pub(crate) fn crypto() -> Result<Crypto, JsValue> {
+    Reflect::get(&js_sys::global(), &"crypto".into())?.dyn_into::<Crypto>()
+}
+
+//resolves with the result of an indexeddb request
+async fn idb_result(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    let promise = Promise::new(&mut |resolve: Function, reject: Function| {
+        let succeeded = request.clone();
+        request.set_onsuccess(Some(
+            Closure::once_into_js(move |_: JsValue| {
+                let _ = resolve.call1(&JsValue::NULL, &succeeded.result().unwrap_or_default());
+            })
+            .unchecked_ref(),
+        ));
+        let failed = request.clone();
+        request.set_onerror(Some(
+            Closure::once_into_js(move |_: JsValue| {
+                let error = failed
+                    .error()
+                    .ok()
+                    .flatten()
+                    .map(JsValue::from)
+                    .unwrap_or_default();
+                let _ = reject.call1(&JsValue::NULL, &error);
+            })
+            .unchecked_ref(),
+        ));
+    });
+    JsFuture::from(promise).await
+}
+
+async fn open_key_database() -> Result<IdbDatabase, JsValue> {
+    let factory = Reflect::get(&js_sys::global(), &"indexedDB".into())?.dyn_into::<IdbFactory>()?;
+    let request = factory.open_with_u32(KEY_DATABASE, 1)?;
+    let upgraded = request.clone();
+    request.set_onupgradeneeded(Some(
+        Closure::once_into_js(move |_: JsValue| {
+            if let Ok(database) = upgraded
+                .result()
+                .and_then(|database| database.dyn_into::<IdbDatabase>())
+            {
+                let _ = database.create_object_store(KEY_OBJECT_STORE);
+            }
+        })
+        .unchecked_ref(),
+    ));
+    idb_result(&request).await?.dyn_into::<IdbDatabase>()
+}
+
+async fn generate_key() -> Result<CryptoKey, JsValue> {
+    let algorithm = Object::new();
+    Reflect::set(&algorithm, &"name".into(), &"AES-GCM".into())?;
+    Reflect::set(&algorithm, &"length".into(), &256.into())?;
+    let usages = Array::of2(&"encrypt".into(), &"decrypt".into());
+    let key = JsFuture::from(
+        crypto()?
+            .subtle()
+            .generate_key_with_object(&algorithm, false, &usages)?,
+    )
+    .await?;
+    key.dyn_into::<CryptoKey>()
+}
+
+async fn load_or_create_key() -> Result<CryptoKey, JsValue> {
+    let database = open_key_database().await?;
+    let key_id = chrome
+        .storage()
+        .session()
+        .get_item(SESSION_KEY_ID, StorageArea::Session)
+        .await
+        .ok()
+        .and_then(|key_id| key_id.as_string());
+    if let Some(key_id) = key_id {
+        let object_store = database
+            .transaction_with_str_and_mode(KEY_OBJECT_STORE, IdbTransactionMode::Readonly)?
+            .object_store(KEY_OBJECT_STORE)?;
+        let key = idb_result(&object_store.get(&key_id.into())?).await?;
+        if let Ok(key) = key.dyn_into::<CryptoKey>() {
+            return Ok(key);
+        }
+    }
+    let key = generate_key().await?;
+    let key_id = create_request_acknowledgement();
+    let object_store = database
+        .transaction_with_str_and_mode(KEY_OBJECT_STORE, IdbTransactionMode::Readwrite)?
+        .object_store(KEY_OBJECT_STORE)?;
+    //keys of earlier sessions can't decrypt anything that is still stored
+    idb_result(&object_store.clear()?).await?;
+    idb_result(&object_store.put_with_key(&key, &key_id.clone().into())?).await?;
+    chrome
+        .storage()
+        .session()
+        .set_string_item(SESSION_KEY_ID.to_owned(), key_id, StorageArea::Session)
+        .await;
+    Ok(key)
+}
+
+async fn session_key() -> Result<CryptoKey, JsValue> {
+    let promise = SESSION_KEY.with(|cached| {
+        cached
+            .borrow_mut()
+            .get_or_insert_with(|| {
+                future_to_promise(async { load_or_create_key().await.map(JsValue::from) })
+            })
+            .clone()
+    });
+    match JsFuture::from(promise).await {
+        Ok(key) => key.dyn_into::<CryptoKey>(),
+        Err(error) => {
+            SESSION_KEY.with(|cached| *cached.borrow_mut() = None);
+            Err(error)
+        }
+    }
+}
+
+fn aes_gcm(iv: &[u8]) -> Result<Object, JsValue> {
+    let algorithm = Object::new();
+    Reflect::set(&algorithm, &"name".into(), &"AES-GCM".into())?;
+    Reflect::set(&algorithm, &"iv".into(), &Uint8Array::from(iv))?;
+    Ok(algorithm)
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|start| u8::from_str_radix(hex.get(start..start + 2)?, 16).ok())
+        .collect()
+}
+
+//encrypts with the session key. the iv is prepended to the ciphertext, both hex encoded
+pub async fn encrypt(plaintext: &str) -> Result<String, JsValue> {
+    let key = session_key().await?;
+    let mut iv = [0u8; IV_LENGTH];
+    getrandom::getrandom(&mut iv).map_err(|error| JsValue::from_str(&error.to_string()))?;
+    let ciphertext = JsFuture::from(crypto()?.subtle().encrypt_with_object_and_buffer_source(
+        &aes_gcm(&iv)?,
+        &key,
+        &Uint8Array::from(plaintext.as_bytes()),
+    )?)
+    .await?;
+    Ok(to_hex(&iv) + &to_hex(&Uint8Array::new(&ciphertext).to_vec()))
+}
+
+//fails when the text was encrypted in an earlier browser session
+pub async fn decrypt(encrypted: &str) -> Result<String, JsValue> {
+    let bytes = from_hex(encrypted)
+        .filter(|bytes| bytes.len() > IV_LENGTH)
+        .ok_or_else(|| JsValue::from_str("malformed ciphertext"))?;
+    let (iv, ciphertext) = bytes.split_at(IV_LENGTH);
+    let key = session_key().await?;
+    let plaintext = JsFuture::from(crypto()?.subtle().decrypt_with_object_and_buffer_source(
+        &aes_gcm(iv)?,
+        &key,
+        &Uint8Array::from(ciphertext),
+    )?)
+    .await?;
+    String::from_utf8(Uint8Array::new(&plaintext).to_vec())
+        .map_err(|error| JsValue::from_str(&error.to_string()))
+}
@@ -1,10 +1,15 @@
 use std::{collections::HashMap, future::Future, pin::Pin, sync::Mutex};
 
-use crate::{js_binding::extension_api::Port, response::ResponseEnum};
+use crate::{
+    js_binding::extension_api::{chrome, Port},
+    response::ResponseEnum,
+};
+use gloo_utils::format::JsValueSerdeExt;
 use lazy_static::lazy_static;
 use serde_json::Value;
 pub type AsyncCallback =
     Box<dyn Send + FnOnce(ResponseEnum, Port) -> Pin<Box<dyn Future<Output = ()>>>>;
+pub type NativeCallback = Box<dyn FnOnce(&[u8], Port) -> Result<(), String> + Send>;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum StorageArea {
@@ -13,7 +18,7 @@ pub enum StorageArea {
     Session,
 }
 lazy_static! {
-    pub static ref MESSAGE_ACKNOWLEDGEMENTS_NATIVE: Mutex<HashMap<String, Vec<Box<dyn FnOnce(&[u8], Port) -> Result<(), String> + Send>>>> =
+    pub static ref MESSAGE_ACKNOWLEDGEMENTS_NATIVE: Mutex<HashMap<String, Vec<NativeCallback>>> =
         Mutex::new(HashMap::new());
 }
 // lazy_static! {
@@ -34,3 +39,64 @@ lazy_static! {
         Mutex::new(map)
     };
 }
+
+//sites the user never wants to be asked about saving credentials for
+pub const NEVER_SAVE_DOMAINS_KEY: &str = "never_save_domains";
+pub async fn load_never_save_domains() -> Vec<String> {
+    chrome
+        .storage()
+        .local()
+        .get_item(NEVER_SAVE_DOMAINS_KEY, StorageArea::Local)
+        .await
+        .ok()
+        .and_then(|value| {
+            <wasm_bindgen::JsValue as JsValueSerdeExt>::into_serde::<String>(&value).ok()
+        })
+        .and_then(|json_string| serde_json::from_str::<Vec<String>>(&json_string).ok())
+        .unwrap_or_default()
+}
+pub async fn set_never_save_domains(domains: &[String]) {
+    chrome
+        .storage()
+        .local()
+        .set_string_item(
+            NEVER_SAVE_DOMAINS_KEY.to_owned(),
+            serde_json::to_string(domains).unwrap(),
+            StorageArea::Local,
+        )
+        .await;
+}
+pub async fn add_never_save_domain(domain: String) {
+    let mut domains = load_never_save_domains().await;
+    if !domains.contains(&domain) {
+        domains.push(domain);
+        set_never_save_domains(&domains).await;
+    }
+}
+
+//groups of domains the user considers the same site, e.g. a service and its regional domains
+pub const EQUIVALENT_DOMAINS_KEY: &str = "equivalent_domains";
+pub async fn load_equivalent_domains() -> Vec<Vec<String>> {
+    chrome
+        .storage()
+        .local()
+        .get_item(EQUIVALENT_DOMAINS_KEY, StorageArea::Local)
+        .await
+        .ok()
+        .and_then(|value| {
+            <wasm_bindgen::JsValue as JsValueSerdeExt>::into_serde::<String>(&value).ok()
+        })
+        .and_then(|json_string| serde_json::from_str::<Vec<Vec<String>>>(&json_string).ok())
+        .unwrap_or_default()
+}
+pub async fn set_equivalent_domains(groups: &[Vec<String>]) {
+    chrome
+        .storage()
+        .local()
+        .set_string_item(
+            EQUIVALENT_DOMAINS_KEY.to_owned(),
+            serde_json::to_string(groups).unwrap(),
+            StorageArea::Local,
+        )
+        .await;
+}
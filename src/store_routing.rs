@@ -0,0 +1,79 @@
+use gloo_utils::format::JsValueSerdeExt;
+use serde::{Deserialize, Serialize};
+
+use crate::js_binding::extension_api::chrome;
+use crate::store::StorageArea;
+
+//rules picking the stores used on a site. kept in sync storage, so they follow the user to their
+//other browsers
+pub const STORE_ROUTES_KEY: &str = "store_routes";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct StoreRoute {
+    //a domain, which covers its subdomains too
+    pub domain: String,
+    //stores whose accounts are offered first on the site, in this order
+    #[serde(default)]
+    pub store_ids: Vec<String>,
+    //store new credentials of the site are saved to
+    #[serde(default)]
+    pub save_store_id: Option<String>,
+}
+
+impl StoreRoute {
+    pub fn covers(&self, host: &str) -> bool {
+        let domain = self.domain.trim().trim_end_matches('.').to_lowercase();
+        let host = host.trim_end_matches('.').to_lowercase();
+        !domain.is_empty()
+            && (host == domain
+                || host
+                    .strip_suffix(&domain)
+                    .is_some_and(|subdomain| subdomain.ends_with('.')))
+    }
+    //position of a store in the rule, stores the rule leaves out come after the listed ones
+    pub fn rank(&self, store_id: Option<&String>) -> usize {
+        store_id
+            .and_then(|store_id| self.store_ids.iter().position(|id| id == store_id))
+            .unwrap_or(self.store_ids.len())
+    }
+    //the store to save to among the given ones, the first listed store that is there otherwise
+    pub fn save_store<'a>(&self, store_ids: &'a [String]) -> Option<&'a String> {
+        self.save_store_id
+            .iter()
+            .chain(self.store_ids.iter())
+            .find_map(|wanted| store_ids.iter().find(|store_id| *store_id == wanted))
+    }
+}
+
+//the rule of the most specific domain covering the host
+pub fn route_for<'a>(routes: &'a [StoreRoute], host: &str) -> Option<&'a StoreRoute> {
+    routes
+        .iter()
+        .filter(|route| route.covers(host))
+        .max_by_key(|route| route.domain.trim().len())
+}
+
+pub async fn load_store_routes() -> Vec<StoreRoute> {
+    chrome
+        .storage()
+        .sync()
+        .get_item(STORE_ROUTES_KEY, StorageArea::Sync)
+        .await
+        .ok()
+        .and_then(|value| {
+            <wasm_bindgen::JsValue as JsValueSerdeExt>::into_serde::<String>(&value).ok()
+        })
+        .and_then(|json_string| serde_json::from_str::<Vec<StoreRoute>>(&json_string).ok())
+        .unwrap_or_default()
+}
+pub async fn set_store_routes(routes: &[StoreRoute]) {
+    chrome
+        .storage()
+        .sync()
+        .set_string_item(
+            STORE_ROUTES_KEY.to_owned(),
+            serde_json::to_string(routes).unwrap(),
+            StorageArea::Sync,
+        )
+        .await;
+}
@@ -8,6 +8,11 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::request::DataFieldType;
+use crate::url_match::AccountUrl;
+//TODO don't use magic string for field names
+//custom field holding the otpauth:// uri (or bare base32 secret) of an account
+pub const TOTP_FIELD: &str = "otpauth";
+
 #[derive(Debug, Serialize, Deserialize, Default, PartialEq, Clone)]
 pub struct Account {
     pub username: String,
@@ -18,16 +23,20 @@ pub struct Account {
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
     pub note: Option<String>,
+    //whether a one-time code can be requested for the account. the secret itself never leaves
+    //the native app
+    #[serde(default)]
+    pub has_totp: bool,
+    //urls the account is used on besides its domain. entries saved before there were several
+    //urls have none
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub urls: Vec<AccountUrl>,
     #[serde(flatten)]
     pub custom_fields: Option<HashMap<String, Value>>,
 }
 impl Account {
     pub fn get_password(&self) -> Option<Secret<String>> {
-        if let Some(password) = &self.password {
-            Some(Secret::new(password.clone()))
-        } else {
-            None
-        }
+        self.password.clone().map(Secret::new)
     }
     pub fn set_password(&mut self, password: Option<String>) {
         self.password = password;
@@ -46,6 +55,8 @@ pub enum Resource {
     Auth,
     #[serde(rename = "store")]
     Store,
+    #[serde(rename = "totp")]
+    Totp,
 }
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 pub enum StorageStatus {
@@ -0,0 +1,314 @@
+use js_sys::RegExp;
+use once_cell::sync::Lazy;
+use publicsuffix::{List, Psl};
+use serde::{Deserialize, Serialize};
+use url::{Host, Url};
+use wasm_bindgen::prelude::*;
+
+use crate::types::Account;
+
+//custom field of an account holding how its domain is matched against page urls
+pub const URL_MATCH_FIELD: &str = "url_match";
+//custom field of an account holding its other urls, each with a match mode
+pub const URLS_FIELD: &str = "urls";
+
+//the public suffix list, private section included, so subdomains of hosting platforms like
+//`github.io` or `elasticbeanstalk.com` count as sites of their own
+static PUBLIC_SUFFIX_LIST: Lazy<List> = Lazy::new(|| {
+    include_str!("public_suffix_list.dat")
+        .parse()
+        .expect("invalid public suffix list")
+});
+
+//the part of a host that was registered, e.g. `example.co.uk` for `accounts.example.co.uk`.
+//ip addresses, single-label hosts and public suffixes themselves are returned as they are
+pub fn registrable_domain(host: &str) -> String {
+    let host = host.trim_end_matches('.').to_lowercase();
+    if !matches!(Host::parse(&host), Ok(Host::Domain(_))) {
+        return host;
+    }
+    PUBLIC_SUFFIX_LIST
+        .domain(host.as_bytes())
+        .and_then(|domain| std::str::from_utf8(domain.as_bytes()).ok())
+        .map(|domain| domain.to_owned())
+        .unwrap_or(host)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(tag = "mode", content = "pattern", rename_all = "snake_case")]
+pub enum MatchMode {
+    //same registrable domain, or one of the same equivalent-domain group
+    #[default]
+    BaseDomain,
+    Host,
+    HostPort,
+    StartsWith(String),
+    Regex(String),
+    Never,
+}
+impl MatchMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MatchMode::BaseDomain => "base_domain",
+            MatchMode::Host => "host",
+            MatchMode::HostPort => "host_port",
+            MatchMode::StartsWith(_) => "starts_with",
+            MatchMode::Regex(_) => "regex",
+            MatchMode::Never => "never",
+        }
+    }
+    pub fn from_name(name: &str, pattern: String) -> Option<MatchMode> {
+        match name {
+            "base_domain" => Some(MatchMode::BaseDomain),
+            "host" => Some(MatchMode::Host),
+            "host_port" => Some(MatchMode::HostPort),
+            "starts_with" => Some(MatchMode::StartsWith(pattern)),
+            "regex" => Some(MatchMode::Regex(pattern)),
+            "never" => Some(MatchMode::Never),
+            _ => None,
+        }
+    }
+    pub fn pattern(&self) -> Option<&String> {
+        match self {
+            MatchMode::StartsWith(pattern) | MatchMode::Regex(pattern) => Some(pattern),
+            _ => None,
+        }
+    }
+}
+
+//an address an account is used on, besides the account's own domain
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct AccountUrl {
+    pub url: String,
+    #[serde(default, rename = "match")]
+    pub match_mode: MatchMode,
+}
+
+impl Account {
+    pub fn match_mode(&self) -> MatchMode {
+        self.custom_fields
+            .as_ref()
+            .and_then(|fields| fields.get(URL_MATCH_FIELD))
+            .and_then(|value| serde_json::from_value::<MatchMode>(value.clone()).ok())
+            .unwrap_or_default()
+    }
+    //the account's domain with its match mode, followed by the other urls of the account
+    pub fn all_urls(&self) -> Vec<AccountUrl> {
+        self.domain
+            .iter()
+            .map(|domain| AccountUrl {
+                url: domain.clone(),
+                match_mode: self.match_mode(),
+            })
+            .chain(self.urls.iter().cloned())
+            .filter(|account_url| !account_url.url.trim().is_empty())
+            .collect()
+    }
+}
+
+#[wasm_bindgen]
+extern "C" {
+    //called without `new` so an invalid pattern is an error instead of an uncaught exception
+    #[wasm_bindgen(catch, js_name = RegExp)]
+    fn try_regexp(pattern: &str) -> Result<RegExp, JsValue>;
+}
+
+//a saved url may be a bare host, a host with a port or a full url
+pub fn parse_saved_url(saved: &str) -> Option<Url> {
+    let saved = saved.trim();
+    if saved.contains("://") {
+        Url::parse(saved).ok()
+    } else {
+        Url::parse(&format!("https://{}", saved)).ok()
+    }
+}
+
+fn same_site(saved_host: &str, page_host: &str, equivalent_domains: &[Vec<String>]) -> bool {
+    let saved_base = registrable_domain(saved_host);
+    let page_base = registrable_domain(page_host);
+    saved_base == page_base
+        || equivalent_domains.iter().any(|group| {
+            let group = group
+                .iter()
+                .map(|domain| registrable_domain(domain))
+                .collect::<Vec<String>>();
+            group.contains(&saved_base) && group.contains(&page_base)
+        })
+}
+
+fn starts_with(saved_url: Option<&Url>, pattern: &str, url: &str) -> bool {
+    let prefix = match saved_url {
+        Some(saved_url) if pattern.trim().is_empty() => saved_url.to_string(),
+        _ => pattern.trim().to_owned(),
+    };
+    !prefix.is_empty() && url.starts_with(&prefix)
+}
+
+//whether the page at `url` is covered by a saved url. a starts-with or regex mode without a
+//pattern matches urls starting with the saved url, which is never taken as a regex itself
+pub fn url_matches(
+    account_url: &AccountUrl,
+    url: &str,
+    equivalent_domains: &[Vec<String>],
+) -> bool {
+    let saved = account_url.url.trim();
+    let Ok(page_url) = Url::parse(url) else {
+        return false;
+    };
+    let saved_url = parse_saved_url(saved);
+    let saved_host = saved_url
+        .as_ref()
+        .and_then(|saved_url| saved_url.host_str());
+    let page_host = page_url.host_str();
+    match &account_url.match_mode {
+        MatchMode::BaseDomain => match (saved_host, page_host) {
+            (Some(saved_host), Some(page_host)) => {
+                same_site(saved_host, page_host, equivalent_domains)
+            }
+            _ => false,
+        },
+        MatchMode::Host => saved_host.is_some() && saved_host == page_host,
+        MatchMode::HostPort => {
+            saved_host.is_some()
+                && saved_host == page_host
+                && saved_url.as_ref().and_then(|saved_url| saved_url.port()) == page_url.port()
+        }
+        MatchMode::StartsWith(pattern) => starts_with(saved_url.as_ref(), pattern, url),
+        MatchMode::Regex(pattern) if pattern.trim().is_empty() => {
+            starts_with(saved_url.as_ref(), "", url)
+        }
+        MatchMode::Regex(pattern) => match try_regexp(pattern) {
+            Ok(regexp) => regexp.test(url),
+            Err(_) => {
+                log::warn!("invalid url pattern: {}", pattern);
+                false
+            }
+        },
+        MatchMode::Never => false,
+    }
+}
+
+//whether an account belongs on the page at `url`, by any of its urls
+pub fn account_matches_url(
+    account: &Account,
+    url: &str,
+    equivalent_domains: &[Vec<String>],
+) -> bool {
+    account
+        .all_urls()
+        .iter()
+        .any(|account_url| url_matches(account_url, url, equivalent_domains))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account_url(url: &str, match_mode: MatchMode) -> AccountUrl {
+        AccountUrl {
+            url: url.to_owned(),
+            match_mode,
+        }
+    }
+
+    #[test]
+    fn registrable_domain_of_subdomains() {
+        assert_eq!(registrable_domain("gist.github.com"), "github.com");
+        assert_eq!(registrable_domain("github.com"), "github.com");
+        assert_eq!(
+            registrable_domain("accounts.example.co.uk"),
+            "example.co.uk"
+        );
+        assert_eq!(registrable_domain("example.co.uk"), "example.co.uk");
+        assert_eq!(registrable_domain("WWW.Example.COM."), "example.com");
+    }
+
+    #[test]
+    fn registrable_domain_of_hosting_platforms() {
+        assert_eq!(
+            registrable_domain("victim.elasticbeanstalk.com"),
+            "victim.elasticbeanstalk.com"
+        );
+        assert_eq!(
+            registrable_domain("bucket.s3.amazonaws.com"),
+            "bucket.s3.amazonaws.com"
+        );
+        assert_eq!(
+            registrable_domain("site.azurestaticapps.net"),
+            "site.azurestaticapps.net"
+        );
+        assert_eq!(registrable_domain("app.glitch.me"), "app.glitch.me");
+        assert_eq!(registrable_domain("user.github.io"), "user.github.io");
+        assert_eq!(registrable_domain("shop.example.com.au"), "example.com.au");
+    }
+
+    #[test]
+    fn registrable_domain_of_other_hosts() {
+        assert_eq!(registrable_domain("localhost"), "localhost");
+        assert_eq!(registrable_domain("192.168.1.1"), "192.168.1.1");
+        assert_eq!(registrable_domain("github.io"), "github.io");
+    }
+
+    #[test]
+    fn base_domain_matches_subdomains_only_of_the_same_site() {
+        let github = account_url("github.com", MatchMode::BaseDomain);
+        assert!(url_matches(&github, "https://gist.github.com/", &[]));
+        assert!(!url_matches(&github, "https://github.com.evil.test/", &[]));
+        let victim = account_url("victim.elasticbeanstalk.com", MatchMode::BaseDomain);
+        assert!(!url_matches(
+            &victim,
+            "https://attacker.elasticbeanstalk.com/",
+            &[]
+        ));
+        let uk = account_url("example.co.uk", MatchMode::BaseDomain);
+        assert!(url_matches(
+            &uk,
+            "https://accounts.example.co.uk/login",
+            &[]
+        ));
+        assert!(!url_matches(&uk, "https://other.co.uk/login", &[]));
+    }
+
+    #[test]
+    fn base_domain_matches_equivalent_domains() {
+        let equivalent_domains = vec![vec!["google.com".to_owned(), "youtube.com".to_owned()]];
+        let google = account_url("accounts.google.com", MatchMode::BaseDomain);
+        assert!(url_matches(
+            &google,
+            "https://www.youtube.com/",
+            &equivalent_domains
+        ));
+        assert!(!url_matches(&google, "https://www.youtube.com/", &[]));
+    }
+
+    #[test]
+    fn host_modes() {
+        let host = account_url("example.com:8443", MatchMode::Host);
+        assert!(url_matches(&host, "https://example.com/", &[]));
+        assert!(!url_matches(&host, "https://www.example.com/", &[]));
+        let host_port = account_url("example.com:8443", MatchMode::HostPort);
+        assert!(url_matches(&host_port, "https://example.com:8443/", &[]));
+        assert!(!url_matches(&host_port, "https://example.com/", &[]));
+    }
+
+    #[test]
+    fn regex_without_pattern_is_not_taken_as_a_regex() {
+        let github = account_url("github.com", MatchMode::Regex(String::new()));
+        assert!(url_matches(&github, "https://github.com/login", &[]));
+        assert!(!url_matches(&github, "https://evil.test/?github.com", &[]));
+        assert!(!url_matches(&github, "https://githubXcom.evil/", &[]));
+        assert!(!url_matches(&github, "https://github.com.evil/", &[]));
+    }
+
+    #[test]
+    fn starts_with_and_never() {
+        let login = account_url(
+            "example.com",
+            MatchMode::StartsWith("https://example.com/login".to_owned()),
+        );
+        assert!(url_matches(&login, "https://example.com/login?next=/", &[]));
+        assert!(!url_matches(&login, "https://example.com/signup", &[]));
+        let never = account_url("example.com", MatchMode::Never);
+        assert!(!url_matches(&never, "https://example.com/", &[]));
+    }
+}
@@ -14,11 +14,9 @@ pub fn create_request_acknowledgement() -> String {
         .collect()
 }
 
-pub fn get_domain_name(addr: &String) -> String {
-    let url = Url::parse(&addr).unwrap();
-    let domain = url.domain().unwrap();
-    let domain_name = domain.to_string();
-    domain_name
+pub fn get_domain_name(addr: &str) -> String {
+    let url = Url::parse(addr).unwrap();
+    url.domain().unwrap().to_string()
 }
 
 pub fn json_string_to_map(
@@ -113,7 +111,7 @@ impl JsonValueExt for serde_json::Map<String, Value> {
     }
 }
 
-pub fn string_filter<'a, 'b>(options: Vec<&'a str>, query: &'b str) -> Vec<&'a str> {
+pub fn string_filter<'a>(options: Vec<&'a str>, query: &str) -> Vec<&'a str> {
     options
         .into_iter()
         .filter(|option| option.to_lowercase().contains(&query.to_lowercase()))